@@ -165,8 +165,10 @@ bindgen \
     "${BINDGEN_COMMON_ARGUMENTS[@]}" \
     --allowlist-function "SCNetwork.*" \
     --allowlist-function "SCBondInterface.*" \
+    --allowlist-function "SCBondStatus.*" \
     --allowlist-function "SCVLANInterface.*" \
-    --allowlist-var "kSC(NetworkInterface|NetworkProtocol|BondStatus).*" \
+    --allowlist-type "SCBondStatusRef" \
+    --allowlist-var "kSC(NetworkConnection|NetworkInterface|NetworkProtocol|BondStatus).*" \
     --blocklist-type "SCNetworkReachability.*" \
     --blocklist-function "SCNetworkReachability.*" \
     --blocklist-type "dispatch_queue_[ts]" \
@@ -239,6 +241,7 @@ echo "Generating bindings for $PREFERENCES_HEADER_PATH"
 bindgen \
     "${BINDGEN_COMMON_ARGUMENTS[@]}" \
     --allowlist-function "SCPreferences.*" \
+    --allowlist-var "kSCPreferencesNotification.*" \
     --blocklist-type "(__)?CF.*" \
     --blocklist-type "Boolean" \
     --blocklist-type "dispatch_queue_[ts]" \
@@ -51,6 +51,28 @@ pub struct SCNetworkConnectionContext {
         Option<unsafe extern "C" fn(info: *const ::core::ffi::c_void) -> CFStringRef>,
 }
 pub type SCNetworkConnectionStatus = i32;
+pub const kSCNetworkConnectionInvalid: _bindgen_ty_67 = -1;
+pub const kSCNetworkConnectionDisconnected: _bindgen_ty_67 = 0;
+pub const kSCNetworkConnectionConnecting: _bindgen_ty_67 = 1;
+pub const kSCNetworkConnectionConnected: _bindgen_ty_67 = 2;
+pub const kSCNetworkConnectionDisconnecting: _bindgen_ty_67 = 3;
+pub type _bindgen_ty_67 = ::core::ffi::c_int;
+pub type SCNetworkConnectionPPPStatus = i32;
+pub const kSCNetworkConnectionPPPDisconnected: _bindgen_ty_68 = 0;
+pub const kSCNetworkConnectionPPPInitializing: _bindgen_ty_68 = 1;
+pub const kSCNetworkConnectionPPPConnectingLink: _bindgen_ty_68 = 2;
+pub const kSCNetworkConnectionPPPDialOnTraffic: _bindgen_ty_68 = 3;
+pub const kSCNetworkConnectionPPPNegotiatingLink: _bindgen_ty_68 = 4;
+pub const kSCNetworkConnectionPPPAuthenticating: _bindgen_ty_68 = 5;
+pub const kSCNetworkConnectionPPPWaitingForCallBack: _bindgen_ty_68 = 6;
+pub const kSCNetworkConnectionPPPNegotiatingNetwork: _bindgen_ty_68 = 7;
+pub const kSCNetworkConnectionPPPConnected: _bindgen_ty_68 = 8;
+pub const kSCNetworkConnectionPPPTerminating: _bindgen_ty_68 = 9;
+pub const kSCNetworkConnectionPPPDisconnectingLink: _bindgen_ty_68 = 10;
+pub const kSCNetworkConnectionPPPHoldingLinkOff: _bindgen_ty_68 = 11;
+pub const kSCNetworkConnectionPPPSuspended: _bindgen_ty_68 = 12;
+pub const kSCNetworkConnectionPPPWaitingForRedial: _bindgen_ty_68 = 13;
+pub type _bindgen_ty_68 = ::core::ffi::c_int;
 pub type SCNetworkConnectionCallBack = Option<
     unsafe extern "C" fn(
         connection: SCNetworkConnectionRef,
@@ -302,6 +324,15 @@ extern "C" {
 
     pub fn SCBondInterfaceCopyStatus(bond: SCBondInterfaceRef) -> SCBondStatusRef;
 
+    pub fn SCBondStatusGetTypeID() -> CFTypeID;
+
+    pub fn SCBondStatusGetMemberInterfaces(bondStatus: SCBondStatusRef) -> CFArrayRef;
+
+    pub fn SCBondStatusGetInterfaceStatus(
+        bondStatus: SCBondStatusRef,
+        interface: SCNetworkInterfaceRef,
+    ) -> CFDictionaryRef;
+
     pub fn SCVLANInterfaceCopyAll(prefs: SCPreferencesRef) -> CFArrayRef;
 
     pub fn SCVLANInterfaceCopyAvailablePhysicalInterfaces() -> CFArrayRef;
@@ -1,8 +1,9 @@
 use core_foundation_sys::array::CFArrayRef;
 use core_foundation_sys::base::Boolean;
 use core_foundation_sys::dictionary::CFDictionaryRef;
+use core_foundation_sys::number::CFNumberRef;
 use core_foundation_sys::string::CFStringRef;
-use crate::network_configuration::SCNetworkInterfaceRef;
+use crate::network_configuration::{SCBondInterfaceRef, SCNetworkInterfaceRef};
 use crate::preferences::SCPreferencesRef;
 
 pub type SCBridgeInterfaceRef = SCNetworkInterfaceRef;
@@ -19,6 +20,12 @@ extern "C" {
     pub static SCBridgeInterfaceRef: SCNetworkInterfaceRef;
 }
 
+extern "C" {
+    pub fn SCBondInterfaceGetMode(bond: SCBondInterfaceRef) -> CFNumberRef;
+
+    pub fn SCBondInterfaceSetMode(bond: SCBondInterfaceRef, mode: CFNumberRef) -> Boolean;
+}
+
 extern "C" {
     pub fn SCBridgeInterfaceCopyAll(prefs: SCPreferencesRef) -> CFArrayRef;
 
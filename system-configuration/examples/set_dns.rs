@@ -6,7 +6,10 @@ use core_foundation::{
     string::{CFString, CFStringRef},
 };
 use system_configuration::{
-    dynamic_store::{SCDynamicStore, SCDynamicStoreBuilder},
+    dynamic_store::{
+        key::{Domain, DynamicStoreKey, Entity},
+        SCDynamicStore, SCDynamicStoreBuilder,
+    },
     sys::schema_definitions::{kSCDynamicStorePropNetPrimaryService, kSCPropNetDNSServerAddresses},
 };
 
@@ -18,10 +21,11 @@ fn main() {
     let primary_service_uuid = get_primary_service_uuid(&store).expect("No PrimaryService active");
     println!("PrimaryService UUID: {}", primary_service_uuid);
 
-    let primary_service_path = CFString::new(&format!(
-        "State:/Network/Service/{}/DNS",
-        primary_service_uuid
-    ));
+    let primary_service_path = DynamicStoreKey::network_service_entity(
+        Domain::State,
+        primary_service_uuid.to_string(),
+        Some(Entity::DNS),
+    );
     println!("PrimaryService path: {}", primary_service_path);
 
     let dns_dictionary = create_dns_dictionary(&[
@@ -29,13 +33,18 @@ fn main() {
         CFString::from_static_string("8.8.4.4"),
     ]);
 
-    let success = store.set(primary_service_path, dns_dictionary);
-    println!("success? {}", success);
+    match store.set(primary_service_path, dns_dictionary) {
+        Ok(()) => println!("success"),
+        Err(error) => println!("failed: {}", error),
+    }
 }
 
 fn get_primary_service_uuid(store: &SCDynamicStore) -> Option<CFString> {
     let dictionary = store
-        .get("State:/Network/Global/IPv4")
+        .get(DynamicStoreKey::network_global_entity(
+            Domain::State,
+            Entity::IPv4,
+        ))
         .and_then(CFPropertyList::downcast_into::<CFDictionary>)?;
     dictionary
         .find(unsafe { kSCDynamicStorePropNetPrimaryService }.to_void())
@@ -0,0 +1,328 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Declarative network configuration.
+//!
+//! A [`NetworkConfigSpec`] describes the desired network sets (locations), their services with
+//! the interfaces they run on, the protocol configuration of every service and the service order.
+//! Applying it happens in three steps:
+//!
+//! 1. [`Snapshot::capture`] reads the current configuration out of an [`SCPreferences`] session.
+//! 2. [`NetworkConfigSpec::plan`] compares the spec with the snapshot and produces a [`Plan`], an
+//!    ordered list of [`Change`]s. This step is pure Rust, so a plan can be built and inspected
+//!    from a snapshot that was constructed by hand.
+//! 3. [`Plan::execute`] performs the changes while holding the preferences lock, then commits
+//!    them and optionally applies them to the running system.
+//!
+//! Sets are matched to the spec by name, and services by name within their set. A service whose
+//! interface does not match the spec is replaced by a new one.
+//!
+//! [`SCPreferences`]: crate::preferences::SCPreferences
+
+mod execute;
+mod plan;
+
+pub use execute::*;
+pub use plan::*;
+
+use crate::{
+    network_configuration::{SCNetworkInterfaceType, SCNetworkProtocolType},
+    plist::Dictionary,
+    settings::SettingsModel,
+};
+
+/// The desired network configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct NetworkConfigSpec {
+    /// The sets to create or update.
+    pub sets: Vec<SetSpec>,
+    /// Name of the set to make the current set. The current set is left as it is if `None`.
+    pub current_set: Option<String>,
+    /// If `true`, sets, services and protocols that are not part of the spec are removed. Sets not
+    /// named in the spec are otherwise left untouched, and unlisted services are ordered after the
+    /// listed ones.
+    pub prune: bool,
+}
+
+/// A network set, also called a location.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SetSpec {
+    /// The user-visible name of the set. Set names must be unique.
+    pub name: String,
+    /// The services of the set, in the desired service order.
+    pub services: Vec<ServiceSpec>,
+}
+
+impl SetSpec {
+    /// Returns a spec for a set with the given name and no services.
+    pub fn new(name: impl Into<String>) -> Self {
+        SetSpec {
+            name: name.into(),
+            services: Vec::new(),
+        }
+    }
+
+    /// Appends a service to the end of the service order.
+    pub fn with_service(mut self, service: ServiceSpec) -> Self {
+        self.services.push(service);
+        self
+    }
+}
+
+/// A network service within a set.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServiceSpec {
+    /// The user-visible name of the service. Service names must be unique within a set.
+    pub name: String,
+    /// The interface the service runs on.
+    pub interface: InterfaceSpec,
+    /// Whether the service is enabled.
+    #[cfg_attr(feature = "serde", serde(default = "enabled_by_default"))]
+    pub enabled: bool,
+    /// The protocols of the service. New services start out with the default protocols of their
+    /// interface, see [`SCNetworkService::establish_default_configuration`].
+    ///
+    /// [`SCNetworkService::establish_default_configuration`]: crate::network_configuration::SCNetworkService::establish_default_configuration
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub protocols: Vec<ProtocolSpec>,
+}
+
+impl ServiceSpec {
+    /// Returns a spec for an enabled service on the given interface, with no protocols listed.
+    pub fn new(name: impl Into<String>, interface: InterfaceSpec) -> Self {
+        ServiceSpec {
+            name: name.into(),
+            interface,
+            enabled: true,
+            protocols: Vec::new(),
+        }
+    }
+
+    /// Sets whether the service is enabled.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Adds a protocol to the service.
+    pub fn with_protocol(mut self, protocol: ProtocolSpec) -> Self {
+        self.protocols.push(protocol);
+        self
+    }
+}
+
+/// A network interface, possibly layered on top of another interface.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterfaceSpec {
+    /// The type of the interface.
+    pub interface_type: SCNetworkInterfaceType,
+    /// The _BSD_ name of the interface, such as `en0`. Any interface of the right type matches if
+    /// `None`.
+    pub bsd_name: Option<String>,
+    /// The interface this interface is layered on, such as the modem under a PPP interface. Any
+    /// underlying interface matches if `None`.
+    pub underlying: Option<Box<InterfaceSpec>>,
+}
+
+impl InterfaceSpec {
+    /// Returns a spec matching any interface of the given type.
+    pub fn new(interface_type: SCNetworkInterfaceType) -> Self {
+        InterfaceSpec {
+            interface_type,
+            bsd_name: None,
+            underlying: None,
+        }
+    }
+
+    /// Restricts the spec to the interface with the given _BSD_ name.
+    pub fn with_bsd_name(mut self, bsd_name: impl Into<String>) -> Self {
+        self.bsd_name = Some(bsd_name.into());
+        self
+    }
+
+    /// Layers this interface on top of `underlying`.
+    pub fn layered_on(mut self, underlying: InterfaceSpec) -> Self {
+        self.underlying = Some(Box::new(underlying));
+        self
+    }
+
+    /// Returns `true` if `interface`, as captured from the live configuration, satisfies this
+    /// spec.
+    pub fn matches(&self, interface: &InterfaceSpec) -> bool {
+        self.interface_type == interface.interface_type
+            && self.bsd_name.as_ref().map_or(true, |bsd_name| {
+                interface.bsd_name.as_ref() == Some(bsd_name)
+            })
+            && match (&self.underlying, &interface.underlying) {
+                (None, _) => true,
+                (Some(spec), Some(underlying)) => spec.matches(underlying),
+                (Some(_), None) => false,
+            }
+    }
+}
+
+/// A protocol of a network service.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolSpec {
+    /// The type of the protocol.
+    pub protocol_type: SCNetworkProtocolType,
+    /// Whether the protocol is enabled.
+    #[cfg_attr(feature = "serde", serde(default = "enabled_by_default"))]
+    pub enabled: bool,
+    /// The configuration of the protocol. The current configuration is left as it is if `None`.
+    pub configuration: Option<Dictionary>,
+}
+
+impl ProtocolSpec {
+    /// Returns a spec for an enabled protocol of the given type, keeping its configuration.
+    pub fn new(protocol_type: SCNetworkProtocolType) -> Self {
+        ProtocolSpec {
+            protocol_type,
+            enabled: true,
+            configuration: None,
+        }
+    }
+
+    /// Sets whether the protocol is enabled.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Sets the configuration of the protocol.
+    pub fn with_configuration(mut self, configuration: Dictionary) -> Self {
+        self.configuration = Some(configuration);
+        self
+    }
+
+    /// Sets the configuration of the protocol from a typed model, such as
+    /// [`DnsConfig`](crate::settings::DnsConfig).
+    pub fn with_model(self, model: &impl SettingsModel) -> Self {
+        self.with_configuration(model.to_plist())
+    }
+}
+
+/// Services and protocols are enabled unless a spec says otherwise.
+#[cfg(feature = "serde")]
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// The network configuration as found in a preferences session.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Snapshot {
+    /// All network sets.
+    pub sets: Vec<SetSnapshot>,
+    /// Identifier of the current set.
+    pub current_set: Option<String>,
+    /// The interfaces of this machine with the protocols a new service on them starts out with.
+    /// New services on interfaces missing here are assumed to start out with the usual protocols
+    /// of their interface type.
+    pub interfaces: Vec<InterfaceSnapshot>,
+}
+
+/// A network interface together with the protocols
+/// `SCNetworkServiceEstablishDefaultConfiguration` adds to new services on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterfaceSnapshot {
+    /// The interface, including the interfaces it is layered on.
+    pub interface: InterfaceSpec,
+    /// The protocols the interface supports.
+    pub protocols: Vec<SCNetworkProtocolType>,
+}
+
+/// A network set as found in a preferences session.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SetSnapshot {
+    /// The set identifier.
+    pub id: String,
+    /// The user-visible name of the set.
+    pub name: Option<String>,
+    /// The services of the set, in service order.
+    pub services: Vec<ServiceSnapshot>,
+}
+
+/// A network service as found in a preferences session.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServiceSnapshot {
+    /// The service identifier.
+    pub id: String,
+    /// The user-visible name of the service.
+    pub name: Option<String>,
+    /// Whether the service is enabled.
+    pub enabled: bool,
+    /// The interface the service runs on, `None` if its type is not known.
+    pub interface: Option<InterfaceSpec>,
+    /// The protocols of the service with their current configuration.
+    pub protocols: Vec<ProtocolSpec>,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_from_json() {
+        let spec: NetworkConfigSpec = serde_json::from_str(
+            r#"{
+                "sets": [{
+                    "name": "Office",
+                    "services": [{
+                        "name": "Ethernet",
+                        "interface": { "interface_type": "Ethernet", "bsd_name": "en0" },
+                        "protocols": [{
+                            "protocol_type": "DNS",
+                            "configuration": { "ServerAddresses": ["10.0.0.1"] }
+                        }]
+                    }]
+                }],
+                "current_set": "Office"
+            }"#,
+        )
+        .unwrap();
+
+        let dns = ProtocolSpec::new(SCNetworkProtocolType::DNS).with_configuration(
+            [(
+                "ServerAddresses".to_owned(),
+                crate::plist::PlistValue::Array(vec!["10.0.0.1".into()]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let expected = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Office").with_service(
+                ServiceSpec::new(
+                    "Ethernet",
+                    InterfaceSpec::new(SCNetworkInterfaceType::Ethernet).with_bsd_name("en0"),
+                )
+                .with_protocol(dns),
+            )],
+            current_set: Some("Office".to_owned()),
+            prune: false,
+        };
+        assert_eq!(spec, expected);
+        let json = serde_json::to_string(&expected).unwrap();
+        assert_eq!(
+            serde_json::from_str::<NetworkConfigSpec>(&json).unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use super::{
+    Change, InterfaceSnapshot, InterfaceSpec, Plan, ProtocolSpec, ServiceRef, ServiceSnapshot,
+    SetRef, SetSnapshot, Snapshot,
+};
+use crate::{
+    error::SCError,
+    network_configuration::{
+        SCNetworkInterface, SCNetworkInterfaceType, SCNetworkProtocol, SCNetworkProtocolType,
+        SCNetworkService, SCNetworkSet,
+    },
+    plist::{dictionary_from_cf, dictionary_to_cf},
+    preferences::SCPreferences,
+};
+use core_foundation::{array::CFArray, string::CFString};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+/// Failure to execute a [`Plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplyError {
+    /// A SystemConfiguration call failed.
+    Framework(SCError),
+    /// A set of the plan no longer exists. The preferences changed after the snapshot was taken.
+    MissingSet(SetRef),
+    /// A service of the plan no longer exists. The preferences changed after the snapshot was
+    /// taken.
+    MissingService(ServiceRef),
+    /// A protocol the plan changes does not exist on its service.
+    MissingProtocol(SCNetworkProtocolType),
+    /// No interface on this machine matches the spec of a new service.
+    MissingInterface(InterfaceSpec),
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::Framework(error) => error.fmt(f),
+            ApplyError::MissingSet(set) => write!(f, "Set {:?} does not exist", set),
+            ApplyError::MissingService(service) => {
+                write!(f, "Service {:?} does not exist", service)
+            }
+            ApplyError::MissingProtocol(protocol_type) => {
+                write!(f, "Protocol {} does not exist", protocol_type)
+            }
+            ApplyError::MissingInterface(interface) => {
+                write!(f, "No interface matches {:?}", interface)
+            }
+        }
+    }
+}
+
+impl Error for ApplyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApplyError::Framework(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SCError> for ApplyError {
+    fn from(error: SCError) -> Self {
+        ApplyError::Framework(error)
+    }
+}
+
+impl Snapshot {
+    /// Reads the network sets and services out of a preferences session.
+    pub fn capture(prefs: &SCPreferences) -> Self {
+        Snapshot {
+            sets: SCNetworkSet::get_sets(prefs)
+                .iter()
+                .filter_map(|set| SetSnapshot::capture(&set))
+                .collect(),
+            current_set: SCNetworkSet::get_current(prefs)
+                .and_then(|set| set.id())
+                .map(|id| id.to_string()),
+            interfaces: InterfaceSnapshot::capture_all(),
+        }
+    }
+}
+
+impl InterfaceSnapshot {
+    /// Reads the interfaces of this machine, including the [IPv4](SCNetworkInterface::ipv4)
+    /// interface and the interfaces that can be layered on top of them.
+    pub fn capture_all() -> Vec<Self> {
+        let mut interfaces = Vec::new();
+        for interface in SCNetworkInterface::get_interfaces().iter() {
+            Self::capture_layers(&interface, &mut interfaces);
+        }
+        Self::capture_layers(&SCNetworkInterface::ipv4(), &mut interfaces);
+        interfaces
+    }
+
+    fn capture_layers(interface: &SCNetworkInterface, interfaces: &mut Vec<Self>) {
+        let spec = match InterfaceSpec::from_interface(interface) {
+            Some(spec) => spec,
+            None => return,
+        };
+        let layers = interface
+            .supported_interface_type_strings()
+            .iter()
+            .map(|interface_type| SCNetworkInterfaceType::from(&*interface_type.to_string()))
+            .filter(|interface_type| !spec.stack_contains(interface_type))
+            .filter_map(|interface_type| interface.layer(interface_type))
+            .collect::<Vec<_>>();
+        interfaces.push(InterfaceSnapshot {
+            interface: spec,
+            protocols: interface
+                .supported_protocol_type_strings()
+                .iter()
+                .map(|protocol_type| SCNetworkProtocolType::from(&*protocol_type.to_string()))
+                .collect(),
+        });
+        for layer in &layers {
+            Self::capture_layers(layer, interfaces);
+        }
+    }
+}
+
+impl SetSnapshot {
+    /// Reads a network set, returning `None` if it has no identifier. The services are listed in
+    /// service order, followed by services missing from the service order.
+    pub fn capture(set: &SCNetworkSet) -> Option<Self> {
+        let mut services = set
+            .services()
+            .iter()
+            .filter_map(|service| ServiceSnapshot::capture(&service))
+            .collect::<Vec<_>>();
+        let order = set
+            .service_order()
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        services.sort_by_key(|service| {
+            order
+                .iter()
+                .position(|id| *id == service.id)
+                .unwrap_or(order.len())
+        });
+        Some(SetSnapshot {
+            id: set.id()?.to_string(),
+            name: set.name().map(|name| name.to_string()),
+            services,
+        })
+    }
+}
+
+impl ServiceSnapshot {
+    /// Reads a network service, returning `None` if it has no identifier.
+    pub fn capture(service: &SCNetworkService) -> Option<Self> {
+        Some(ServiceSnapshot {
+            id: service.id()?.to_string(),
+            name: service.name().map(|name| name.to_string()),
+            enabled: service.enabled(),
+            interface: service
+                .network_interface()
+                .and_then(|interface| InterfaceSpec::from_interface(&interface)),
+            protocols: service
+                .network_protocols()
+                .iter()
+                .filter_map(|protocol| ProtocolSpec::from_protocol(&protocol))
+                .collect(),
+        })
+    }
+}
+
+impl InterfaceSpec {
+    /// Describes an existing interface, including the interfaces it is layered on. Returns `None`
+    /// if the interface has no type.
+    pub fn from_interface(interface: &SCNetworkInterface) -> Option<Self> {
+        Some(InterfaceSpec {
+            interface_type: interface.interface_type()?,
+            bsd_name: interface.bsd_name().map(|bsd_name| bsd_name.to_string()),
+            underlying: interface
+                .underlying_interface()
+                .and_then(|underlying| Self::from_interface(&underlying))
+                .map(Box::new),
+        })
+    }
+
+    /// Finds the interface matching this spec among the interfaces of this machine, creating the
+    /// layered interfaces on top of it as needed. An IPv4 spec resolves to
+    /// [`SCNetworkInterface::ipv4`], the root of VPN interfaces such as L2TP.
+    pub fn resolve(&self) -> Option<SCNetworkInterface> {
+        match &self.underlying {
+            Some(underlying) => underlying.resolve()?.layer(self.interface_type.clone()),
+            None if self.interface_type == SCNetworkInterfaceType::IPv4 => {
+                Some(SCNetworkInterface::ipv4())
+            }
+            None => SCNetworkInterface::get_interfaces()
+                .iter()
+                .find(|interface| {
+                    Self::from_interface(interface).map_or(false, |live| self.matches(&live))
+                })
+                .map(|interface| interface.clone()),
+        }
+    }
+
+    /// Returns `true` if this interface or one it is layered on has the given type.
+    fn stack_contains(&self, interface_type: &SCNetworkInterfaceType) -> bool {
+        self.interface_type == *interface_type
+            || self.underlying.as_ref().map_or(false, |underlying| {
+                underlying.stack_contains(interface_type)
+            })
+    }
+}
+
+impl ProtocolSpec {
+    /// Describes an existing protocol with its current configuration. Returns `None` if the
+    /// protocol has no type.
+    pub fn from_protocol(protocol: &SCNetworkProtocol) -> Option<Self> {
+        Some(ProtocolSpec {
+            protocol_type: protocol.protocol_type()?,
+            enabled: protocol.enabled(),
+            configuration: protocol
+                .configuration()
+                .and_then(|configuration| dictionary_from_cf(&configuration)),
+        })
+    }
+}
+
+impl Plan {
+    /// Makes the changes of this plan to `prefs` and commits them. If `apply` is `true`, the
+    /// committed configuration is also applied to the running system.
+    ///
+    /// The preferences are locked for the duration of the changes, waiting for other processes to
+    /// release their lock. If a change fails, all changes made to the session are discarded with
+    /// [`SCPreferences::synchronize`].
+    pub fn execute(&self, prefs: &mut SCPreferences, apply: bool) -> Result<(), ApplyError> {
+        prefs.lock(true)?;
+        let mut result = Executor::new(prefs).execute(&self.changes);
+        if result.is_ok() {
+            result = prefs.commit_changes().map_err(ApplyError::from);
+        }
+        if result.is_ok() && apply {
+            result = prefs.apply_changes().map_err(ApplyError::from);
+        }
+        if result.is_err() {
+            prefs.synchronize();
+        }
+        let unlocked = prefs.unlock();
+        result?;
+        Ok(unlocked?)
+    }
+}
+
+struct Executor<'a> {
+    prefs: &'a SCPreferences,
+    created_sets: HashMap<String, SCNetworkSet>,
+    created_services: HashMap<ServiceRef, SCNetworkService>,
+}
+
+impl<'a> Executor<'a> {
+    fn new(prefs: &'a SCPreferences) -> Self {
+        Executor {
+            prefs,
+            created_sets: HashMap::new(),
+            created_services: HashMap::new(),
+        }
+    }
+
+    fn execute(&mut self, changes: &[Change]) -> Result<(), ApplyError> {
+        for change in changes {
+            self.execute_change(change)?;
+        }
+        Ok(())
+    }
+
+    fn execute_change(&mut self, change: &Change) -> Result<(), ApplyError> {
+        match change {
+            Change::CreateSet { name } => {
+                let set = SCNetworkSet::create_named(self.prefs, &CFString::new(name))?;
+                self.created_sets.insert(name.clone(), set);
+            }
+            Change::CreateService {
+                set,
+                name,
+                interface,
+            } => {
+                let mut network_set = self.set(set)?;
+                let network_interface = interface
+                    .resolve()
+                    .ok_or_else(|| ApplyError::MissingInterface(interface.clone()))?;
+                let mut service = SCNetworkService::create(self.prefs, &network_interface)
+                    .ok_or_else(SCError::last)?;
+                service.set_name(Some(&CFString::new(name)))?;
+                service.establish_default_configuration()?;
+                network_set.add_service(&service)?;
+                self.created_services.insert(
+                    ServiceRef::New {
+                        set: set.clone(),
+                        name: name.clone(),
+                    },
+                    service,
+                );
+            }
+            Change::RemoveService { set, service } => {
+                let service_ref = ServiceRef::Existing(service.clone());
+                let service = self.service(&service_ref)?;
+                self.set(set)?.remove_service(&service)?;
+            }
+            Change::DeleteService { service } => {
+                self.service(&ServiceRef::Existing(service.clone()))?
+                    .remove()?;
+            }
+            Change::SetServiceEnabled { service, enabled } => {
+                self.service(service)?.set_enabled(*enabled)?;
+            }
+            Change::AddProtocol {
+                service,
+                protocol_type,
+            } => {
+                let mut service = self.service(service)?;
+                if service.protocol(protocol_type.clone()).is_none() {
+                    service.add_network_protocol(protocol_type.clone())?;
+                }
+            }
+            Change::SetProtocolConfiguration {
+                service,
+                protocol_type,
+                configuration,
+            } => {
+                self.protocol(service, protocol_type)?
+                    .set_configuration(&dictionary_to_cf(configuration))?;
+            }
+            Change::SetProtocolEnabled {
+                service,
+                protocol_type,
+                enabled,
+            } => {
+                self.protocol(service, protocol_type)?
+                    .set_enabled(*enabled)?;
+            }
+            Change::RemoveProtocol {
+                service,
+                protocol_type,
+            } => {
+                let mut service = self.service(service)?;
+                if service.protocol(protocol_type.clone()).is_some() {
+                    service.remove_network_protocol(protocol_type.clone())?;
+                }
+            }
+            Change::SetServiceOrder { set, order } => {
+                let ids = order
+                    .iter()
+                    .map(|service| {
+                        self.service(service)?
+                            .id()
+                            .ok_or_else(|| ApplyError::MissingService(service.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.set(set)?
+                    .set_service_order(CFArray::from_CFTypes(&ids))?;
+            }
+            Change::SetCurrentSet { set } => {
+                self.set(set)?.set_current()?;
+            }
+            Change::RemoveSet { set } => {
+                self.set(&SetRef::Existing(set.clone()))?.remove()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&self, set: &SetRef) -> Result<SCNetworkSet, ApplyError> {
+        match set {
+            SetRef::Existing(id) => SCNetworkSet::find_set(self.prefs, id.as_str()),
+            SetRef::New(name) => self.created_sets.get(name).cloned(),
+        }
+        .ok_or_else(|| ApplyError::MissingSet(set.clone()))
+    }
+
+    fn service(&self, service: &ServiceRef) -> Result<SCNetworkService, ApplyError> {
+        match service {
+            ServiceRef::Existing(id) => SCNetworkService::find_service(self.prefs, id.as_str()),
+            ServiceRef::New { .. } => self.created_services.get(service).cloned(),
+        }
+        .ok_or_else(|| ApplyError::MissingService(service.clone()))
+    }
+
+    fn protocol(
+        &self,
+        service: &ServiceRef,
+        protocol_type: &SCNetworkProtocolType,
+    ) -> Result<SCNetworkProtocol, ApplyError> {
+        self.service(service)?
+            .protocol(protocol_type.clone())
+            .ok_or_else(|| ApplyError::MissingProtocol(protocol_type.clone()))
+    }
+}
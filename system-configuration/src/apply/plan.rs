@@ -0,0 +1,1207 @@
+use super::{
+    InterfaceSnapshot, InterfaceSpec, NetworkConfigSpec, ProtocolSpec, ServiceSnapshot,
+    ServiceSpec, SetSnapshot, SetSpec, Snapshot,
+};
+use crate::{
+    network_configuration::{SCNetworkInterfaceType, SCNetworkProtocolType},
+    plist::Dictionary,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+};
+
+/// Refers to a set that a [`Change`] operates on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SetRef {
+    /// A set that already exists, by identifier.
+    Existing(String),
+    /// A set created by an earlier [`Change::CreateSet`], by name.
+    New(String),
+}
+
+/// Refers to a service that a [`Change`] operates on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ServiceRef {
+    /// A service that already exists, by identifier.
+    Existing(String),
+    /// A service created by an earlier [`Change::CreateService`], by its set and name.
+    New {
+        /// The set the service was created in.
+        set: SetRef,
+        /// The name of the service.
+        name: String,
+    },
+}
+
+/// A single step of a [`Plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Creates a new set with the given name.
+    CreateSet {
+        /// The name of the new set.
+        name: String,
+    },
+    /// Creates a service with the default configuration of its interface and adds it to a set.
+    CreateService {
+        /// The set to add the service to.
+        set: SetRef,
+        /// The name of the new service.
+        name: String,
+        /// The interface of the new service.
+        interface: InterfaceSpec,
+    },
+    /// Removes a service from a set. The service itself stays in the preferences.
+    RemoveService {
+        /// The set to remove the service from.
+        set: SetRef,
+        /// Identifier of the service.
+        service: String,
+    },
+    /// Removes a service that is no longer part of any set from the preferences.
+    DeleteService {
+        /// Identifier of the service.
+        service: String,
+    },
+    /// Enables or disables a service.
+    SetServiceEnabled {
+        /// The service to change.
+        service: ServiceRef,
+        /// Whether the service should be enabled.
+        enabled: bool,
+    },
+    /// Adds a protocol to a service. Nothing happens if the service already has the protocol.
+    AddProtocol {
+        /// The service to change.
+        service: ServiceRef,
+        /// The protocol to add.
+        protocol_type: SCNetworkProtocolType,
+    },
+    /// Replaces the configuration of a protocol.
+    SetProtocolConfiguration {
+        /// The service to change.
+        service: ServiceRef,
+        /// The protocol to change.
+        protocol_type: SCNetworkProtocolType,
+        /// The new configuration.
+        configuration: Dictionary,
+    },
+    /// Enables or disables a protocol.
+    SetProtocolEnabled {
+        /// The service to change.
+        service: ServiceRef,
+        /// The protocol to change.
+        protocol_type: SCNetworkProtocolType,
+        /// Whether the protocol should be enabled.
+        enabled: bool,
+    },
+    /// Removes a protocol from a service. Nothing happens if the service does not have the
+    /// protocol.
+    RemoveProtocol {
+        /// The service to change.
+        service: ServiceRef,
+        /// The protocol to remove.
+        protocol_type: SCNetworkProtocolType,
+    },
+    /// Sets the service order of a set.
+    SetServiceOrder {
+        /// The set to change.
+        set: SetRef,
+        /// The services of the set, in the new order.
+        order: Vec<ServiceRef>,
+    },
+    /// Makes a set the current set.
+    SetCurrentSet {
+        /// The set to make current.
+        set: SetRef,
+    },
+    /// Removes a set. The services of the set stay in the preferences.
+    RemoveSet {
+        /// Identifier of the set.
+        set: String,
+    },
+}
+
+/// The ordered list of changes that turn a [`Snapshot`] into the configuration described by a
+/// [`NetworkConfigSpec`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    /// The changes, in the order they have to be made.
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    /// Returns `true` if the configuration already matches the spec.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Failure to plan the changes for a [`NetworkConfigSpec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// The spec lists more than one set with this name.
+    DuplicateSet(String),
+    /// The spec lists more than one service with this name in a set.
+    DuplicateService {
+        /// The name of the set.
+        set: String,
+        /// The name of the service.
+        service: String,
+    },
+    /// More than one existing set has this name, so the set of the spec cannot be matched.
+    AmbiguousSet(String),
+    /// More than one existing service in a set has this name, so the service of the spec cannot
+    /// be matched.
+    AmbiguousService {
+        /// The name of the set.
+        set: String,
+        /// The name of the service.
+        service: String,
+    },
+    /// The set to make current is neither in the spec nor in the snapshot.
+    UnknownCurrentSet(String),
+    /// The current set would be removed without another set becoming current.
+    CurrentSetRemoved(String),
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::DuplicateSet(set) => write!(f, "Set {:?} is listed more than once", set),
+            PlanError::DuplicateService { set, service } => write!(
+                f,
+                "Service {:?} is listed more than once in set {:?}",
+                service, set
+            ),
+            PlanError::AmbiguousSet(set) => {
+                write!(f, "More than one existing set is named {:?}", set)
+            }
+            PlanError::AmbiguousService { set, service } => write!(
+                f,
+                "More than one existing service in set {:?} is named {:?}",
+                set, service
+            ),
+            PlanError::UnknownCurrentSet(set) => write!(f, "No set is named {:?}", set),
+            PlanError::CurrentSetRemoved(set) => {
+                write!(f, "The current set {:?} would be removed", set)
+            }
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+impl NetworkConfigSpec {
+    /// Computes the changes needed to turn the configuration in `snapshot` into the one described
+    /// by this spec. An empty plan means the configuration already matches.
+    pub fn plan(&self, snapshot: &Snapshot) -> Result<Plan, PlanError> {
+        self.check_duplicates()?;
+        let sets = self
+            .sets
+            .iter()
+            .map(|set| Ok((set, find_set(snapshot, &set.name)?)))
+            .collect::<Result<Vec<_>, PlanError>>()?;
+        let removed_sets = if self.prune {
+            snapshot
+                .sets
+                .iter()
+                .filter(|live| {
+                    !sets.iter().any(|(_, matched)| {
+                        matched.map(|m| m.id.as_str()) == Some(live.id.as_str())
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let current_set = match &self.current_set {
+            Some(name) => Some(match sets.iter().find(|(set, _)| set.name == *name) {
+                Some((set, live)) => set_ref(set, *live),
+                None => match find_set(snapshot, name)? {
+                    Some(live) if !removed_sets.iter().any(|set| set.id == live.id) => {
+                        SetRef::Existing(live.id.clone())
+                    }
+                    _ => return Err(PlanError::UnknownCurrentSet(name.clone())),
+                },
+            }),
+            None => None,
+        };
+        if current_set.is_none() {
+            if let Some(removed) = removed_sets
+                .iter()
+                .find(|set| Some(&set.id) == snapshot.current_set.as_ref())
+            {
+                return Err(PlanError::CurrentSetRemoved(
+                    removed.name.clone().unwrap_or_else(|| removed.id.clone()),
+                ));
+            }
+        }
+
+        let mut planner = Planner {
+            prune: self.prune,
+            interfaces: &snapshot.interfaces,
+            references: HashMap::new(),
+            changes: Vec::new(),
+            deleted: HashSet::new(),
+        };
+        let mut service_matches = Vec::new();
+        for (set, live) in &sets {
+            let matches = match_services(set, *live)?;
+            planner.count_references(*live, &matches);
+            service_matches.push(matches);
+        }
+        for live in &snapshot.sets {
+            if !removed_sets.iter().any(|set| set.id == live.id)
+                && !sets
+                    .iter()
+                    .any(|(_, matched)| matched.map(|m| &m.id) == Some(&live.id))
+            {
+                for service in &live.services {
+                    *planner.references.entry(service.id.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        for (set, live) in &sets {
+            if live.is_none() {
+                planner.changes.push(Change::CreateSet {
+                    name: set.name.clone(),
+                });
+            }
+        }
+        for ((set, live), matches) in sets.iter().zip(service_matches) {
+            planner.plan_set(set, *live, matches);
+        }
+        if let Some(current_set) = current_set {
+            let unchanged = match &current_set {
+                SetRef::Existing(id) => snapshot.current_set.as_ref() == Some(id),
+                SetRef::New(_) => false,
+            };
+            if !unchanged {
+                planner
+                    .changes
+                    .push(Change::SetCurrentSet { set: current_set });
+            }
+        }
+        for set in removed_sets {
+            planner.changes.push(Change::RemoveSet {
+                set: set.id.clone(),
+            });
+            for service in &set.services {
+                planner.delete_if_unreferenced(&service.id);
+            }
+        }
+        Ok(Plan {
+            changes: planner.changes,
+        })
+    }
+
+    fn check_duplicates(&self) -> Result<(), PlanError> {
+        let mut set_names = HashSet::new();
+        for set in &self.sets {
+            if !set_names.insert(set.name.as_str()) {
+                return Err(PlanError::DuplicateSet(set.name.clone()));
+            }
+            let mut service_names = HashSet::new();
+            for service in &set.services {
+                if !service_names.insert(service.name.as_str()) {
+                    return Err(PlanError::DuplicateService {
+                        set: set.name.clone(),
+                        service: service.name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The existing service each service of a set spec corresponds to, if any.
+struct ServiceMatches<'a> {
+    /// The services of the spec, with the existing service they update.
+    listed: Vec<(&'a ServiceSpec, Option<&'a ServiceSnapshot>)>,
+    /// Existing services that have to be replaced because their interface does not match.
+    replaced: Vec<&'a ServiceSnapshot>,
+    /// Existing services that are not in the spec.
+    unlisted: Vec<&'a ServiceSnapshot>,
+}
+
+struct Planner<'a> {
+    prune: bool,
+    /// The interfaces of this machine, for the protocols new services start out with.
+    interfaces: &'a [InterfaceSnapshot],
+    /// The number of sets each existing service is still part of once the plan is executed.
+    references: HashMap<String, usize>,
+    changes: Vec<Change>,
+    deleted: HashSet<String>,
+}
+
+impl Planner<'_> {
+    fn count_references(&mut self, live: Option<&SetSnapshot>, matches: &ServiceMatches<'_>) {
+        let prune = self.prune;
+        let kept = matches.listed.iter().filter_map(|(_, live)| *live);
+        let unlisted = matches.unlisted.iter().copied().filter(|_| !prune);
+        for service in kept.chain(unlisted) {
+            *self.references.entry(service.id.clone()).or_default() += 1;
+        }
+        // Make sure services of this set are known, so they get deleted if no set keeps them.
+        for service in live.iter().flat_map(|set| &set.services) {
+            self.references.entry(service.id.clone()).or_default();
+        }
+    }
+
+    fn delete_if_unreferenced(&mut self, service: &str) {
+        if self.references.get(service).copied().unwrap_or_default() == 0
+            && self.deleted.insert(service.to_owned())
+        {
+            self.changes.push(Change::DeleteService {
+                service: service.to_owned(),
+            });
+        }
+    }
+
+    fn plan_set(&mut self, set: &SetSpec, live: Option<&SetSnapshot>, matches: ServiceMatches<'_>) {
+        let prune = self.prune;
+        let set_ref = set_ref(set, live);
+        let removed = matches
+            .replaced
+            .iter()
+            .chain(matches.unlisted.iter().filter(|_| prune));
+        for service in removed {
+            self.changes.push(Change::RemoveService {
+                set: set_ref.clone(),
+                service: service.id.clone(),
+            });
+            self.delete_if_unreferenced(&service.id);
+        }
+
+        let mut order = Vec::new();
+        for (service, live) in &matches.listed {
+            let service_ref = match live {
+                Some(live) => ServiceRef::Existing(live.id.clone()),
+                None => {
+                    self.changes.push(Change::CreateService {
+                        set: set_ref.clone(),
+                        name: service.name.clone(),
+                        interface: service.interface.clone(),
+                    });
+                    ServiceRef::New {
+                        set: set_ref.clone(),
+                        name: service.name.clone(),
+                    }
+                }
+            };
+            self.plan_service(service, *live, &service_ref);
+            order.push(service_ref);
+        }
+        if !prune {
+            order.extend(
+                matches
+                    .unlisted
+                    .iter()
+                    .map(|service| ServiceRef::Existing(service.id.clone())),
+            );
+        }
+
+        let current_order = live
+            .map(|live| {
+                live.services
+                    .iter()
+                    .filter(|service| !matches.replaced.iter().any(|r| r.id == service.id))
+                    .filter(|service| {
+                        !prune || !matches.unlisted.iter().any(|u| u.id == service.id)
+                    })
+                    .map(|service| ServiceRef::Existing(service.id.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if order != current_order {
+            self.changes.push(Change::SetServiceOrder {
+                set: set_ref,
+                order,
+            });
+        }
+    }
+
+    fn plan_service(
+        &mut self,
+        service: &ServiceSpec,
+        live: Option<&ServiceSnapshot>,
+        service_ref: &ServiceRef,
+    ) {
+        // New services are enabled.
+        if service.enabled != live.map_or(true, |live| live.enabled) {
+            self.changes.push(Change::SetServiceEnabled {
+                service: service_ref.clone(),
+                enabled: service.enabled,
+            });
+        }
+
+        // New services start out with the default protocols of their interface.
+        let default_protocols;
+        let live_protocols = match live {
+            Some(live) => &live.protocols,
+            None => {
+                default_protocols = self
+                    .interfaces
+                    .iter()
+                    .find(|interface| service.interface.matches(&interface.interface))
+                    .map(|interface| {
+                        interface
+                            .protocols
+                            .iter()
+                            .cloned()
+                            .map(ProtocolSpec::new)
+                            .collect()
+                    })
+                    .unwrap_or_else(|| default_protocols_of(&service.interface.interface_type));
+                &default_protocols
+            }
+        };
+        for protocol in &service.protocols {
+            let live_protocol = live_protocols
+                .iter()
+                .find(|live| live.protocol_type == protocol.protocol_type);
+            if live_protocol.is_none() {
+                self.changes.push(Change::AddProtocol {
+                    service: service_ref.clone(),
+                    protocol_type: protocol.protocol_type.clone(),
+                });
+            }
+            if let Some(configuration) = &protocol.configuration {
+                if live_protocol.and_then(|live| live.configuration.as_ref()) != Some(configuration)
+                {
+                    self.changes.push(Change::SetProtocolConfiguration {
+                        service: service_ref.clone(),
+                        protocol_type: protocol.protocol_type.clone(),
+                        configuration: configuration.clone(),
+                    });
+                }
+            }
+            // Added protocols are enabled.
+            if protocol.enabled != live_protocol.map_or(true, |live| live.enabled) {
+                self.changes.push(Change::SetProtocolEnabled {
+                    service: service_ref.clone(),
+                    protocol_type: protocol.protocol_type.clone(),
+                    enabled: protocol.enabled,
+                });
+            }
+        }
+        if self.prune {
+            for live in live_protocols {
+                if !service
+                    .protocols
+                    .iter()
+                    .any(|protocol| protocol.protocol_type == live.protocol_type)
+                {
+                    self.changes.push(Change::RemoveProtocol {
+                        service: service_ref.clone(),
+                        protocol_type: live.protocol_type.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// The protocols `SCNetworkServiceEstablishDefaultConfiguration` usually adds to a service on an
+/// interface of the given type, for snapshots that do not list the interfaces of the machine.
+fn default_protocols_of(interface_type: &SCNetworkInterfaceType) -> Vec<ProtocolSpec> {
+    use SCNetworkInterfaceType as Interface;
+    use SCNetworkProtocolType::*;
+
+    let protocols: &[SCNetworkProtocolType] = match interface_type {
+        Interface::Bond
+        | Interface::Bridge
+        | Interface::Ethernet
+        | Interface::FireWire
+        | Interface::IEEE80211
+        | Interface::IPSec
+        | Interface::PPP
+        | Interface::VLAN
+        | Interface::VPN => &[DNS, IPv4, IPv6, Proxies, SMB],
+        Interface::Loopback => &[IPv4, IPv6],
+        Interface::SixToFour => &[IPv6],
+        _ => &[],
+    };
+    protocols.iter().cloned().map(ProtocolSpec::new).collect()
+}
+
+fn set_ref(set: &SetSpec, live: Option<&SetSnapshot>) -> SetRef {
+    match live {
+        Some(live) => SetRef::Existing(live.id.clone()),
+        None => SetRef::New(set.name.clone()),
+    }
+}
+
+fn find_set<'a>(snapshot: &'a Snapshot, name: &str) -> Result<Option<&'a SetSnapshot>, PlanError> {
+    let mut found = snapshot
+        .sets
+        .iter()
+        .filter(|set| set.name.as_deref() == Some(name));
+    match (found.next(), found.next()) {
+        (Some(_), Some(_)) => Err(PlanError::AmbiguousSet(name.to_owned())),
+        (set, _) => Ok(set),
+    }
+}
+
+fn match_services<'a>(
+    set: &'a SetSpec,
+    live: Option<&'a SetSnapshot>,
+) -> Result<ServiceMatches<'a>, PlanError> {
+    let live_services = live.map_or(&[] as &[ServiceSnapshot], |live| &live.services);
+    let mut matches = ServiceMatches {
+        listed: Vec::new(),
+        replaced: Vec::new(),
+        unlisted: Vec::new(),
+    };
+    for service in &set.services {
+        let mut found = live_services
+            .iter()
+            .filter(|live| live.name.as_deref() == Some(service.name.as_str()));
+        let live = match (found.next(), found.next()) {
+            (Some(_), Some(_)) => {
+                return Err(PlanError::AmbiguousService {
+                    set: set.name.clone(),
+                    service: service.name.clone(),
+                })
+            }
+            (live, _) => live,
+        };
+        match live {
+            Some(live)
+                if !live
+                    .interface
+                    .as_ref()
+                    .map_or(false, |interface| service.interface.matches(interface)) =>
+            {
+                matches.replaced.push(live);
+                matches.listed.push((service, None));
+            }
+            live => matches.listed.push((service, live)),
+        }
+    }
+    for live in live_services {
+        if !set
+            .services
+            .iter()
+            .any(|service| live.name.as_deref() == Some(service.name.as_str()))
+        {
+            matches.unlisted.push(live);
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        network_configuration::SCNetworkInterfaceType,
+        plist::PlistValue,
+        settings::{DnsConfig, SettingsModel},
+    };
+
+    fn ethernet(bsd_name: &str) -> InterfaceSpec {
+        InterfaceSpec::new(SCNetworkInterfaceType::Ethernet).with_bsd_name(bsd_name)
+    }
+
+    fn wwan() -> InterfaceSpec {
+        InterfaceSpec::new(SCNetworkInterfaceType::WWAN).with_bsd_name("wwan0")
+    }
+
+    fn dns(server: &str) -> Dictionary {
+        DnsConfig {
+            server_addresses: Some(vec![server.parse().unwrap()]),
+            ..Default::default()
+        }
+        .to_plist()
+    }
+
+    fn live_service(id: &str, name: &str, interface: InterfaceSpec) -> ServiceSnapshot {
+        ServiceSnapshot {
+            id: id.to_owned(),
+            name: Some(name.to_owned()),
+            enabled: true,
+            interface: Some(interface),
+            protocols: vec![
+                ProtocolSpec::new(SCNetworkProtocolType::DNS),
+                ProtocolSpec::new(SCNetworkProtocolType::IPv4),
+            ],
+        }
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            sets: vec![
+                SetSnapshot {
+                    id: "set-home".to_owned(),
+                    name: Some("Home".to_owned()),
+                    services: vec![
+                        live_service("svc-eth", "Ethernet", ethernet("en0")),
+                        live_service("svc-wifi", "Wi-Fi", {
+                            InterfaceSpec::new(SCNetworkInterfaceType::IEEE80211)
+                                .with_bsd_name("en1")
+                        }),
+                    ],
+                },
+                SetSnapshot {
+                    id: "set-office".to_owned(),
+                    name: Some("Office".to_owned()),
+                    services: vec![live_service("svc-office", "Ethernet", ethernet("en0"))],
+                },
+            ],
+            current_set: Some("set-home".to_owned()),
+            interfaces: vec![InterfaceSnapshot {
+                interface: wwan(),
+                protocols: vec![
+                    SCNetworkProtocolType::DNS,
+                    SCNetworkProtocolType::IPv4,
+                    SCNetworkProtocolType::IPv6,
+                ],
+            }],
+        }
+    }
+
+    fn home_spec() -> SetSpec {
+        SetSpec::new("Home")
+            .with_service(ServiceSpec::new("Ethernet", ethernet("en0")))
+            .with_service(ServiceSpec::new(
+                "Wi-Fi",
+                InterfaceSpec::new(SCNetworkInterfaceType::IEEE80211),
+            ))
+    }
+
+    fn existing(id: &str) -> ServiceRef {
+        ServiceRef::Existing(id.to_owned())
+    }
+
+    #[test]
+    fn unchanged_configuration() {
+        let spec = NetworkConfigSpec {
+            sets: vec![home_spec()],
+            current_set: Some("Home".to_owned()),
+            prune: false,
+        };
+        assert_eq!(spec.plan(&snapshot()), Ok(Plan::default()));
+    }
+
+    #[test]
+    fn update_existing_services() {
+        let mut home = home_spec();
+        home.services.swap(0, 1);
+        home.services[0].enabled = false;
+        home.services[1] = home.services[1].clone().with_protocol(
+            ProtocolSpec::new(SCNetworkProtocolType::DNS).with_configuration(dns("9.9.9.9")),
+        );
+        home.services[1] = home.services[1]
+            .clone()
+            .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv6).enabled(false));
+        let spec = NetworkConfigSpec {
+            sets: vec![home],
+            ..Default::default()
+        };
+
+        let plan = spec.plan(&snapshot()).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![
+                Change::SetServiceEnabled {
+                    service: existing("svc-wifi"),
+                    enabled: false,
+                },
+                Change::SetProtocolConfiguration {
+                    service: existing("svc-eth"),
+                    protocol_type: SCNetworkProtocolType::DNS,
+                    configuration: dns("9.9.9.9"),
+                },
+                Change::AddProtocol {
+                    service: existing("svc-eth"),
+                    protocol_type: SCNetworkProtocolType::IPv6,
+                },
+                Change::SetProtocolEnabled {
+                    service: existing("svc-eth"),
+                    protocol_type: SCNetworkProtocolType::IPv6,
+                    enabled: false,
+                },
+                Change::SetServiceOrder {
+                    set: SetRef::Existing("set-home".to_owned()),
+                    order: vec![existing("svc-wifi"), existing("svc-eth")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn create_set_and_make_it_current() {
+        let travel = SetRef::New("Travel".to_owned());
+        let tethering = ServiceRef::New {
+            set: travel.clone(),
+            name: "iPhone".to_owned(),
+        };
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Travel").with_service(
+                ServiceSpec::new("iPhone", ethernet("en5")).with_protocol(
+                    ProtocolSpec::new(SCNetworkProtocolType::DNS)
+                        .with_configuration(dns("1.1.1.1")),
+                ),
+            )],
+            current_set: Some("Travel".to_owned()),
+            prune: false,
+        };
+
+        let plan = spec.plan(&snapshot()).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![
+                Change::CreateSet {
+                    name: "Travel".to_owned(),
+                },
+                Change::CreateService {
+                    set: travel.clone(),
+                    name: "iPhone".to_owned(),
+                    interface: ethernet("en5"),
+                },
+                Change::SetProtocolConfiguration {
+                    service: tethering.clone(),
+                    protocol_type: SCNetworkProtocolType::DNS,
+                    configuration: dns("1.1.1.1"),
+                },
+                Change::SetServiceOrder {
+                    set: travel.clone(),
+                    order: vec![tethering],
+                },
+                Change::SetCurrentSet { set: travel },
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_service_with_other_interface() {
+        let spec = NetworkConfigSpec {
+            sets: vec![
+                SetSpec::new("Office").with_service(ServiceSpec::new("Ethernet", ethernet("en2")))
+            ],
+            ..Default::default()
+        };
+        let office = SetRef::Existing("set-office".to_owned());
+        let plan = spec.plan(&snapshot()).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![
+                Change::RemoveService {
+                    set: office.clone(),
+                    service: "svc-office".to_owned(),
+                },
+                Change::DeleteService {
+                    service: "svc-office".to_owned(),
+                },
+                Change::CreateService {
+                    set: office.clone(),
+                    name: "Ethernet".to_owned(),
+                    interface: ethernet("en2"),
+                },
+                Change::SetServiceOrder {
+                    set: office.clone(),
+                    order: vec![ServiceRef::New {
+                        set: office,
+                        name: "Ethernet".to_owned(),
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn prune_unlisted_configuration() {
+        let mut snapshot = snapshot();
+        // The Ethernet service of the Office set is shared with the Home set.
+        snapshot.sets[1].services[0].id = "svc-eth".to_owned();
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Home").with_service(
+                ServiceSpec::new("Ethernet", ethernet("en0"))
+                    .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv4)),
+            )],
+            current_set: None,
+            prune: true,
+        };
+        let home = SetRef::Existing("set-home".to_owned());
+
+        let plan = spec.plan(&snapshot).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![
+                Change::RemoveService {
+                    set: home.clone(),
+                    service: "svc-wifi".to_owned(),
+                },
+                Change::DeleteService {
+                    service: "svc-wifi".to_owned(),
+                },
+                Change::RemoveProtocol {
+                    service: existing("svc-eth"),
+                    protocol_type: SCNetworkProtocolType::DNS,
+                },
+                Change::RemoveSet {
+                    set: "set-office".to_owned(),
+                },
+            ]
+        );
+    }
+
+    /// Makes the changes of a plan to a snapshot the way the executor makes them to the
+    /// preferences.
+    fn execute(plan: &Plan, snapshot: &mut Snapshot) {
+        fn services<'a>(
+            snapshot: &'a mut Snapshot,
+            service: &'a ServiceRef,
+        ) -> impl Iterator<Item = &'a mut ServiceSnapshot> + 'a {
+            let id = match service {
+                ServiceRef::Existing(id) => id.clone(),
+                ServiceRef::New { set, name } => new_service_id(set, name),
+            };
+            snapshot
+                .sets
+                .iter_mut()
+                .flat_map(|set| &mut set.services)
+                .filter(move |service| service.id == id)
+        }
+        fn new_service_id(set: &SetRef, name: &str) -> String {
+            format!("{:?}/{}", set, name)
+        }
+        fn set<'a>(snapshot: &'a mut Snapshot, set: &SetRef) -> &'a mut SetSnapshot {
+            let id = match set {
+                SetRef::Existing(id) => id.clone(),
+                SetRef::New(name) => format!("new-{}", name),
+            };
+            snapshot.sets.iter_mut().find(|set| set.id == id).unwrap()
+        }
+        fn protocols<'a>(
+            snapshot: &'a mut Snapshot,
+            service: &'a ServiceRef,
+            protocol_type: &'a SCNetworkProtocolType,
+        ) -> impl Iterator<Item = &'a mut ProtocolSpec> + 'a {
+            services(snapshot, service)
+                .flat_map(|service| &mut service.protocols)
+                .filter(move |protocol| protocol.protocol_type == *protocol_type)
+        }
+
+        let mut created = Vec::new();
+        for change in &plan.changes {
+            match change {
+                Change::CreateSet { name } => snapshot.sets.push(SetSnapshot {
+                    id: format!("new-{}", name),
+                    name: Some(name.clone()),
+                    services: Vec::new(),
+                }),
+                Change::CreateService {
+                    set: set_ref,
+                    name,
+                    interface,
+                } => {
+                    // New services get the protocols their interface supports from
+                    // `SCNetworkServiceEstablishDefaultConfiguration`, Ethernet services the
+                    // ones below.
+                    let defaults = match snapshot
+                        .interfaces
+                        .iter()
+                        .find(|live| live.interface == *interface)
+                    {
+                        Some(live) => live.protocols.clone(),
+                        None => {
+                            assert_eq!(interface.interface_type, SCNetworkInterfaceType::Ethernet);
+                            vec![
+                                SCNetworkProtocolType::DNS,
+                                SCNetworkProtocolType::IPv4,
+                                SCNetworkProtocolType::IPv6,
+                                SCNetworkProtocolType::Proxies,
+                                SCNetworkProtocolType::SMB,
+                            ]
+                        }
+                    };
+                    let service = ServiceSnapshot {
+                        id: new_service_id(set_ref, name),
+                        name: Some(name.clone()),
+                        enabled: true,
+                        interface: Some(interface.clone()),
+                        protocols: defaults
+                            .into_iter()
+                            .map(|protocol_type| {
+                                ProtocolSpec::new(protocol_type)
+                                    .with_configuration(Dictionary::new())
+                            })
+                            .collect(),
+                    };
+                    created.push(service.clone());
+                    set(snapshot, set_ref).services.push(service);
+                }
+                Change::RemoveService {
+                    set: set_ref,
+                    service,
+                } => {
+                    set(snapshot, set_ref).services.retain(|s| s.id != *service);
+                }
+                Change::DeleteService { .. } => {}
+                Change::SetServiceEnabled { service, enabled } => {
+                    services(snapshot, service).for_each(|service| service.enabled = *enabled);
+                }
+                Change::AddProtocol {
+                    service,
+                    protocol_type,
+                } => {
+                    for service in services(snapshot, service) {
+                        if !service
+                            .protocols
+                            .iter()
+                            .any(|protocol| protocol.protocol_type == *protocol_type)
+                        {
+                            service
+                                .protocols
+                                .push(ProtocolSpec::new(protocol_type.clone()));
+                        }
+                    }
+                }
+                Change::SetProtocolConfiguration {
+                    service,
+                    protocol_type,
+                    configuration,
+                } => protocols(snapshot, service, protocol_type)
+                    .for_each(|protocol| protocol.configuration = Some(configuration.clone())),
+                Change::SetProtocolEnabled {
+                    service,
+                    protocol_type,
+                    enabled,
+                } => protocols(snapshot, service, protocol_type)
+                    .for_each(|protocol| protocol.enabled = *enabled),
+                Change::RemoveProtocol {
+                    service,
+                    protocol_type,
+                } => services(snapshot, service).for_each(|service| {
+                    service
+                        .protocols
+                        .retain(|protocol| protocol.protocol_type != *protocol_type)
+                }),
+                Change::SetServiceOrder {
+                    set: set_ref,
+                    order,
+                } => {
+                    let ids = order
+                        .iter()
+                        .map(|service| match service {
+                            ServiceRef::Existing(id) => id.clone(),
+                            ServiceRef::New { set, name } => new_service_id(set, name),
+                        })
+                        .collect::<Vec<_>>();
+                    set(snapshot, set_ref)
+                        .services
+                        .sort_by_key(|service| ids.iter().position(|id| *id == service.id));
+                }
+                Change::SetCurrentSet { set: set_ref } => {
+                    snapshot.current_set = Some(set(snapshot, set_ref).id.clone());
+                }
+                Change::RemoveSet { set } => snapshot.sets.retain(|s| s.id != *set),
+            }
+        }
+    }
+
+    #[test]
+    fn executed_plans_converge() {
+        let specs = [
+            NetworkConfigSpec {
+                sets: vec![SetSpec::new("Travel").with_service(
+                    ServiceSpec::new("iPhone", ethernet("en5"))
+                        .with_protocol(
+                            ProtocolSpec::new(SCNetworkProtocolType::DNS)
+                                .with_configuration(dns("1.1.1.1")),
+                        )
+                        .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv4))
+                        .with_protocol(
+                            ProtocolSpec::new(SCNetworkProtocolType::IPv6).enabled(false),
+                        ),
+                )],
+                current_set: Some("Travel".to_owned()),
+                prune: true,
+            },
+            NetworkConfigSpec {
+                sets: vec![home_spec().with_service(ServiceSpec::new("Dock", ethernet("en7")))],
+                current_set: Some("Home".to_owned()),
+                prune: false,
+            },
+            NetworkConfigSpec {
+                sets: vec![SetSpec::new("Office").with_service(
+                    ServiceSpec::new("Ethernet", ethernet("en2"))
+                        .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv4)),
+                )],
+                current_set: Some("Office".to_owned()),
+                prune: true,
+            },
+            NetworkConfigSpec {
+                sets: vec![SetSpec::new("Travel").with_service(
+                    ServiceSpec::new("Cellular", wwan())
+                        .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv4)),
+                )],
+                current_set: Some("Travel".to_owned()),
+                prune: true,
+            },
+        ];
+        for spec in specs {
+            let mut snapshot = snapshot();
+            let plan = spec.plan(&snapshot).unwrap();
+            assert!(!plan.is_empty());
+            execute(&plan, &mut snapshot);
+            assert_eq!(spec.plan(&snapshot), Ok(Plan::default()), "{:#?}", plan);
+        }
+    }
+
+    #[test]
+    fn prune_default_protocols_of_new_services() {
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Office").with_service(
+                ServiceSpec::new("Ethernet", ethernet("en2"))
+                    .with_protocol(ProtocolSpec::new(SCNetworkProtocolType::IPv4)),
+            )],
+            current_set: Some("Office".to_owned()),
+            prune: true,
+        };
+        let service = ServiceRef::New {
+            set: SetRef::Existing("set-office".to_owned()),
+            name: "Ethernet".to_owned(),
+        };
+        let removed = spec
+            .plan(&snapshot())
+            .unwrap()
+            .changes
+            .into_iter()
+            .filter_map(|change| match change {
+                Change::RemoveProtocol {
+                    service: removed_from,
+                    protocol_type,
+                } if removed_from == service => Some(protocol_type),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            removed,
+            [
+                SCNetworkProtocolType::DNS,
+                SCNetworkProtocolType::IPv6,
+                SCNetworkProtocolType::Proxies,
+                SCNetworkProtocolType::SMB,
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_unlisted_services_last() {
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Home").with_service(ServiceSpec::new(
+                "Wi-Fi",
+                InterfaceSpec::new(SCNetworkInterfaceType::IEEE80211),
+            ))],
+            ..Default::default()
+        };
+        let home = SetRef::Existing("set-home".to_owned());
+        let plan = spec.plan(&snapshot()).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![Change::SetServiceOrder {
+                set: home.clone(),
+                order: vec![existing("svc-wifi"), existing("svc-eth")],
+            }]
+        );
+
+        let spec = NetworkConfigSpec {
+            sets: vec![
+                SetSpec::new("Home").with_service(ServiceSpec::new("Thunderbolt", ethernet("en3")))
+            ],
+            ..Default::default()
+        };
+        let plan = spec.plan(&snapshot()).unwrap();
+        assert_eq!(
+            plan.changes.last(),
+            Some(&Change::SetServiceOrder {
+                set: home.clone(),
+                order: vec![
+                    ServiceRef::New {
+                        set: home,
+                        name: "Thunderbolt".to_owned(),
+                    },
+                    existing("svc-eth"),
+                    existing("svc-wifi"),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Home"), SetSpec::new("Home")],
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.plan(&snapshot()),
+            Err(PlanError::DuplicateSet("Home".to_owned()))
+        );
+
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Home")
+                .with_service(ServiceSpec::new("Ethernet", ethernet("en0")))
+                .with_service(ServiceSpec::new("Ethernet", ethernet("en1")))],
+            ..Default::default()
+        };
+        assert!(matches!(
+            spec.plan(&snapshot()),
+            Err(PlanError::DuplicateService { .. })
+        ));
+
+        let spec = NetworkConfigSpec {
+            current_set: Some("Moon".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.plan(&snapshot()),
+            Err(PlanError::UnknownCurrentSet("Moon".to_owned()))
+        );
+
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Office")],
+            current_set: None,
+            prune: true,
+        };
+        assert_eq!(
+            spec.plan(&snapshot()),
+            Err(PlanError::CurrentSetRemoved("Home".to_owned()))
+        );
+
+        let mut snapshot = snapshot();
+        snapshot.sets[1].name = Some("Home".to_owned());
+        let spec = NetworkConfigSpec {
+            sets: vec![SetSpec::new("Home")],
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.plan(&snapshot),
+            Err(PlanError::AmbiguousSet("Home".to_owned()))
+        );
+    }
+
+    #[test]
+    fn protocol_configuration_from_model() {
+        let protocol = ProtocolSpec::new(SCNetworkProtocolType::DNS).with_model(&DnsConfig {
+            domain_name: Some("example.com".to_owned()),
+            ..Default::default()
+        });
+        assert_eq!(
+            protocol
+                .configuration
+                .unwrap()
+                .get("DomainName")
+                .and_then(PlistValue::as_str),
+            Some("example.com")
+        );
+    }
+}
@@ -12,14 +12,27 @@
 //!
 //! [`SCDynamicStore`]: https://developer.apple.com/documentation/systemconfiguration/scdynamicstore?language=objc
 
-use crate::sys::{
-    dynamic_store::{
-        kSCDynamicStoreUseSessionKeys, SCDynamicStoreCallBack, SCDynamicStoreContext,
-        SCDynamicStoreCopyKeyList, SCDynamicStoreCopyValue, SCDynamicStoreCreateRunLoopSource,
-        SCDynamicStoreCreateWithOptions, SCDynamicStoreGetTypeID, SCDynamicStoreRef,
-        SCDynamicStoreRemoveValue, SCDynamicStoreSetNotificationKeys, SCDynamicStoreSetValue,
+mod backend;
+pub mod key;
+mod memory;
+mod pattern;
+#[cfg(feature = "async")]
+pub mod watch;
+
+pub use backend::*;
+pub use memory::*;
+
+use crate::{
+    error::{to_result, SCError},
+    sys::{
+        dynamic_store::{
+            kSCDynamicStoreUseSessionKeys, SCDynamicStoreCallBack, SCDynamicStoreContext,
+            SCDynamicStoreCopyKeyList, SCDynamicStoreCopyValue, SCDynamicStoreCreateRunLoopSource,
+            SCDynamicStoreCreateWithOptions, SCDynamicStoreGetTypeID, SCDynamicStoreRef,
+            SCDynamicStoreRemoveValue, SCDynamicStoreSetNotificationKeys, SCDynamicStoreSetValue,
+        },
+        dynamic_store_copy_specific::SCDynamicStoreCopyProxies,
     },
-    dynamic_store_copy_specific::SCDynamicStoreCopyProxies,
 };
 use core_foundation::{
     array::{CFArray, CFArrayRef},
@@ -51,6 +64,11 @@ pub struct SCDynamicStoreCallBackContext<T> {
 pub type SCDynamicStoreCallBackT<T> =
     fn(store: SCDynamicStore, changed_keys: CFArray<CFString>, info: &mut T);
 
+/// Callback state of sessions created with a closure through [`SCDynamicStoreBuilder::callback`].
+pub struct SCDynamicStoreClosure(Box<ClosureCallBack>);
+
+type ClosureCallBack = dyn FnMut(&SCDynamicStore, &[CFString]) + Send;
+
 /// Builder for [`SCDynamicStore`] sessions.
 ///
 /// [`SCDynamicStore`]: struct.SCDynamicStore.html
@@ -101,6 +119,22 @@ impl<T> SCDynamicStoreBuilder<T> {
         }
     }
 
+    /// Set a closure to call when a watched value in the dynamic store is changed. The closure
+    /// gets the session and the keys that changed, and can keep state between calls in its
+    /// captures.
+    ///
+    /// The closure is dropped when the session is released, just like the `info` of a
+    /// [`callback_context`](Self::callback_context). Replaces any previously set callback.
+    pub fn callback<F>(self, callback: F) -> SCDynamicStoreBuilder<SCDynamicStoreClosure>
+    where
+        F: FnMut(&SCDynamicStore, &[CFString]) + Send + 'static,
+    {
+        self.callback_context(SCDynamicStoreCallBackContext {
+            callout: call_closure,
+            info: SCDynamicStoreClosure(Box::new(callback)),
+        })
+    }
+
     /// Create the dynamic store session.
     pub fn build(mut self) -> SCDynamicStore {
         let store_options = self.create_store_options();
@@ -223,14 +257,20 @@ impl SCDynamicStore {
     }
 
     /// Sets the value of the given key. Overwrites existing values.
-    /// Returns `true` on success, false on failure.
-    pub fn set<S: Into<CFString>, V: CFPropertyListSubClass>(&self, key: S, value: V) -> bool {
+    pub fn set<S: Into<CFString>, V: CFPropertyListSubClass>(
+        &self,
+        key: S,
+        value: V,
+    ) -> Result<(), SCError> {
         self.set_raw(key, &value.into_CFPropertyList())
     }
 
     /// Sets the value of the given key. Overwrites existing values.
-    /// Returns `true` on success, false on failure.
-    pub fn set_raw<S: Into<CFString>>(&self, key: S, value: &CFPropertyList) -> bool {
+    pub fn set_raw<S: Into<CFString>>(
+        &self,
+        key: S,
+        value: &CFPropertyList,
+    ) -> Result<(), SCError> {
         let cf_key = key.into();
         let success = unsafe {
             SCDynamicStoreSetValue(
@@ -239,16 +279,16 @@ impl SCDynamicStore {
                 value.as_concrete_TypeRef(),
             )
         };
-        success != 0
+        to_result(success)
     }
 
     /// Removes the value of the specified key from the dynamic store.
-    pub fn remove<S: Into<CFString>>(&self, key: S) -> bool {
+    pub fn remove<S: Into<CFString>>(&self, key: S) -> Result<(), SCError> {
         let cf_key = key.into();
         let success = unsafe {
             SCDynamicStoreRemoveValue(self.as_concrete_TypeRef(), cf_key.as_concrete_TypeRef())
         };
-        success != 0
+        to_result(success)
     }
 
     /// Specifies a set of keys and key patterns that should be monitored for changes.
@@ -256,7 +296,7 @@ impl SCDynamicStore {
         &self,
         keys: &CFArray<T1>,
         patterns: &CFArray<T2>,
-    ) -> bool {
+    ) -> Result<(), SCError> {
         let success = unsafe {
             SCDynamicStoreSetNotificationKeys(
                 self.as_concrete_TypeRef(),
@@ -264,7 +304,7 @@ impl SCDynamicStore {
                 patterns.as_concrete_TypeRef(),
             )
         };
-        success != 0
+        to_result(success)
     }
 
     /// Creates a run loop source object that can be added to the application's run loop.
@@ -280,6 +320,14 @@ impl SCDynamicStore {
     }
 }
 
+fn to_cf_strings(strings: &[String]) -> CFArray<CFString> {
+    let strings = strings
+        .iter()
+        .map(|string| CFString::new(string))
+        .collect::<Vec<_>>();
+    CFArray::from_CFTypes(&strings)
+}
+
 /// The raw callback used by the safe `SCDynamicStore` to convert from the `SCDynamicStoreCallBack`
 /// to the `SCDynamicStoreCallBackT`
 unsafe extern "C" fn convert_callback<T>(
@@ -294,6 +342,18 @@ unsafe extern "C" fn convert_callback<T>(
     (context.callout)(store, changed_keys, &mut context.info);
 }
 
+fn call_closure(
+    store: SCDynamicStore,
+    changed_keys: CFArray<CFString>,
+    closure: &mut SCDynamicStoreClosure,
+) {
+    let changed_keys = changed_keys
+        .iter()
+        .map(|key| key.clone())
+        .collect::<Vec<_>>();
+    (closure.0)(&store, &changed_keys);
+}
+
 // Release function called by core foundation on release of the dynamic store context.
 unsafe extern "C" fn release_callback_context<T>(context_ptr: *const c_void) {
     // Bring back the context object from raw ptr so it is correctly freed.
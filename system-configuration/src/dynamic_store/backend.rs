@@ -0,0 +1,85 @@
+use super::{to_cf_strings, SCDynamicStore};
+use crate::{
+    error::{to_result, SCError},
+    plist::{dictionary_to_cf, Dictionary, PlistValue},
+    sys::dynamic_store::SCDynamicStoreSetMultiple,
+};
+use core_foundation::base::TCFType;
+
+/// The operations of a dynamic store session, in terms of pure Rust values.
+///
+/// Implemented by [`SCDynamicStore`] and by [`MemoryDynamicStore`], so code written against this
+/// trait can be tested without the System Configuration server. The methods behave like the
+/// `SCDynamicStore` functions of the same name, including the [`SCError`] they fail with.
+///
+/// [`MemoryDynamicStore`]: super::MemoryDynamicStore
+pub trait DynamicStoreBackend {
+    /// Returns the keys matching `pattern`, a POSIX extended regular expression that may match
+    /// anywhere in a key. Fails with [`SCError::Failed`] if the pattern is invalid.
+    fn get_keys(&self, pattern: &str) -> Result<Vec<String>, SCError>;
+
+    /// Returns the value of a key.
+    fn get(&self, key: &str) -> Option<PlistValue>;
+
+    /// Sets the value of a key, replacing the current value.
+    fn set(&self, key: &str, value: &PlistValue) -> Result<(), SCError>;
+
+    /// Removes the value of a key. Fails with [`SCError::NoKey`] if the key has no value.
+    fn remove(&self, key: &str) -> Result<(), SCError>;
+
+    /// Sets and removes several keys at once and notifies the watchers of the keys in `notify`,
+    /// whether or not they changed. Watchers receive all of these changes in one notification.
+    fn set_multiple(
+        &self,
+        set: &Dictionary,
+        remove: &[String],
+        notify: &[String],
+    ) -> Result<(), SCError>;
+
+    /// Replaces the keys and key patterns this session is notified about. Fails with
+    /// [`SCError::Failed`] if one of the patterns is invalid.
+    fn set_notification_keys(&self, keys: &[String], patterns: &[String]) -> Result<(), SCError>;
+}
+
+impl DynamicStoreBackend for SCDynamicStore {
+    fn get_keys(&self, pattern: &str) -> Result<Vec<String>, SCError> {
+        SCDynamicStore::get_keys(self, pattern)
+            .map(|keys| keys.iter().map(|key| key.to_string()).collect())
+            .ok_or_else(SCError::last)
+    }
+
+    fn get(&self, key: &str) -> Option<PlistValue> {
+        SCDynamicStore::get(self, key).and_then(|value| PlistValue::from_property_list(&value))
+    }
+
+    fn set(&self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        SCDynamicStore::set_raw(self, key, &value.to_property_list())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), SCError> {
+        SCDynamicStore::remove(self, key)
+    }
+
+    fn set_multiple(
+        &self,
+        set: &Dictionary,
+        remove: &[String],
+        notify: &[String],
+    ) -> Result<(), SCError> {
+        let set = dictionary_to_cf(set);
+        let remove = to_cf_strings(remove);
+        let notify = to_cf_strings(notify);
+        to_result(unsafe {
+            SCDynamicStoreSetMultiple(
+                self.as_concrete_TypeRef(),
+                set.as_concrete_TypeRef(),
+                remove.as_concrete_TypeRef(),
+                notify.as_concrete_TypeRef(),
+            )
+        })
+    }
+
+    fn set_notification_keys(&self, keys: &[String], patterns: &[String]) -> Result<(), SCError> {
+        SCDynamicStore::set_notification_keys(self, &to_cf_strings(keys), &to_cf_strings(patterns))
+    }
+}
@@ -0,0 +1,807 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Building and parsing of [`SCDynamicStore`] keys and key patterns.
+//!
+//! Keys are built the same way as the [`SCDynamicStoreKey`] functions do, out of the
+//! `kSCDynamicStoreDomain*`, `kSCComp*` and `kSCEnt*` schema definitions. The strings behind those
+//! constants are mirrored here so keys can be built and parsed without calling into the framework.
+//!
+//! [`SCDynamicStore`]: super::SCDynamicStore
+//! [`SCDynamicStoreKey`]: https://developer.apple.com/documentation/systemconfiguration/scdynamicstorekey?language=objc
+
+use core_foundation::string::CFString;
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// Regular expression matching any single key segment, the value of `kSCCompAnyRegex`.
+pub const ANY_SEGMENT_PATTERN: &str = "[^/]+";
+
+/// The `kSCCompNetwork` component that all network keys are nested under.
+const NETWORK: &str = "Network";
+
+/// The domain a dynamic store key lives in, the `kSCDynamicStoreDomain*` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Domain {
+    /// `kSCDynamicStoreDomainFile`.
+    File,
+    /// `kSCDynamicStoreDomainPlugin`.
+    Plugin,
+    /// `kSCDynamicStoreDomainSetup`, the configuration the system was told to use.
+    Setup,
+    /// `kSCDynamicStoreDomainState`, the configuration the system is actually using.
+    State,
+    /// `kSCDynamicStoreDomainPrefs`.
+    Prefs,
+}
+
+impl Domain {
+    /// All known domains.
+    pub const ALL: [Domain; 5] = [
+        Domain::File,
+        Domain::Plugin,
+        Domain::Setup,
+        Domain::State,
+        Domain::Prefs,
+    ];
+
+    /// Returns the string this domain is represented by in keys, including the trailing colon.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Domain::File => "File:",
+            Domain::Plugin => "Plugin:",
+            Domain::Setup => "Setup:",
+            Domain::State => "State:",
+            Domain::Prefs => "Prefs:",
+        }
+    }
+
+    fn from_key_prefix(prefix: &str) -> Option<Self> {
+        Domain::ALL
+            .into_iter()
+            .find(|domain| domain.as_str() == prefix)
+    }
+}
+
+impl Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The component of a dynamic store key following the domain, the `kSCComp*` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Component {
+    /// `kSCCompGlobal`, global network entities. Keys look like `State:/Network/Global/IPv4`.
+    Global,
+    /// `kSCCompInterface`, per interface entities. Keys look like
+    /// `State:/Network/Interface/en0/Link`.
+    Interface,
+    /// `kSCCompService`, per service entities. Keys look like
+    /// `State:/Network/Service/<service id>/DNS`.
+    Service,
+    /// `kSCCompHostNames`, the `Setup:/Network/HostNames` key.
+    HostNames,
+    /// `kSCCompSystem`, the `Setup:/System` key holding the computer name.
+    System,
+    /// `kSCCompUsers`, user entities such as `State:/Users/ConsoleUser`.
+    Users,
+}
+
+impl Component {
+    /// All known components.
+    pub const ALL: [Component; 6] = [
+        Component::Global,
+        Component::Interface,
+        Component::Service,
+        Component::HostNames,
+        Component::System,
+        Component::Users,
+    ];
+
+    /// Returns the string this component is represented by in keys.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Component::Global => "Global",
+            Component::Interface => "Interface",
+            Component::Service => "Service",
+            Component::HostNames => "HostNames",
+            Component::System => "System",
+            Component::Users => "Users",
+        }
+    }
+
+    /// Returns `true` if keys for this component are nested under the `Network` component.
+    fn is_network(&self) -> bool {
+        matches!(
+            self,
+            Component::Global | Component::Interface | Component::Service | Component::HostNames
+        )
+    }
+
+    /// Returns `true` if keys for this component can have an identifier segment.
+    fn has_id(&self) -> bool {
+        matches!(self, Component::Interface | Component::Service)
+    }
+
+    fn from_segment(segment: &str, network: bool) -> Option<Self> {
+        Component::ALL
+            .into_iter()
+            .find(|component| component.is_network() == network && component.as_str() == segment)
+    }
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The entity at the end of a dynamic store key, the `kSCEnt*` constants.
+///
+/// Entities compare by the string they are represented by in keys, so an [`Other`] holding the
+/// name of a known entity is equal to that entity.
+///
+/// [`Other`]: Entity::Other
+#[derive(Debug, Clone)]
+#[allow(missing_docs)]
+pub enum Entity {
+    AirPort,
+    DHCP,
+    DNS,
+    Ethernet,
+    FireWire,
+    Interface,
+    IPSec,
+    IPv4,
+    IPv6,
+    L2TP,
+    Link,
+    Modem,
+    PPP,
+    PPPoE,
+    PPPSerial,
+    PPTP,
+    Proxies,
+    SMB,
+    SixToFour,
+    /// `kSCEntUsersConsoleUser`.
+    ConsoleUser,
+    /// An entity not known to this crate, the rest of the key after the component and the
+    /// identifier. It can span several `/`-separated segments, none of which may be empty.
+    Other(String),
+}
+
+impl Entity {
+    /// All entities known to this crate.
+    pub const KNOWN: [Entity; 20] = [
+        Entity::AirPort,
+        Entity::DHCP,
+        Entity::DNS,
+        Entity::Ethernet,
+        Entity::FireWire,
+        Entity::Interface,
+        Entity::IPSec,
+        Entity::IPv4,
+        Entity::IPv6,
+        Entity::L2TP,
+        Entity::Link,
+        Entity::Modem,
+        Entity::PPP,
+        Entity::PPPoE,
+        Entity::PPPSerial,
+        Entity::PPTP,
+        Entity::Proxies,
+        Entity::SMB,
+        Entity::SixToFour,
+        Entity::ConsoleUser,
+    ];
+
+    /// Returns the string this entity is represented by in keys.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Entity::AirPort => "AirPort",
+            Entity::DHCP => "DHCP",
+            Entity::DNS => "DNS",
+            Entity::Ethernet => "Ethernet",
+            Entity::FireWire => "FireWire",
+            Entity::Interface => "Interface",
+            Entity::IPSec => "IPSec",
+            Entity::IPv4 => "IPv4",
+            Entity::IPv6 => "IPv6",
+            Entity::L2TP => "L2TP",
+            Entity::Link => "Link",
+            Entity::Modem => "Modem",
+            Entity::PPP => "PPP",
+            Entity::PPPoE => "PPPoE",
+            Entity::PPPSerial => "PPPSerial",
+            Entity::PPTP => "PPTP",
+            Entity::Proxies => "Proxies",
+            Entity::SMB => "SMB",
+            Entity::SixToFour => "6to4",
+            Entity::ConsoleUser => "ConsoleUser",
+            Entity::Other(entity) => entity,
+        }
+    }
+}
+
+impl From<&str> for Entity {
+    fn from(entity: &str) -> Self {
+        Entity::KNOWN
+            .into_iter()
+            .find(|known| known.as_str() == entity)
+            .unwrap_or_else(|| Entity::Other(entity.to_owned()))
+    }
+}
+
+impl PartialEq for Entity {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Entity {}
+
+impl Hash for Entity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialOrd for Entity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A typed dynamic store key.
+///
+/// Use the constructors to build the keys the `SCDynamicStoreKeyCreate*` functions build, and
+/// [`str::parse`] to turn an existing key back into its parts. The string form of the key is
+/// available through the [`Display`] implementation, and it converts into a [`CFString`], so it
+/// can be passed directly to methods such as [`SCDynamicStore::get`].
+///
+/// [`SCDynamicStore::get`]: super::SCDynamicStore::get
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DynamicStoreKey {
+    /// The domain of the key.
+    pub domain: Domain,
+    /// The component of the key.
+    pub component: Component,
+    /// The interface name or service identifier, for [`Component::Interface`] and
+    /// [`Component::Service`] keys.
+    pub id: Option<String>,
+    /// The entity the key points to, if any.
+    pub entity: Option<Entity>,
+}
+
+impl DynamicStoreKey {
+    /// Key for a global network entity, such as `State:/Network/Global/IPv4`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateNetworkGlobalEntity`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateNetworkGlobalEntity`]: https://developer.apple.com/documentation/systemconfiguration/1437811-scdynamicstorekeycreatenetworkgl?language=objc
+    pub fn network_global_entity(domain: Domain, entity: Entity) -> Self {
+        DynamicStoreKey {
+            domain,
+            component: Component::Global,
+            id: None,
+            entity: Some(entity),
+        }
+    }
+
+    /// Key for the list of network interfaces, such as `State:/Network/Interface`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateNetworkInterface`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateNetworkInterface`]: https://developer.apple.com/documentation/systemconfiguration/1437818-scdynamicstorekeycreatenetworkin?language=objc
+    pub fn network_interface(domain: Domain) -> Self {
+        DynamicStoreKey {
+            domain,
+            component: Component::Interface,
+            id: None,
+            entity: None,
+        }
+    }
+
+    /// Key for an entity of a network interface, such as `State:/Network/Interface/en0/Link`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateNetworkInterfaceEntity`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateNetworkInterfaceEntity`]: https://developer.apple.com/documentation/systemconfiguration/1437827-scdynamicstorekeycreatenetworkin?language=objc
+    pub fn network_interface_entity(
+        domain: Domain,
+        interface_name: impl Into<String>,
+        entity: Option<Entity>,
+    ) -> Self {
+        DynamicStoreKey {
+            domain,
+            component: Component::Interface,
+            id: Some(interface_name.into()),
+            entity,
+        }
+    }
+
+    /// Key for an entity of a network service, such as `State:/Network/Service/<service id>/DNS`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateNetworkServiceEntity`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateNetworkServiceEntity`]: https://developer.apple.com/documentation/systemconfiguration/1437822-scdynamicstorekeycreatenetworkse?language=objc
+    pub fn network_service_entity(
+        domain: Domain,
+        service_id: impl Into<String>,
+        entity: Option<Entity>,
+    ) -> Self {
+        DynamicStoreKey {
+            domain,
+            component: Component::Service,
+            id: Some(service_id.into()),
+            entity,
+        }
+    }
+
+    /// Key for the host names, `Setup:/Network/HostNames`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateHostNames`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateHostNames`]: https://developer.apple.com/documentation/systemconfiguration/1437823-scdynamicstorekeycreatehostnames?language=objc
+    pub fn host_names() -> Self {
+        DynamicStoreKey {
+            domain: Domain::Setup,
+            component: Component::HostNames,
+            id: None,
+            entity: None,
+        }
+    }
+
+    /// Key for the computer name, `Setup:/System`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateComputerName`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateComputerName`]: https://developer.apple.com/documentation/systemconfiguration/1437816-scdynamicstorekeycreatecomputern?language=objc
+    pub fn computer_name() -> Self {
+        DynamicStoreKey {
+            domain: Domain::Setup,
+            component: Component::System,
+            id: None,
+            entity: None,
+        }
+    }
+
+    /// Key for the console user, `State:/Users/ConsoleUser`.
+    ///
+    /// See [`SCDynamicStoreKeyCreateConsoleUser`] for details.
+    ///
+    /// [`SCDynamicStoreKeyCreateConsoleUser`]: https://developer.apple.com/documentation/systemconfiguration/1437830-scdynamicstorekeycreateconsoleus?language=objc
+    pub fn console_user() -> Self {
+        DynamicStoreKey {
+            domain: Domain::State,
+            component: Component::Users,
+            id: None,
+            entity: Some(Entity::ConsoleUser),
+        }
+    }
+
+    /// Returns a regular expression matching exactly this key. Useful for
+    /// [`SCDynamicStore::set_notification_keys`] and [`SCDynamicStore::get_keys`].
+    ///
+    /// [`SCDynamicStore::set_notification_keys`]: super::SCDynamicStore::set_notification_keys
+    /// [`SCDynamicStore::get_keys`]: super::SCDynamicStore::get_keys
+    pub fn to_pattern(&self) -> String {
+        format!("^{}$", escape_pattern(&self.to_string()))
+    }
+}
+
+impl Display for DynamicStoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.domain)?;
+        if self.component.is_network() {
+            write!(f, "/{}", NETWORK)?;
+        }
+        write!(f, "/{}", self.component)?;
+        if let Some(id) = &self.id {
+            write!(f, "/{}", id)?;
+        }
+        if let Some(entity) = &self.entity {
+            write!(f, "/{}", entity)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DynamicStoreKey {
+    type Err = ParseKeyError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseKeyError::InvalidLayout(key.to_owned());
+
+        let colon = key.find(':').ok_or_else(invalid)?;
+        let (prefix, path) = key.split_at(colon + 1);
+        let domain = Domain::from_key_prefix(prefix)
+            .ok_or_else(|| ParseKeyError::UnknownDomain(prefix.to_owned()))?;
+
+        let mut segments = path.strip_prefix('/').ok_or_else(invalid)?.split('/');
+        let mut segment = segments.next().ok_or_else(invalid)?;
+        let network = segment == NETWORK;
+        if network {
+            segment = segments.next().ok_or_else(invalid)?;
+        }
+        let component = Component::from_segment(segment, network)
+            .ok_or_else(|| ParseKeyError::UnknownComponent(segment.to_owned()))?;
+
+        let rest = segments.collect::<Vec<_>>();
+        if rest.iter().any(|segment| segment.is_empty()) {
+            return Err(invalid());
+        }
+        let (id, entity) = match (component, rest.as_slice()) {
+            (Component::Interface | Component::Service, []) => (None, None),
+            (Component::Interface | Component::Service, [id]) => (Some(*id), None),
+            (Component::Interface | Component::Service, [id, entity @ ..]) => {
+                (Some(*id), Some(entity.join("/")))
+            }
+            (Component::Global | Component::Users, [_, ..]) => (None, Some(rest.join("/"))),
+            (Component::HostNames | Component::System, []) => (None, None),
+            _ => return Err(invalid()),
+        };
+
+        Ok(DynamicStoreKey {
+            domain,
+            component,
+            id: id.map(str::to_owned),
+            entity: entity.as_deref().map(Entity::from),
+        })
+    }
+}
+
+impl From<&DynamicStoreKey> for CFString {
+    fn from(key: &DynamicStoreKey) -> Self {
+        CFString::new(&key.to_string())
+    }
+}
+
+impl From<DynamicStoreKey> for CFString {
+    fn from(key: DynamicStoreKey) -> Self {
+        CFString::from(&key)
+    }
+}
+
+/// Pattern matching the given entity of any network interface, such as
+/// `State:/Network/Interface/[^/]+/Link`.
+///
+/// Built the same way as passing `kSCCompAnyRegex` as the interface name to
+/// [`SCDynamicStoreKeyCreateNetworkInterfaceEntity`].
+///
+/// [`SCDynamicStoreKeyCreateNetworkInterfaceEntity`]: https://developer.apple.com/documentation/systemconfiguration/1437827-scdynamicstorekeycreatenetworkin?language=objc
+pub fn network_interface_entity_pattern(domain: Domain, entity: &Entity) -> String {
+    any_id_pattern(domain, Component::Interface, entity)
+}
+
+/// Pattern matching the given entity of any network service, such as
+/// `State:/Network/Service/[^/]+/DNS`.
+///
+/// Built the same way as passing `kSCCompAnyRegex` as the service identifier to
+/// [`SCDynamicStoreKeyCreateNetworkServiceEntity`].
+///
+/// [`SCDynamicStoreKeyCreateNetworkServiceEntity`]: https://developer.apple.com/documentation/systemconfiguration/1437822-scdynamicstorekeycreatenetworkse?language=objc
+pub fn network_service_entity_pattern(domain: Domain, entity: &Entity) -> String {
+    any_id_pattern(domain, Component::Service, entity)
+}
+
+fn any_id_pattern(domain: Domain, component: Component, entity: &Entity) -> String {
+    debug_assert!(component.has_id());
+    format!(
+        "^{}/{}/{}/{}/{}$",
+        escape_pattern(domain.as_str()),
+        NETWORK,
+        component,
+        ANY_SEGMENT_PATTERN,
+        escape_pattern(entity.as_str())
+    )
+}
+
+/// Escapes all characters with a special meaning in POSIX extended regular expressions.
+fn escape_pattern(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Failure to parse a dynamic store key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseKeyError {
+    /// The key does not start with a known `kSCDynamicStoreDomain*` prefix.
+    UnknownDomain(String),
+    /// The key does not contain a known `kSCComp*` component.
+    UnknownComponent(String),
+    /// The segments of the key do not match the layout of any key this module builds.
+    InvalidLayout(String),
+}
+
+impl Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownDomain(domain) => write!(f, "Unknown dynamic store domain: {}", domain),
+            Self::UnknownComponent(component) => {
+                write!(f, "Unknown dynamic store key component: {}", component)
+            }
+            Self::InvalidLayout(key) => write!(f, "Invalid dynamic store key: {}", key),
+        }
+    }
+}
+
+impl Error for ParseKeyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_entities() -> Vec<Entity> {
+        let mut entities = Entity::KNOWN.to_vec();
+        entities.push(Entity::Other("NetInfo".to_owned()));
+        entities.push(Entity::Other("IPv4".to_owned()));
+        entities.push(Entity::Other("IPv4/Extra".to_owned()));
+        entities
+    }
+
+    fn assert_round_trip(key: &DynamicStoreKey) {
+        let string = key.to_string();
+        assert_eq!(
+            string.parse::<DynamicStoreKey>().as_ref(),
+            Ok(key),
+            "{}",
+            string
+        );
+    }
+
+    #[test]
+    fn builds_framework_keys() {
+        let keys = [
+            (
+                DynamicStoreKey::network_global_entity(Domain::State, Entity::IPv4),
+                "State:/Network/Global/IPv4",
+            ),
+            (
+                DynamicStoreKey::network_interface(Domain::State),
+                "State:/Network/Interface",
+            ),
+            (
+                DynamicStoreKey::network_interface_entity(Domain::State, "en0", Some(Entity::Link)),
+                "State:/Network/Interface/en0/Link",
+            ),
+            (
+                DynamicStoreKey::network_service_entity(Domain::Setup, "ABC-123", None),
+                "Setup:/Network/Service/ABC-123",
+            ),
+            (
+                DynamicStoreKey::network_service_entity(Domain::State, "ABC", Some(Entity::DNS)),
+                "State:/Network/Service/ABC/DNS",
+            ),
+            (
+                DynamicStoreKey::network_global_entity(Domain::State, Entity::SixToFour),
+                "State:/Network/Global/6to4",
+            ),
+            (DynamicStoreKey::host_names(), "Setup:/Network/HostNames"),
+            (DynamicStoreKey::computer_name(), "Setup:/System"),
+            (DynamicStoreKey::console_user(), "State:/Users/ConsoleUser"),
+        ];
+        for (key, expected) in keys {
+            assert_eq!(key.to_string(), expected);
+            assert_round_trip(&key);
+        }
+    }
+
+    #[test]
+    fn round_trip_all_combinations() {
+        for domain in Domain::ALL {
+            for entity in all_entities() {
+                assert_round_trip(&DynamicStoreKey::network_global_entity(
+                    domain,
+                    entity.clone(),
+                ));
+                assert_round_trip(&DynamicStoreKey::network_interface_entity(
+                    domain,
+                    "en0",
+                    Some(entity.clone()),
+                ));
+                assert_round_trip(&DynamicStoreKey::network_service_entity(
+                    domain,
+                    "9B3E7F5C-3F4E-4F0A-9E4B-1C2D3E4F5A6B",
+                    Some(entity.clone()),
+                ));
+                assert_round_trip(&DynamicStoreKey {
+                    domain,
+                    component: Component::Users,
+                    id: None,
+                    entity: Some(entity),
+                });
+            }
+            assert_round_trip(&DynamicStoreKey::network_interface(domain));
+            assert_round_trip(&DynamicStoreKey::network_interface_entity(
+                domain, "utun3", None,
+            ));
+            assert_round_trip(&DynamicStoreKey::network_service_entity(domain, "id", None));
+            for component in [Component::HostNames, Component::System] {
+                assert_round_trip(&DynamicStoreKey {
+                    domain,
+                    component,
+                    id: None,
+                    entity: None,
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn parses_entities() {
+        for entity in all_entities() {
+            assert_eq!(Entity::from(entity.as_str()), entity);
+            assert_eq!(Entity::from(entity.as_str()).as_str(), entity.as_str());
+        }
+        assert_eq!(Entity::Other("6to4".to_owned()), Entity::SixToFour);
+        assert_ne!(Entity::Other("ipv4".to_owned()), Entity::IPv4);
+    }
+
+    #[test]
+    fn parses_unknown_entities() {
+        for key in [
+            "State:/Network/Global/IPv4/Extra",
+            "State:/Network/Service/id/DNS/Extra",
+            "Setup:/Network/Interface/en0/com.example/a/b",
+            "State:/Users/Foo",
+        ] {
+            let parsed = key.parse::<DynamicStoreKey>().unwrap();
+            assert!(matches!(parsed.entity, Some(Entity::Other(_))), "{}", key);
+            assert_eq!(parsed.to_string(), key);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        let unknown_domain = ["Foo:/Network/Global/IPv4", "State/Network/Global/IPv4", ""];
+        for key in unknown_domain {
+            assert!(key.parse::<DynamicStoreKey>().is_err(), "{}", key);
+        }
+        assert_eq!(
+            "Foo:/Network/Global/IPv4".parse::<DynamicStoreKey>(),
+            Err(ParseKeyError::UnknownDomain("Foo:".to_owned()))
+        );
+        assert_eq!(
+            "State:/Network/Foo/IPv4".parse::<DynamicStoreKey>(),
+            Err(ParseKeyError::UnknownComponent("Foo".to_owned()))
+        );
+        assert_eq!(
+            "State:/Global/IPv4".parse::<DynamicStoreKey>(),
+            Err(ParseKeyError::UnknownComponent("Global".to_owned()))
+        );
+        assert_eq!(
+            "State:/Network/System".parse::<DynamicStoreKey>(),
+            Err(ParseKeyError::UnknownComponent("System".to_owned()))
+        );
+        let invalid_layout = [
+            "State:",
+            "State:Network/Global/IPv4",
+            "State:/Network",
+            "State:/Network/Global",
+            "State:/Network/Global/IPv4/",
+            "State:/Network/Service/id/DNS//Extra",
+            "State:/Network/Service//DNS",
+            "State:/Network/Interface/",
+            "Setup:/Network/HostNames/Extra",
+            "Setup:/System/Extra",
+            "State:/Users",
+        ];
+        for key in invalid_layout {
+            assert_eq!(
+                key.parse::<DynamicStoreKey>(),
+                Err(ParseKeyError::InvalidLayout(key.to_owned())),
+                "{}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(
+            network_service_entity_pattern(Domain::State, &Entity::DNS),
+            "^State:/Network/Service/[^/]+/DNS$"
+        );
+        assert_eq!(
+            network_interface_entity_pattern(Domain::Setup, &Entity::AirPort),
+            "^Setup:/Network/Interface/[^/]+/AirPort$"
+        );
+        assert_eq!(
+            DynamicStoreKey::network_interface_entity(Domain::State, "en0.1", None).to_pattern(),
+            "^State:/Network/Interface/en0\\.1$"
+        );
+        assert_eq!(
+            escape_pattern("a(b)[c]{d}*+?|^$.\\"),
+            "a\\(b\\)\\[c\\]\\{d\\}\\*\\+\\?\\|\\^\\$\\.\\\\"
+        );
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::base::TCFType;
+
+        let wrap = |const_str| unsafe { CFString::wrap_under_get_rule(const_str) }.to_string();
+        unsafe {
+            let domains = [
+                (Domain::File, kSCDynamicStoreDomainFile),
+                (Domain::Plugin, kSCDynamicStoreDomainPlugin),
+                (Domain::Setup, kSCDynamicStoreDomainSetup),
+                (Domain::State, kSCDynamicStoreDomainState),
+                (Domain::Prefs, kSCDynamicStoreDomainPrefs),
+            ];
+            for (domain, const_str) in domains {
+                assert_eq!(domain.as_str(), wrap(const_str));
+            }
+            let components = [
+                (Component::Global, kSCCompGlobal),
+                (Component::Interface, kSCCompInterface),
+                (Component::Service, kSCCompService),
+                (Component::HostNames, kSCCompHostNames),
+                (Component::System, kSCCompSystem),
+                (Component::Users, kSCCompUsers),
+            ];
+            for (component, const_str) in components {
+                assert_eq!(component.as_str(), wrap(const_str));
+            }
+            assert_eq!(NETWORK, wrap(kSCCompNetwork));
+            assert_eq!(ANY_SEGMENT_PATTERN, wrap(kSCCompAnyRegex));
+            let entities = [
+                (Entity::AirPort, kSCEntNetAirPort),
+                (Entity::DHCP, kSCEntNetDHCP),
+                (Entity::DNS, kSCEntNetDNS),
+                (Entity::Ethernet, kSCEntNetEthernet),
+                (Entity::FireWire, kSCEntNetFireWire),
+                (Entity::Interface, kSCEntNetInterface),
+                (Entity::IPSec, kSCEntNetIPSec),
+                (Entity::IPv4, kSCEntNetIPv4),
+                (Entity::IPv6, kSCEntNetIPv6),
+                (Entity::L2TP, kSCEntNetL2TP),
+                (Entity::Link, kSCEntNetLink),
+                (Entity::Modem, kSCEntNetModem),
+                (Entity::PPP, kSCEntNetPPP),
+                (Entity::PPPoE, kSCEntNetPPPoE),
+                (Entity::PPPSerial, kSCEntNetPPPSerial),
+                (Entity::PPTP, kSCEntNetPPTP),
+                (Entity::Proxies, kSCEntNetProxies),
+                (Entity::SMB, kSCEntNetSMB),
+                (Entity::SixToFour, kSCEntNet6to4),
+                (Entity::ConsoleUser, kSCEntUsersConsoleUser),
+            ];
+            for (entity, const_str) in entities {
+                assert_eq!(entity.as_str(), wrap(const_str));
+            }
+        }
+    }
+}
@@ -0,0 +1,440 @@
+#[cfg(feature = "async")]
+use super::watch::{channel, ChangeSender, DynamicStoreWatch};
+use super::{pattern::Pattern, DynamicStoreBackend};
+use crate::{
+    error::SCError,
+    plist::{Dictionary, PlistValue},
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Callback of a [`MemoryDynamicStore`] session, called with the keys that changed.
+type CallBack = dyn FnMut(&MemoryDynamicStore, &[String]) + Send;
+
+/// A dynamic store kept in memory, for testing code written against [`DynamicStoreBackend`].
+///
+/// A `MemoryDynamicStore` is one session on a store. Further sessions on the same store are
+/// opened with [`session`], like creating several [`SCDynamicStore`] sessions. Notifications
+/// follow the rules of the real store:
+///
+/// - A session is notified about the keys and patterns given to
+///   [`set_notification_keys`](DynamicStoreBackend::set_notification_keys), whichever session
+///   changed them. Patterns are POSIX extended regular expressions that may match anywhere in a
+///   key, as in [`get_keys`](DynamicStoreBackend::get_keys).
+/// - Changed keys are queued until they are delivered, and every key is queued once. The keys
+///   changed by one [`set_multiple`](DynamicStoreBackend::set_multiple) are therefore always
+///   delivered together, as are all changes made between two deliveries.
+/// - Delivery happens when the test calls [`dispatch`], which stands in for the run loop calling
+///   the callback set with [`set_callback`], or [`notified_keys`], which stands in for
+///   `SCDynamicStoreCopyNotifiedKeys`.
+///
+/// [`session`]: #method.session
+/// [`dispatch`]: #method.dispatch
+/// [`set_callback`]: #method.set_callback
+/// [`notified_keys`]: #method.notified_keys
+/// [`SCDynamicStore`]: super::SCDynamicStore
+pub struct MemoryDynamicStore {
+    store: Arc<Mutex<Store>>,
+    id: usize,
+}
+
+#[derive(Default)]
+struct Store {
+    values: Dictionary,
+    watchers: BTreeMap<usize, Watcher>,
+    next_id: usize,
+}
+
+#[derive(Default)]
+struct Watcher {
+    keys: Vec<String>,
+    patterns: Vec<Pattern>,
+    /// Changed keys that were not delivered yet, in the order they first changed.
+    pending: Vec<String>,
+    callback: Option<Box<CallBack>>,
+    /// Receives the changes directly instead of queueing them, for watches.
+    #[cfg(feature = "async")]
+    sender: Option<ChangeSender>,
+}
+
+impl Store {
+    fn add_watcher(&mut self, watcher: Watcher) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watchers.insert(id, watcher);
+        id
+    }
+
+    /// Queues the changed keys for every session watching them.
+    fn notify(&mut self, changed: &[String]) {
+        #[cfg(feature = "async")]
+        self.watchers.retain(|_, watcher| {
+            watcher
+                .sender
+                .as_ref()
+                .map_or(true, |sender| !sender.is_closed())
+        });
+        for watcher in self.watchers.values_mut() {
+            let matched = changed
+                .iter()
+                .filter(|key| watcher.is_watching(key))
+                .cloned()
+                .collect::<Vec<_>>();
+            #[cfg(feature = "async")]
+            if let Some(sender) = &watcher.sender {
+                sender.send(matched);
+                continue;
+            }
+            for key in matched {
+                if !watcher.pending.contains(&key) {
+                    watcher.pending.push(key);
+                }
+            }
+        }
+    }
+}
+
+impl Watcher {
+    fn is_watching(&self, key: &str) -> bool {
+        self.keys.iter().any(|watched| watched == key)
+            || self.patterns.iter().any(|pattern| pattern.is_match(key))
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Pattern>, SCError> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern).map_err(|_| SCError::Failed))
+        .collect()
+}
+
+impl MemoryDynamicStore {
+    /// Creates an empty store and returns a session on it.
+    pub fn new() -> Self {
+        Self::with_values(Dictionary::new())
+    }
+
+    /// Creates a store holding `values` and returns a session on it.
+    pub fn with_values(values: Dictionary) -> Self {
+        let store = Store {
+            values,
+            ..Store::default()
+        };
+        Self::open(Arc::new(Mutex::new(store)))
+    }
+
+    fn open(store: Arc<Mutex<Store>>) -> Self {
+        let id = store.lock().unwrap().add_watcher(Watcher::default());
+        MemoryDynamicStore { store, id }
+    }
+
+    /// Opens another session on the same store.
+    pub fn session(&self) -> Self {
+        Self::open(self.store.clone())
+    }
+
+    /// Returns all values of the store.
+    pub fn values(&self) -> Dictionary {
+        self.lock().values.clone()
+    }
+
+    /// Sets the callback [`dispatch`](#method.dispatch) delivers the changed keys to, replacing
+    /// the current one.
+    pub fn set_callback<F>(&self, callback: F)
+    where
+        F: FnMut(&MemoryDynamicStore, &[String]) + Send + 'static,
+    {
+        self.watcher(|watcher| watcher.callback = Some(Box::new(callback)));
+    }
+
+    /// Delivers the keys that changed since the last delivery to the callback of this session, as
+    /// one batch. Returns `false`, leaving the keys queued, if nothing changed or the session has
+    /// no callback.
+    ///
+    /// The store is not locked while the callback runs, so it may use the session.
+    pub fn dispatch(&self) -> bool {
+        let taken = self.watcher(|watcher| {
+            if watcher.pending.is_empty() {
+                return None;
+            }
+            let callback = watcher.callback.take()?;
+            Some((callback, std::mem::take(&mut watcher.pending)))
+        });
+        let (mut callback, keys) = match taken {
+            Some(taken) => taken,
+            None => return false,
+        };
+        callback(self, &keys);
+        self.watcher(|watcher| {
+            // Keep a callback the callback itself installed.
+            if watcher.callback.is_none() {
+                watcher.callback = Some(callback);
+            }
+        });
+        true
+    }
+
+    /// Returns the keys that changed since the last delivery, and clears them.
+    pub fn notified_keys(&self) -> Vec<String> {
+        self.watcher(|watcher| std::mem::take(&mut watcher.pending))
+    }
+
+    /// Watches the given keys and key patterns for changes, like [`SCDynamicStore::watch`].
+    /// Changes are sent to the stream as soon as they are made.
+    ///
+    /// [`SCDynamicStore::watch`]: super::SCDynamicStore::watch
+    #[cfg(feature = "async")]
+    pub fn watch(
+        &self,
+        keys: &[String],
+        patterns: &[String],
+    ) -> Result<DynamicStoreWatch, SCError> {
+        let (sender, watch) = channel();
+        let watcher = Watcher {
+            keys: keys.to_vec(),
+            patterns: compile(patterns)?,
+            sender: Some(sender),
+            ..Watcher::default()
+        };
+        self.lock().add_watcher(watcher);
+        Ok(watch)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Store> {
+        self.store.lock().unwrap()
+    }
+
+    fn watcher<T>(&self, f: impl FnOnce(&mut Watcher) -> T) -> T {
+        f(self
+            .lock()
+            .watchers
+            .get_mut(&self.id)
+            .expect("session is registered while it exists"))
+    }
+}
+
+impl DynamicStoreBackend for MemoryDynamicStore {
+    fn get_keys(&self, pattern: &str) -> Result<Vec<String>, SCError> {
+        let pattern = Pattern::new(pattern).map_err(|_| SCError::Failed)?;
+        Ok(self
+            .lock()
+            .values
+            .keys()
+            .filter(|key| pattern.is_match(key))
+            .cloned()
+            .collect())
+    }
+
+    fn get(&self, key: &str) -> Option<PlistValue> {
+        self.lock().values.get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        let mut store = self.lock();
+        store.values.insert(key.to_owned(), value.clone());
+        store.notify(&[key.to_owned()]);
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), SCError> {
+        let mut store = self.lock();
+        store.values.remove(key).ok_or(SCError::NoKey)?;
+        store.notify(&[key.to_owned()]);
+        Ok(())
+    }
+
+    fn set_multiple(
+        &self,
+        set: &Dictionary,
+        remove: &[String],
+        notify: &[String],
+    ) -> Result<(), SCError> {
+        let mut store = self.lock();
+        let mut changed = Vec::new();
+        for (key, value) in set {
+            store.values.insert(key.clone(), value.clone());
+            changed.push(key.clone());
+        }
+        for key in remove {
+            if store.values.remove(key).is_some() {
+                changed.push(key.clone());
+            }
+        }
+        changed.extend(notify.iter().cloned());
+        store.notify(&changed);
+        Ok(())
+    }
+
+    fn set_notification_keys(&self, keys: &[String], patterns: &[String]) -> Result<(), SCError> {
+        let patterns = compile(patterns)?;
+        self.watcher(|watcher| {
+            watcher.keys = keys.to_vec();
+            watcher.patterns = patterns;
+        });
+        Ok(())
+    }
+}
+
+impl Default for MemoryDynamicStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Dictionary> for MemoryDynamicStore {
+    fn from(values: Dictionary) -> Self {
+        Self::with_values(values)
+    }
+}
+
+impl fmt::Debug for MemoryDynamicStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryDynamicStore")
+            .field("session", &self.id)
+            .field("values", &self.lock().values)
+            .finish()
+    }
+}
+
+impl Drop for MemoryDynamicStore {
+    fn drop(&mut self) {
+        if let Ok(mut store) = self.store.lock() {
+            store.watchers.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|string| string.to_string()).collect()
+    }
+
+    const DNS: &str = "State:/Network/Global/DNS";
+    const PROXIES: &str = "State:/Network/Global/Proxies";
+    const SERVICE_DNS: &str = "State:/Network/Service/A/DNS";
+
+    #[test]
+    fn values_and_key_list() {
+        let store = MemoryDynamicStore::new();
+        store.set(DNS, &"a".into()).unwrap();
+        store.set(SERVICE_DNS, &"b".into()).unwrap();
+        assert_eq!(store.session().get(DNS), Some("a".into()));
+        assert_eq!(
+            store.get_keys("^State:/Network/Service/[^/]+/DNS$"),
+            Ok(strings(&[SERVICE_DNS]))
+        );
+        assert_eq!(store.get_keys("DNS"), Ok(strings(&[DNS, SERVICE_DNS])));
+        assert_eq!(store.get_keys("(DNS"), Err(SCError::Failed));
+
+        store.remove(DNS).unwrap();
+        assert_eq!(store.remove(DNS), Err(SCError::NoKey));
+        assert_eq!(store.get(DNS), None);
+    }
+
+    #[test]
+    fn notifications_are_batched() {
+        let store = MemoryDynamicStore::new();
+        let watcher = store.session();
+        watcher
+            .set_notification_keys(&strings(&[DNS]), &strings(&["^State:/Network/Service/"]))
+            .unwrap();
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let delivered = batches.clone();
+        watcher.set_callback(move |_, keys| delivered.lock().unwrap().push(keys.to_vec()));
+        assert!(!watcher.dispatch());
+
+        let set = [
+            (DNS.to_owned(), "a".into()),
+            (PROXIES.to_owned(), "p".into()),
+        ]
+        .into_iter()
+        .collect();
+        store
+            .set_multiple(&set, &strings(&["Missing"]), &strings(&[SERVICE_DNS]))
+            .unwrap();
+        store.set(DNS, &"b".into()).unwrap();
+        assert!(watcher.dispatch());
+        assert!(!watcher.dispatch());
+        store.remove(DNS).unwrap();
+        assert!(watcher.dispatch());
+
+        assert_eq!(
+            *batches.lock().unwrap(),
+            vec![strings(&[DNS, SERVICE_DNS]), strings(&[DNS])]
+        );
+        assert!(store.notified_keys().is_empty());
+    }
+
+    #[test]
+    fn notified_keys() {
+        let store = MemoryDynamicStore::new();
+        store
+            .set_notification_keys(&[], &strings(&["Global"]))
+            .unwrap();
+        store.set(PROXIES, &"p".into()).unwrap();
+        store.set(SERVICE_DNS, &"s".into()).unwrap();
+        assert_eq!(store.notified_keys(), strings(&[PROXIES]));
+        assert!(store.notified_keys().is_empty());
+        assert_eq!(
+            store.set_notification_keys(&[], &strings(&["["])),
+            Err(SCError::Failed)
+        );
+    }
+
+    #[test]
+    fn callback_can_use_the_session() {
+        let store = MemoryDynamicStore::new();
+        store
+            .set_notification_keys(&strings(&[DNS, PROXIES]), &[])
+            .unwrap();
+        store.set_callback(|store, keys| {
+            if keys.iter().any(|key| key == DNS) {
+                store.set(PROXIES, &"derived".into()).unwrap();
+            }
+        });
+        store.set(DNS, &"a".into()).unwrap();
+        assert!(store.dispatch());
+        assert_eq!(store.get(PROXIES), Some("derived".into()));
+        assert_eq!(store.notified_keys(), strings(&[PROXIES]));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn watch() {
+        use futures_core::Stream;
+        use std::{
+            pin::Pin,
+            task::{Context, Poll, Wake, Waker},
+        };
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+
+        let store = MemoryDynamicStore::new();
+        let mut watch = store.watch(&strings(&[DNS]), &[]).unwrap();
+        store.set(DNS, &"a".into()).unwrap();
+        store.set(PROXIES, &"p".into()).unwrap();
+        store.remove(DNS).unwrap();
+        match Pin::new(&mut watch).poll_next(&mut cx) {
+            Poll::Ready(Some(changed)) => assert_eq!(changed.keys(), strings(&[DNS])),
+            _ => panic!("expected a change"),
+        }
+        assert!(Pin::new(&mut watch).poll_next(&mut cx).is_pending());
+
+        drop(watch);
+        store.set(DNS, &"b".into()).unwrap();
+        assert_eq!(store.lock().watchers.len(), 1);
+    }
+}
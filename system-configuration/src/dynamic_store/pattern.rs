@@ -0,0 +1,619 @@
+//! POSIX extended regular expressions, as used for dynamic store key patterns.
+//!
+//! The dynamic store compiles key patterns with `regcomp(3)` and `REG_EXTENDED`, and a key matches
+//! if the expression matches anywhere in it. [`Pattern`] implements the same dialect in pure Rust:
+//! anchors, `.`, bracket expressions with ranges and character classes, grouping, alternation and
+//! the `*`, `+`, `?` and `{m,n}` repetitions. Matching simulates all alternatives at once, so it
+//! takes time linear in the length of the key.
+
+use std::fmt;
+
+/// Repetition counts above this are rejected, like `RE_DUP_MAX`.
+const MAX_REPETITION: u32 = 255;
+
+/// A compiled pattern.
+#[derive(Clone)]
+pub(crate) struct Pattern {
+    source: String,
+    program: Vec<Inst>,
+}
+
+/// Failure to compile a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PatternError {
+    pub position: usize,
+    pub reason: &'static str,
+}
+
+impl Pattern {
+    /// Compiles a POSIX extended regular expression.
+    pub fn new(source: &str) -> Result<Self, PatternError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            position: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unmatched `)`"));
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program);
+        program.push(Inst::Match);
+        Ok(Pattern {
+            source: source.to_owned(),
+            program,
+        })
+    }
+
+    /// Returns `true` if the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = text.chars().collect::<Vec<_>>();
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        for position in 0..=text.len() {
+            // Searching is unanchored, so a match may start at every position.
+            if self.add_thread(&mut current, 0, position, text.len()) {
+                return true;
+            }
+            let c = match text.get(position) {
+                Some(&c) => c,
+                None => break,
+            };
+            next.clear();
+            for &pc in &current.list {
+                let advances = match &self.program[pc] {
+                    Inst::Char(expected) => *expected == c,
+                    Inst::Any => true,
+                    Inst::Class(class) => class.matches(c),
+                    _ => false,
+                };
+                if advances && self.add_thread(&mut next, pc + 1, position + 1, text.len()) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+
+    /// Adds the thread at `pc` and every thread reachable from it without consuming input.
+    /// Returns `true` if one of them reached the end of the program.
+    fn add_thread(&self, threads: &mut Threads, pc: usize, position: usize, len: usize) -> bool {
+        if !threads.insert(pc) {
+            return false;
+        }
+        match &self.program[pc] {
+            Inst::Match => true,
+            Inst::Jump(target) => self.add_thread(threads, *target, position, len),
+            Inst::Split(first, second) => {
+                self.add_thread(threads, *first, position, len)
+                    || self.add_thread(threads, *second, position, len)
+            }
+            Inst::Start => position == 0 && self.add_thread(threads, pc + 1, position, len),
+            Inst::End => position == len && self.add_thread(threads, pc + 1, position, len),
+            Inst::Char(_) | Inst::Any | Inst::Class(_) => {
+                threads.list.push(pc);
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Pattern").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid pattern at character {}: {}",
+            self.position, self.reason
+        )
+    }
+}
+
+/// The threads of the simulation at one position: the instructions waiting for the next
+/// character, and which instructions were already visited.
+struct Threads {
+    list: Vec<usize>,
+    visited: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            visited: vec![false; len],
+        }
+    }
+
+    fn insert(&mut self, pc: usize) -> bool {
+        !std::mem::replace(&mut self.visited[pc], true)
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.visited.iter_mut().for_each(|visited| *visited = false);
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Jump(usize),
+    /// Continues at both targets, preferring neither.
+    Split(usize, usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClassItem {
+    Range(char, char),
+    Named(NamedClass),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NamedClass {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match *item {
+            ClassItem::Range(start, end) => start <= c && c <= end,
+            ClassItem::Named(class) => class.matches(c),
+        });
+        found != self.negated
+    }
+}
+
+impl NamedClass {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "alnum" => NamedClass::Alnum,
+            "alpha" => NamedClass::Alpha,
+            "blank" => NamedClass::Blank,
+            "cntrl" => NamedClass::Cntrl,
+            "digit" => NamedClass::Digit,
+            "graph" => NamedClass::Graph,
+            "lower" => NamedClass::Lower,
+            "print" => NamedClass::Print,
+            "punct" => NamedClass::Punct,
+            "space" => NamedClass::Space,
+            "upper" => NamedClass::Upper,
+            "xdigit" => NamedClass::Xdigit,
+            _ => return None,
+        })
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            NamedClass::Alnum => c.is_alphanumeric(),
+            NamedClass::Alpha => c.is_alphabetic(),
+            NamedClass::Blank => c == ' ' || c == '\t',
+            NamedClass::Cntrl => c.is_control(),
+            NamedClass::Digit => c.is_ascii_digit(),
+            NamedClass::Graph => !c.is_control() && !c.is_whitespace(),
+            NamedClass::Lower => c.is_lowercase(),
+            NamedClass::Print => !c.is_control(),
+            NamedClass::Punct => c.is_ascii_punctuation(),
+            NamedClass::Space => c.is_whitespace(),
+            NamedClass::Upper => c.is_uppercase(),
+            NamedClass::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, reason: &'static str) -> PatternError {
+        PatternError {
+            position: self.position,
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `branch ('|' branch)*`
+    fn alternation(&mut self) -> Result<Node, PatternError> {
+        let mut branches = vec![self.branch()?];
+        while self.eat('|') {
+            branches.push(self.branch()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().expect("one branch")
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    /// A sequence of repeated atoms, ending at `|`, `)` or the end of the pattern.
+    fn branch(&mut self) -> Result<Node, PatternError> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom(nodes.is_empty())?;
+            nodes.push(self.repetitions(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().expect("one node"),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn atom(&mut self, first_in_branch: bool) -> Result<Node, PatternError> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.position += 1;
+        Ok(match c {
+            '(' => {
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    return Err(self.error("unmatched `(`"));
+                }
+                node
+            }
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '[' => Node::Class(self.bracket()?),
+            '\\' => {
+                let escaped = self.peek().ok_or_else(|| self.error("trailing `\\`"))?;
+                self.position += 1;
+                Node::Char(escaped)
+            }
+            // A repetition operator with nothing to repeat stands for itself.
+            '*' | '+' | '?' if first_in_branch => Node::Char(c),
+            '*' | '+' | '?' => return Err(self.error("repetition operator without operand")),
+            c => Node::Char(c),
+        })
+    }
+
+    fn repetitions(&mut self, mut node: Node) -> Result<Node, PatternError> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.bound()? {
+                    Some(bound) => bound,
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            if !matches!(self.peek(), Some('{')) {
+                self.position += 1;
+            }
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+            };
+        }
+    }
+
+    /// Parses `{m}`, `{m,}` or `{m,n}`, consuming it. Returns `None` without consuming anything
+    /// if the brace does not start a bound, in which case it is an ordinary character.
+    fn bound(&mut self) -> Result<Option<(u32, Option<u32>)>, PatternError> {
+        let start = self.position;
+        self.position += 1;
+        let min = match self.number()? {
+            Some(min) => min,
+            None => {
+                self.position = start;
+                return Ok(None);
+            }
+        };
+        let max = if self.eat(',') {
+            self.number()?
+        } else {
+            Some(min)
+        };
+        if !self.eat('}') {
+            return Err(self.error("unterminated repetition bound"));
+        }
+        if min > MAX_REPETITION || max.map_or(false, |max| max > MAX_REPETITION || max < min) {
+            return Err(self.error("invalid repetition bound"));
+        }
+        // Leave the position on the `}` so the caller's common handling steps past it.
+        self.position -= 1;
+        Ok(Some((min, max)))
+    }
+
+    /// Parses a decimal number, returning `None` if there are no digits.
+    fn number(&mut self) -> Result<Option<u32>, PatternError> {
+        let start = self.position;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Ok(None);
+        }
+        self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map(Some)
+            .map_err(|_| self.error("invalid repetition bound"))
+    }
+
+    /// Parses a bracket expression after its opening `[`.
+    fn bracket(&mut self) -> Result<Class, PatternError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated bracket expression"))?;
+            if c == ']' && !first {
+                self.position += 1;
+                return Ok(Class { negated, items });
+            }
+            first = false;
+            if c == '[' && self.chars.get(self.position + 1) == Some(&':') {
+                let name = self.delimited(':')?;
+                let class = NamedClass::from_name(&name)
+                    .ok_or_else(|| self.error("unknown character class"))?;
+                items.push(ClassItem::Named(class));
+                continue;
+            }
+            let start = self.bracket_char()?;
+            if self.peek() == Some('-') && self.chars.get(self.position + 1) != Some(&']') {
+                self.position += 1;
+                let end = self.bracket_char()?;
+                if end < start {
+                    return Err(self.error("invalid range"));
+                }
+                items.push(ClassItem::Range(start, end));
+            } else {
+                items.push(ClassItem::Range(start, start));
+            }
+        }
+    }
+
+    /// Parses a single character of a bracket expression, including the collating element and
+    /// equivalence class forms `[.c.]` and `[=c=]` for a single character.
+    fn bracket_char(&mut self) -> Result<char, PatternError> {
+        if self.peek() == Some('[') {
+            if let Some(&delimiter @ ('.' | '=')) = self.chars.get(self.position + 1) {
+                let element = self.delimited(delimiter)?;
+                let mut chars = element.chars();
+                return match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(c),
+                    _ => Err(self.error("unsupported collating element")),
+                };
+            }
+        }
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated bracket expression"))?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    /// Parses `[<delimiter>...<delimiter>]`, returning the text between the delimiters.
+    fn delimited(&mut self, delimiter: char) -> Result<String, PatternError> {
+        self.position += 2;
+        let start = self.position;
+        while self.position + 1 < self.chars.len() {
+            if self.chars[self.position] == delimiter && self.chars[self.position + 1] == ']' {
+                let text = self.chars[start..self.position].iter().collect();
+                self.position += 2;
+                return Ok(text);
+            }
+            self.position += 1;
+        }
+        Err(self.error("unterminated bracket expression"))
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => (),
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, program)),
+        Node::Alternate(branches) => {
+            let mut jumps = Vec::new();
+            for (index, branch) in branches.iter().enumerate() {
+                if index + 1 < branches.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(branch, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(branch, program);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    let end = program.len();
+                    program[split] = Inst::Split(split + 1, end);
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn dynamic_store_patterns() {
+        let pattern = "^State:/Network/Service/[^/]+/DNS$";
+        assert!(is_match(pattern, "State:/Network/Service/ABC/DNS"));
+        assert!(!is_match(pattern, "State:/Network/Service/ABC/IPv4"));
+        assert!(!is_match(pattern, "State:/Network/Service//DNS"));
+        assert!(!is_match(pattern, "State:/Network/Service/A/B/DNS"));
+        assert!(is_match("Interface", "State:/Network/Interface/en0/Link"));
+        assert!(is_match(
+            "^(State|Setup):/Network/Global/(IPv4|DNS)$",
+            "Setup:/Network/Global/DNS"
+        ));
+        assert!(is_match(
+            r"^State:/Network/Global/IPv4\.x$",
+            "State:/Network/Global/IPv4.x"
+        ));
+        assert!(!is_match(
+            r"^State:/Network/Global/IPv4\.x$",
+            "State:/Network/Global/IPv4_x"
+        ));
+    }
+
+    #[test]
+    fn repetitions() {
+        assert!(is_match("^a*$", ""));
+        assert!(is_match("^a+b?$", "aaa"));
+        assert!(!is_match("^a+$", ""));
+        assert!(is_match("^a{2,3}$", "aaa"));
+        assert!(!is_match("^a{2,3}$", "aaaa"));
+        assert!(is_match("^a{2,}$", "aaaaa"));
+        assert!(is_match("^(ab){2}$", "abab"));
+        assert!(is_match("^a{,2}$", "a{,2}"));
+        assert!(is_match("^*a$", "*a"));
+        assert!(is_match("^(a|)+$", "aaa"));
+        assert!(is_match("^(a*)*$", "aaaa"));
+    }
+
+    #[test]
+    fn bracket_expressions() {
+        assert!(is_match("^[]a]+$", "]a"));
+        assert!(is_match("^[^]a]$", "b"));
+        assert!(is_match("^[a-c-]+$", "a-c"));
+        assert!(is_match("^[[:digit:][:upper:]]+$", "A1"));
+        assert!(!is_match("^[[:digit:]]$", "a"));
+        assert!(is_match("^[[.-.]x]$", "-"));
+        assert!(is_match("^[.]$", "."));
+        assert!(!is_match("^[.]$", "a"));
+    }
+
+    #[test]
+    fn anchors_and_search() {
+        assert!(is_match("b", "abc"));
+        assert!(!is_match("^b", "abc"));
+        assert!(is_match("c$", "abc"));
+        assert!(is_match("", "abc"));
+        assert!(is_match("a|^b", "b"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for pattern in [
+            "(a",
+            "a)",
+            "[a",
+            "[[:foo:]]",
+            "[z-a]",
+            "a{3,2}",
+            "a{256}",
+            "a{1,99999999999}",
+            "a{99999999999}",
+            "a{2",
+            "\\",
+        ] {
+            assert!(Pattern::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn linear_time() {
+        let pattern = Pattern::new("^(a|a)*(a*)*b$").unwrap();
+        assert!(!pattern.is_match(&"a".repeat(10_000)));
+    }
+}
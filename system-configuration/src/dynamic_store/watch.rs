@@ -0,0 +1,375 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Asynchronous notifications about changes to dynamic store keys.
+//!
+//! Requires the `async` feature. [`SCDynamicStore::watch`] creates a dynamic store session on a
+//! dedicated thread running a `CFRunLoop`, and hands out the changed keys as a
+//! [`Stream`](futures_core::Stream).
+//!
+//! Changes are coalesced: all keys that change between two polls of the stream are delivered as a
+//! single [`ChangedKeys`], each key at most once. A slow consumer therefore never causes unbounded
+//! buffering, it just receives larger batches.
+
+use super::{to_cf_strings, SCDynamicStore, SCDynamicStoreBuilder, SCDynamicStoreCallBackContext};
+use crate::error::SCError;
+use core_foundation::{
+    array::CFArray,
+    runloop::{kCFRunLoopDefaultMode, CFRunLoop},
+    string::CFString,
+};
+use futures_core::Stream;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+/// How long the watch thread runs its run loop before checking whether it should stop. Stopping
+/// normally wakes the run loop right away, this only bounds the time a stop racing with the start
+/// of the run loop can go unnoticed.
+const RUN_LOOP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The keys that changed since the stream was last polled, in the order they first changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedKeys(Vec<String>);
+
+impl ChangedKeys {
+    /// Returns the changed keys.
+    pub fn keys(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Returns `true` if `key` is among the changed keys.
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.iter().any(|changed| changed == key)
+    }
+
+    /// Returns the changed keys.
+    pub fn into_keys(self) -> Vec<String> {
+        self.0
+    }
+}
+
+impl IntoIterator for ChangedKeys {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    pending: Vec<String>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// The producing half of a change queue. Fed by the dynamic store callback, or by anything else
+/// producing key changes.
+pub(crate) struct ChangeSender {
+    state: Arc<Mutex<State>>,
+}
+
+impl ChangeSender {
+    /// Adds changed keys to the queue, skipping keys that are already waiting to be delivered.
+    pub fn send(&self, keys: impl IntoIterator<Item = String>) {
+        let mut state = self.state.lock().unwrap();
+        let mut changed = false;
+        for key in keys {
+            if !state.pending.contains(&key) {
+                state.pending.push(key);
+                changed = true;
+            }
+        }
+        if changed {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Returns `true` if the consuming half of the queue was dropped.
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.state) == 1
+    }
+}
+
+impl Drop for ChangeSender {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a change queue, returning its producing and consuming halves.
+pub(crate) fn channel() -> (ChangeSender, DynamicStoreWatch) {
+    let state = Arc::new(Mutex::new(State::default()));
+    let sender = ChangeSender {
+        state: state.clone(),
+    };
+    let watch = DynamicStoreWatch {
+        state,
+        run_loop: None,
+    };
+    (sender, watch)
+}
+
+/// Handle to the thread running the run loop of a watch.
+struct RunLoopThread {
+    run_loop: CFRunLoop,
+    stopped: Arc<AtomicBool>,
+}
+
+/// A [`Stream`] of changes to dynamic store keys, created by [`SCDynamicStore::watch`] or
+/// [`SCDynamicStoreBuilder::watch`].
+///
+/// The stream ends if the dynamic store session goes away. Dropping the stream stops the watch
+/// thread and releases the session.
+pub struct DynamicStoreWatch {
+    state: Arc<Mutex<State>>,
+    run_loop: Option<RunLoopThread>,
+}
+
+impl Stream for DynamicStoreWatch {
+    type Item = ChangedKeys;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.pending.is_empty() {
+            Poll::Ready(Some(ChangedKeys(std::mem::take(&mut state.pending))))
+        } else if state.closed {
+            Poll::Ready(None)
+        } else {
+            if !state
+                .waker
+                .as_ref()
+                .map_or(false, |waker| waker.will_wake(cx.waker()))
+            {
+                state.waker = Some(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for DynamicStoreWatch {
+    fn drop(&mut self) {
+        if let Some(thread) = &self.run_loop {
+            thread.stopped.store(true, Ordering::SeqCst);
+            thread.run_loop.stop();
+        }
+    }
+}
+
+impl SCDynamicStore {
+    /// Watches the given keys and key patterns for changes. See [`SCDynamicStoreBuilder::watch`]
+    /// for details.
+    pub fn watch(
+        keys: &CFArray<CFString>,
+        patterns: &CFArray<CFString>,
+    ) -> Result<DynamicStoreWatch, SCError> {
+        SCDynamicStoreBuilder::new("system-configuration-rs watch").watch(keys, patterns)
+    }
+}
+
+impl SCDynamicStoreBuilder<()> {
+    /// Creates the dynamic store session on a dedicated run loop thread, and returns a stream of
+    /// the changes to the given keys and key patterns. See
+    /// [`SCDynamicStore::set_notification_keys`] for how keys and patterns are matched.
+    pub fn watch(
+        self,
+        keys: &CFArray<CFString>,
+        patterns: &CFArray<CFString>,
+    ) -> Result<DynamicStoreWatch, SCError> {
+        // CoreFoundation strings can't be sent to the thread, so they are copied into Rust ones.
+        let name = self.name.to_string();
+        let session_keys = self.session_keys;
+        let keys = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        let patterns = patterns
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<_>>();
+
+        let (sender, mut watch) = channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("dynamic-store-watch".to_owned())
+            .spawn(move || {
+                let store = SCDynamicStoreBuilder::new(name.as_str())
+                    .session_keys(session_keys)
+                    .callback_context(SCDynamicStoreCallBackContext {
+                        callout: forward_changes,
+                        info: sender,
+                    })
+                    .build();
+                if let Err(error) =
+                    store.set_notification_keys(&to_cf_strings(&keys), &to_cf_strings(&patterns))
+                {
+                    let _ = ready_tx.send(Err(error));
+                    return;
+                }
+                let run_loop_source = store.create_run_loop_source();
+                let run_loop = CFRunLoop::get_current();
+                run_loop.add_source(&run_loop_source, unsafe { kCFRunLoopDefaultMode });
+                let _ = ready_tx.send(Ok(run_loop));
+                while !thread_stopped.load(Ordering::SeqCst) {
+                    CFRunLoop::run_in_mode(
+                        unsafe { kCFRunLoopDefaultMode },
+                        RUN_LOOP_INTERVAL,
+                        false,
+                    );
+                }
+            })
+            .map_err(|_| SCError::Failed)?;
+
+        let run_loop = ready_rx.recv().map_err(|_| SCError::Failed)??;
+        watch.run_loop = Some(RunLoopThread { run_loop, stopped });
+        Ok(watch)
+    }
+}
+
+fn forward_changes(
+    _store: SCDynamicStore,
+    changed_keys: CFArray<CFString>,
+    sender: &mut ChangeSender,
+) {
+    sender.send(changed_keys.iter().map(|key| key.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::atomic::AtomicUsize, task::Wake};
+
+    /// Waker counting how often it is woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn poll(
+        watch: &mut DynamicStoreWatch,
+        waker: &Arc<CountingWaker>,
+    ) -> Poll<Option<ChangedKeys>> {
+        let waker = Waker::from(waker.clone());
+        Pin::new(watch).poll_next(&mut Context::from_waker(&waker))
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn coalesces_changes() {
+        let (sender, mut watch) = channel();
+        let waker = Arc::new(CountingWaker::default());
+
+        assert_eq!(poll(&mut watch, &waker), Poll::Pending);
+        sender.send(keys(&[
+            "State:/Network/Global/IPv4",
+            "State:/Network/Global/DNS",
+        ]));
+        sender.send(keys(&[
+            "State:/Network/Global/DNS",
+            "Setup:/Network/HostNames",
+        ]));
+        assert_eq!(waker.0.load(Ordering::SeqCst), 1);
+
+        let changed = match poll(&mut watch, &waker) {
+            Poll::Ready(Some(changed)) => changed,
+            other => panic!("unexpected poll result: {:?}", other),
+        };
+        assert_eq!(
+            changed.keys(),
+            keys(&[
+                "State:/Network/Global/IPv4",
+                "State:/Network/Global/DNS",
+                "Setup:/Network/HostNames"
+            ])
+        );
+        assert!(changed.contains("Setup:/Network/HostNames"));
+        assert_eq!(poll(&mut watch, &waker), Poll::Pending);
+
+        sender.send(keys(&["State:/Network/Global/IPv4"]));
+        assert_eq!(waker.0.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            poll(&mut watch, &waker),
+            Poll::Ready(Some(ChangedKeys(keys(&["State:/Network/Global/IPv4"]))))
+        );
+    }
+
+    #[test]
+    fn empty_changes_do_not_wake() {
+        let (sender, mut watch) = channel();
+        let waker = Arc::new(CountingWaker::default());
+        assert_eq!(poll(&mut watch, &waker), Poll::Pending);
+        sender.send(Vec::new());
+        assert_eq!(waker.0.load(Ordering::SeqCst), 0);
+        assert_eq!(poll(&mut watch, &waker), Poll::Pending);
+    }
+
+    #[test]
+    fn ends_after_sender_is_dropped() {
+        let (sender, mut watch) = channel();
+        let waker = Arc::new(CountingWaker::default());
+        assert_eq!(poll(&mut watch, &waker), Poll::Pending);
+        sender.send(keys(&["a"]));
+        drop(sender);
+
+        assert_eq!(
+            poll(&mut watch, &waker),
+            Poll::Ready(Some(ChangedKeys(keys(&["a"]))))
+        );
+        assert_eq!(poll(&mut watch, &waker), Poll::Ready(None));
+        assert_eq!(poll(&mut watch, &waker), Poll::Ready(None));
+    }
+
+    #[test]
+    fn delivers_changes_from_other_threads() {
+        let (sender, mut watch) = channel();
+        let waker = Arc::new(CountingWaker::default());
+        let producer = thread::spawn(move || {
+            for i in 0..1000 {
+                sender.send(vec![format!("key{}", i % 10)]);
+            }
+        });
+
+        let mut seen = Vec::new();
+        loop {
+            match poll(&mut watch, &waker) {
+                Poll::Ready(Some(changed)) => {
+                    assert!(changed.keys().len() <= 10);
+                    seen.extend(changed);
+                }
+                Poll::Ready(None) => break,
+                Poll::Pending => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        for i in 0..10 {
+            assert!(seen.contains(&format!("key{}", i)));
+        }
+    }
+}
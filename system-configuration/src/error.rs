@@ -0,0 +1,289 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Error type for failing SystemConfiguration calls.
+//!
+//! Most SystemConfiguration functions only report success or failure. The reason for a failure is
+//! stored per thread and can be retrieved with [`SCError()`]. [`SCError`] models those status
+//! codes.
+//!
+//! [`SCError()`]: https://developer.apple.com/documentation/systemconfiguration/1516805-scerror?language=objc
+
+#![allow(non_upper_case_globals)]
+
+use crate::sys::system_configuration::{
+    kSCStatusAccessError, kSCStatusConnectionIgnore, kSCStatusConnectionNoService, kSCStatusFailed,
+    kSCStatusInvalidArgument, kSCStatusKeyExists, kSCStatusLocked, kSCStatusMaxLink,
+    kSCStatusNeedLock, kSCStatusNoConfigFile, kSCStatusNoKey, kSCStatusNoLink,
+    kSCStatusNoPrefsSession, kSCStatusNoStoreServer, kSCStatusNoStoreSession,
+    kSCStatusNotifierActive, kSCStatusOK, kSCStatusPrefsBusy, kSCStatusReachabilityUnknown,
+    kSCStatusStale, SCErrorString,
+};
+use core_foundation::base::Boolean;
+use std::{
+    error::Error,
+    ffi::CStr,
+    fmt::{self, Display},
+};
+
+/// A SystemConfiguration status code describing why an operation failed.
+///
+/// See [_Status and Error Codes_] for details.
+///
+/// [_Status and Error Codes_]: https://developer.apple.com/documentation/systemconfiguration/1516940-anonymous?language=objc
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SCError {
+    /// Non-specific failure.
+    Failed,
+    /// Invalid argument.
+    InvalidArgument,
+    /// Permission denied. Must be root to obtain lock or the process has insufficient privileges
+    /// to store data.
+    AccessError,
+    /// No such key.
+    NoKey,
+    /// Key already defined.
+    KeyExists,
+    /// Lock already held.
+    Locked,
+    /// Lock required for this operation.
+    NeedLock,
+    /// Configuration daemon session not active.
+    NoStoreSession,
+    /// Configuration daemon not (or no longer) available.
+    NoStoreServer,
+    /// Notifier is currently active.
+    NotifierActive,
+    /// Preferences session not active.
+    NoPrefsSession,
+    /// Preferences update currently in progress.
+    PrefsBusy,
+    /// Configuration file not found.
+    NoConfigFile,
+    /// No such link.
+    NoLink,
+    /// Write attempted on stale version of object.
+    Stale,
+    /// Maximum link count exceeded.
+    MaxLink,
+    /// Network reachability cannot be determined.
+    ReachabilityUnknown,
+    /// Network connection service not available.
+    ConnectionNoService,
+    /// Network connection service policy ignore.
+    ConnectionIgnore,
+    /// A status code not known to this crate.
+    Other(i32),
+}
+
+impl SCError {
+    /// Returns the error corresponding to the given status code. Or `None` if the code is
+    /// `kSCStatusOK`, which does not indicate an error.
+    pub fn from_code(code: i32) -> Option<Self> {
+        let error = match u32::try_from(code) {
+            Ok(kSCStatusOK) => return None,
+            Ok(kSCStatusFailed) => SCError::Failed,
+            Ok(kSCStatusInvalidArgument) => SCError::InvalidArgument,
+            Ok(kSCStatusAccessError) => SCError::AccessError,
+            Ok(kSCStatusNoKey) => SCError::NoKey,
+            Ok(kSCStatusKeyExists) => SCError::KeyExists,
+            Ok(kSCStatusLocked) => SCError::Locked,
+            Ok(kSCStatusNeedLock) => SCError::NeedLock,
+            Ok(kSCStatusNoStoreSession) => SCError::NoStoreSession,
+            Ok(kSCStatusNoStoreServer) => SCError::NoStoreServer,
+            Ok(kSCStatusNotifierActive) => SCError::NotifierActive,
+            Ok(kSCStatusNoPrefsSession) => SCError::NoPrefsSession,
+            Ok(kSCStatusPrefsBusy) => SCError::PrefsBusy,
+            Ok(kSCStatusNoConfigFile) => SCError::NoConfigFile,
+            Ok(kSCStatusNoLink) => SCError::NoLink,
+            Ok(kSCStatusStale) => SCError::Stale,
+            Ok(kSCStatusMaxLink) => SCError::MaxLink,
+            Ok(kSCStatusReachabilityUnknown) => SCError::ReachabilityUnknown,
+            Ok(kSCStatusConnectionNoService) => SCError::ConnectionNoService,
+            Ok(kSCStatusConnectionIgnore) => SCError::ConnectionIgnore,
+            _ => SCError::Other(code),
+        };
+        Some(error)
+    }
+
+    /// Returns the status code of this error.
+    pub fn code(&self) -> i32 {
+        let code = match self {
+            SCError::Failed => kSCStatusFailed,
+            SCError::InvalidArgument => kSCStatusInvalidArgument,
+            SCError::AccessError => kSCStatusAccessError,
+            SCError::NoKey => kSCStatusNoKey,
+            SCError::KeyExists => kSCStatusKeyExists,
+            SCError::Locked => kSCStatusLocked,
+            SCError::NeedLock => kSCStatusNeedLock,
+            SCError::NoStoreSession => kSCStatusNoStoreSession,
+            SCError::NoStoreServer => kSCStatusNoStoreServer,
+            SCError::NotifierActive => kSCStatusNotifierActive,
+            SCError::NoPrefsSession => kSCStatusNoPrefsSession,
+            SCError::PrefsBusy => kSCStatusPrefsBusy,
+            SCError::NoConfigFile => kSCStatusNoConfigFile,
+            SCError::NoLink => kSCStatusNoLink,
+            SCError::Stale => kSCStatusStale,
+            SCError::MaxLink => kSCStatusMaxLink,
+            SCError::ReachabilityUnknown => kSCStatusReachabilityUnknown,
+            SCError::ConnectionNoService => kSCStatusConnectionNoService,
+            SCError::ConnectionIgnore => kSCStatusConnectionIgnore,
+            SCError::Other(code) => return *code,
+        };
+        code as i32
+    }
+
+    /// Returns the error of the most recent SystemConfiguration call made on this thread.
+    ///
+    /// If the framework does not report a failure, [`SCError::Failed`] is returned, since this
+    /// function is only meant to be called after a call indicated that it failed.
+    ///
+    /// See [`SCError()`] for details.
+    ///
+    /// [`SCError()`]: https://developer.apple.com/documentation/systemconfiguration/1516805-scerror?language=objc
+    pub fn last() -> Self {
+        let code = unsafe { crate::sys::system_configuration::SCError() };
+        SCError::from_code(code).unwrap_or(SCError::Failed)
+    }
+
+    /// Returns the description the framework itself has for this error.
+    ///
+    /// See [`SCErrorString`] for details.
+    ///
+    /// [`SCErrorString`]: https://developer.apple.com/documentation/systemconfiguration/1516922-scerrorstring?language=objc
+    pub fn framework_description(&self) -> String {
+        unsafe {
+            let ptr = SCErrorString(self.code());
+            if ptr.is_null() {
+                self.to_string()
+            } else {
+                CStr::from_ptr(ptr).to_string_lossy().into_owned()
+            }
+        }
+    }
+}
+
+impl Display for SCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SCError::Failed => write!(f, "Failed"),
+            SCError::InvalidArgument => write!(f, "Invalid argument"),
+            SCError::AccessError => write!(f, "Permission denied"),
+            SCError::NoKey => write!(f, "No such key"),
+            SCError::KeyExists => write!(f, "Key already defined"),
+            SCError::Locked => write!(f, "Lock already held"),
+            SCError::NeedLock => write!(f, "Lock required for this operation"),
+            SCError::NoStoreSession => write!(f, "Configuration daemon session not active"),
+            SCError::NoStoreServer => write!(f, "Configuration daemon not (no longer) available"),
+            SCError::NotifierActive => write!(f, "Notifier is currently active"),
+            SCError::NoPrefsSession => write!(f, "Preferences session not active"),
+            SCError::PrefsBusy => write!(f, "Preferences update currently in progress"),
+            SCError::NoConfigFile => write!(f, "Configuration file not found"),
+            SCError::NoLink => write!(f, "No such link"),
+            SCError::Stale => write!(f, "Write attempted on stale version of object"),
+            SCError::MaxLink => write!(f, "Maximum link count exceeded"),
+            SCError::ReachabilityUnknown => write!(f, "Network reachability cannot be determined"),
+            SCError::ConnectionNoService => {
+                write!(f, "Network service for connection not available")
+            }
+            SCError::ConnectionIgnore => {
+                write!(
+                    f,
+                    "Network connection information not available at this time"
+                )
+            }
+            SCError::Other(code) => write!(f, "Unknown SystemConfiguration status: {}", code),
+        }
+    }
+}
+
+impl Error for SCError {}
+
+/// Converts the `Boolean` returned by most SystemConfiguration functions into a `Result`, fetching
+/// the reason of the failure with [`SCError::last`].
+pub(crate) fn to_result(success: Boolean) -> Result<(), SCError> {
+    if success != 0 {
+        Ok(())
+    } else {
+        Err(SCError::last())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_ERRORS: [SCError; 19] = [
+        SCError::Failed,
+        SCError::InvalidArgument,
+        SCError::AccessError,
+        SCError::NoKey,
+        SCError::KeyExists,
+        SCError::Locked,
+        SCError::NeedLock,
+        SCError::NoStoreSession,
+        SCError::NoStoreServer,
+        SCError::NotifierActive,
+        SCError::NoPrefsSession,
+        SCError::PrefsBusy,
+        SCError::NoConfigFile,
+        SCError::NoLink,
+        SCError::Stale,
+        SCError::MaxLink,
+        SCError::ReachabilityUnknown,
+        SCError::ConnectionNoService,
+        SCError::ConnectionIgnore,
+    ];
+
+    #[test]
+    fn ok_is_not_an_error() {
+        assert_eq!(SCError::from_code(kSCStatusOK as i32), None);
+    }
+
+    #[test]
+    fn code_round_trip() {
+        for error in ALL_ERRORS {
+            assert_eq!(SCError::from_code(error.code()), Some(error));
+            assert!(!matches!(error, SCError::Other(_)));
+        }
+    }
+
+    #[test]
+    fn known_codes() {
+        assert_eq!(SCError::from_code(1001), Some(SCError::Failed));
+        assert_eq!(SCError::from_code(1006), Some(SCError::Locked));
+        assert_eq!(SCError::from_code(1007), Some(SCError::NeedLock));
+        assert_eq!(SCError::from_code(3005), Some(SCError::Stale));
+        assert_eq!(SCError::NoStoreSession.code(), 2001);
+    }
+
+    #[test]
+    fn unknown_codes() {
+        for code in [-1, 1, 1000, 1008, 9999, i32::MAX, i32::MIN] {
+            let error = SCError::from_code(code).unwrap();
+            assert_eq!(error, SCError::Other(code));
+            assert_eq!(error.code(), code);
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(SCError::KeyExists.to_string(), "Key already defined");
+        assert_eq!(
+            SCError::NeedLock.to_string(),
+            "Lock required for this operation"
+        );
+        assert_eq!(
+            SCError::Other(42).to_string(),
+            "Unknown SystemConfiguration status: 42"
+        );
+        for error in ALL_ERRORS {
+            assert!(!error.to_string().is_empty());
+        }
+    }
+}
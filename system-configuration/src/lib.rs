@@ -25,10 +25,35 @@ pub extern crate core_foundation;
 /// Low-level SystemConfiguration bindings
 pub extern crate system_configuration_sys as sys;
 
+/// Implements `Serialize` and `Deserialize` for a type with an `as_str` method and a lossless
+/// `From<&str>` implementation, so that it is represented by its string.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_as_str {
+    ($ty:ty) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let string = <String as serde::Deserialize>::deserialize(deserializer)?;
+                Ok(Self::from(string.as_str()))
+            }
+        }
+    };
+}
+
+pub mod apply;
 pub mod dynamic_store;
+pub mod error;
 pub mod network_configuration;
+pub mod network_connection;
 pub mod network_reachability;
+pub mod plist;
 pub mod preferences;
+pub mod settings;
 
 pub(crate) mod helpers {
     use core_foundation::array::CFArray;
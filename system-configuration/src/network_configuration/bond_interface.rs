@@ -1,4 +1,4 @@
-#![allow(non_snake_case)]
+#![allow(non_snake_case, non_upper_case_globals)]
 
 use std::os;
 use core_foundation::{
@@ -8,12 +8,24 @@ use core_foundation::{
     string::CFString,
 };
 use sys::network_configuration::{
-    SCNetworkInterfaceGetTypeID, SCBondInterfaceCopyAll, SCBondInterfaceRef, SCBondInterfaceCopyAvailableMemberInterfaces,
-    SCBondInterfaceCreate, SCBondInterfaceGetMemberInterfaces, SCBondInterfaceGetOptions, SCBondInterfaceRemove,
-    SCBondInterfaceSetMemberInterfaces, SCBondInterfaceSetOptions
+    kSCBondStatusLinkInvalid, kSCBondStatusNoPartner, kSCBondStatusNotInActiveGroup, kSCBondStatusOK,
+    kSCBondStatusUnknown, SCNetworkInterfaceGetTypeID, SCBondInterfaceCopyAll, SCBondInterfaceRef,
+    SCBondInterfaceCopyAvailableMemberInterfaces, SCBondInterfaceCopyStatus, SCBondInterfaceCreate,
+    SCBondInterfaceGetMemberInterfaces, SCBondInterfaceGetOptions, SCBondInterfaceRemove,
+    SCBondInterfaceSetLocalizedDisplayName, SCBondInterfaceSetMemberInterfaces, SCBondInterfaceSetOptions,
+    SCBondStatusGetInterfaceStatus, SCBondStatusGetMemberInterfaces, SCBondStatusGetTypeID, SCBondStatusRef
 };
 use super::{SCNetworkInterface, SCNetworkInterfaceSubClass, SCNetworkInterfaceType};
+use crate::error::{to_result, SCError};
+use crate::plist::{dictionary_from_cf, Dictionary};
 use crate::preferences::SCPreferences;
+use crate::settings::{BondOptions, ConfigError, SettingsModel};
+#[cfg(feature = "private")]
+use crate::settings::BondMode;
+#[cfg(feature = "private")]
+use core_foundation::number::CFNumber;
+#[cfg(feature = "private")]
+use sys::network_configuration_private::{SCBondInterfaceGetMode, SCBondInterfaceSetMode};
 
 use crate::helpers::create_empty_array;
 
@@ -187,24 +199,331 @@ impl SCBondInterface {
         }
     }
 
-    /// Removes the Ethernet bond interface from the configuration.
+    /// Returns the options of the Ethernet bond interface. The options are empty if no changes to
+    /// the default configuration have been saved.
+    pub fn bond_options(&self) -> Result<BondOptions, ConfigError> {
+        match self.options() {
+            Some(options) => BondOptions::from_dictionary(&options),
+            None => Ok(BondOptions::default()),
+        }
+    }
+
+    /// Returns the current status of the Ethernet bond interface and of each of its members. Or
+    /// `None` if the bond is not active.
+    ///
+    /// See [`SCBondInterfaceCopyStatus`] for more details.
     ///
-    /// Returns: `true` if the interface was removed; `false` if an error was encountered.
-    pub fn remove(self) -> bool {
-        (unsafe { SCBondInterfaceRemove(self.0) }) != 0
+    /// [`SCBondInterfaceCopyStatus`]: https://developer.apple.com/documentation/systemconfiguration/scbondinterfacecopystatus(_:)?language=objc
+    pub fn status(&self) -> Option<BondStatus> {
+        unsafe {
+            let status_ref = SCBondInterfaceCopyStatus(self.0);
+            if status_ref.is_null() {
+                return None;
+            }
+            Some(BondStatus::from(&SCBondStatus::wrap_under_create_rule(status_ref)))
+        }
+    }
+
+    /// Removes the Ethernet bond interface from the configuration.
+    pub fn remove(self) -> Result<(), SCError> {
+        to_result(unsafe { SCBondInterfaceRemove(self.0) })
     }
 
     /// Sets the member interfaces for the specified Ethernet bond interface.
-    ///
-    /// Returns: `true` if the configuration was stored; `false` if an error occurred.
-    pub fn set_member_interfaces(&mut self, members: &CFArray<SCNetworkInterface>) -> bool {
-        (unsafe { SCBondInterfaceSetMemberInterfaces(self.0, members.as_concrete_TypeRef()) }) != 0
+    pub fn set_member_interfaces(
+        &mut self,
+        members: &CFArray<SCNetworkInterface>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCBondInterfaceSetMemberInterfaces(self.0, members.as_concrete_TypeRef())
+        })
     }
 
     /// Sets the configuration settings for the specified Ethernet bond interface.
+    pub fn set_options(
+        &mut self,
+        new_options: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe { SCBondInterfaceSetOptions(self.0, new_options.as_concrete_TypeRef()) })
+    }
+
+    /// Sets the options of the Ethernet bond interface.
+    pub fn set_bond_options(&mut self, options: &BondOptions) -> Result<(), SCError> {
+        self.set_options(&options.to_dictionary())
+    }
+
+    /// Returns how the members of the Ethernet bond interface are aggregated. Or `None` if no
+    /// mode has been saved, in which case the framework uses LACP.
+    ///
+    /// The mode is stored beside the options of the bond, not in them.
+    #[cfg(feature = "private")]
+    pub fn mode(&self) -> Option<BondMode> {
+        unsafe {
+            let mode_ref = SCBondInterfaceGetMode(self.0);
+            if mode_ref.is_null() {
+                return None;
+            }
+            CFNumber::wrap_under_get_rule(mode_ref)
+                .to_i64()
+                .map(BondMode::from)
+        }
+    }
+
+    /// Sets how the members of the Ethernet bond interface are aggregated.
+    ///
+    /// Fails with [`SCError::InvalidArgument`] if the framework does not support the mode.
+    #[cfg(feature = "private")]
+    pub fn set_mode(&mut self, mode: BondMode) -> Result<(), SCError> {
+        let mode = CFNumber::from(i64::from(mode));
+        to_result(unsafe { SCBondInterfaceSetMode(self.0, mode.as_concrete_TypeRef()) })
+    }
+
+    /// Sets the localized display name of the Ethernet bond interface.
+    pub fn set_display_name(&mut self, name: &CFString) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCBondInterfaceSetLocalizedDisplayName(self.0, name.as_concrete_TypeRef())
+        })
+    }
+}
+
+/// `kSCBondStatusDeviceAggregationStatus`.
+const AGGREGATION_STATUS: &str = "AggregationStatus";
+/// `kSCBondStatusDeviceCollecting`.
+const COLLECTING: &str = "Collecting";
+/// `kSCBondStatusDeviceDistributing`.
+const DISTRIBUTING: &str = "Distributing";
+
+core_foundation::declare_TCFType! {
+    /// The status of an Ethernet bond interface and its members, as returned by
+    /// `SCBondInterfaceCopyStatus`. Use [`BondStatus`] for an owned, typed snapshot.
+    ///
+    /// See [`SCBondStatusRef`] for details.
+    ///
+    /// [`SCBondStatusRef`]: https://developer.apple.com/documentation/systemconfiguration/scbondstatus?language=objc
+    SCBondStatus, SCBondStatusRef
+}
+core_foundation::impl_TCFType!(SCBondStatus, SCBondStatusRef, SCBondStatusGetTypeID);
+core_foundation::impl_CFTypeDescription!(SCBondStatus);
+
+impl SCBondStatus {
+    /// Returns the member interfaces of the bond the status is for.
+    ///
+    /// See [`SCBondStatusGetMemberInterfaces`] for more details.
+    ///
+    /// [`SCBondStatusGetMemberInterfaces`]: https://developer.apple.com/documentation/systemconfiguration/scbondstatusgetmemberinterfaces(_:)?language=objc
+    pub fn member_interfaces(&self) -> CFArray<SCNetworkInterface> {
+        unsafe {
+            let array_ptr = SCBondStatusGetMemberInterfaces(self.0);
+            if array_ptr.is_null() {
+                return create_empty_array();
+            }
+            CFArray::<SCNetworkInterface>::wrap_under_get_rule(array_ptr)
+        }
+    }
+
+    /// Returns the status dictionary of the given member interface, or of the bond itself if
+    /// `interface` is `None`.
+    ///
+    /// See [`SCBondStatusGetInterfaceStatus`] for more details.
     ///
-    /// Returns: `true` if the configuration was stored; `false` if an error occurred.
-    pub fn set_options(&mut self, new_options: &CFDictionary<CFString, CFType>) -> bool {
-        (unsafe { SCBondInterfaceSetOptions(self.0, new_options.as_concrete_TypeRef()) }) != 0
+    /// [`SCBondStatusGetInterfaceStatus`]: https://developer.apple.com/documentation/systemconfiguration/scbondstatusgetinterfacestatus(_:_:)?language=objc
+    pub fn interface_status(
+        &self,
+        interface: Option<&SCNetworkInterface>,
+    ) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe {
+            let dictionary_ref = SCBondStatusGetInterfaceStatus(
+                self.0,
+                interface.map_or(std::ptr::null(), |interface| interface.as_concrete_TypeRef()),
+            );
+            if !dictionary_ref.is_null() {
+                Some(CFDictionary::wrap_under_get_rule(dictionary_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn device_status(&self, interface: Option<&SCNetworkInterface>) -> BondDeviceStatus {
+        self.interface_status(interface)
+            .and_then(|dictionary| dictionary_from_cf(&dictionary))
+            .map(|dictionary| BondDeviceStatus::from_plist(&dictionary))
+            .unwrap_or_default()
+    }
+}
+
+/// The aggregation status of a bond or one of its members, the `kSCBondStatus*` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BondMemberStatus {
+    /// `kSCBondStatusOK`, the link is active and aggregated.
+    Ok,
+    /// `kSCBondStatusLinkInvalid`, the link has no carrier or the wrong speed or duplex.
+    LinkInvalid,
+    /// `kSCBondStatusNoPartner`, the link is not receiving LACP frames from its partner.
+    NoPartner,
+    /// `kSCBondStatusNotInActiveGroup`, the link is aggregated with a different partner than the
+    /// active links.
+    NotInActiveGroup,
+    /// `kSCBondStatusUnknown`, the status of the link cannot be determined.
+    Unknown,
+    /// A status not known to this crate.
+    Other(i64),
+}
+
+impl From<i64> for BondMemberStatus {
+    fn from(code: i64) -> Self {
+        match u32::try_from(code) {
+            Ok(kSCBondStatusOK) => BondMemberStatus::Ok,
+            Ok(kSCBondStatusLinkInvalid) => BondMemberStatus::LinkInvalid,
+            Ok(kSCBondStatusNoPartner) => BondMemberStatus::NoPartner,
+            Ok(kSCBondStatusNotInActiveGroup) => BondMemberStatus::NotInActiveGroup,
+            Ok(kSCBondStatusUnknown) => BondMemberStatus::Unknown,
+            _ => BondMemberStatus::Other(code),
+        }
+    }
+}
+
+impl From<BondMemberStatus> for i64 {
+    fn from(status: BondMemberStatus) -> Self {
+        let code = match status {
+            BondMemberStatus::Ok => kSCBondStatusOK,
+            BondMemberStatus::LinkInvalid => kSCBondStatusLinkInvalid,
+            BondMemberStatus::NoPartner => kSCBondStatusNoPartner,
+            BondMemberStatus::NotInActiveGroup => kSCBondStatusNotInActiveGroup,
+            BondMemberStatus::Unknown => kSCBondStatusUnknown,
+            BondMemberStatus::Other(code) => return code,
+        };
+        i64::from(code)
+    }
+}
+
+/// The status of a bond or one of its members. Every field is `None` when the framework did not
+/// report it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BondDeviceStatus {
+    /// `kSCBondStatusDeviceAggregationStatus`.
+    pub aggregation_status: Option<BondMemberStatus>,
+    /// `kSCBondStatusDeviceCollecting`, whether frames received on the link are accepted.
+    pub collecting: Option<bool>,
+    /// `kSCBondStatusDeviceDistributing`, whether frames are sent on the link.
+    pub distributing: Option<bool>,
+}
+
+impl BondDeviceStatus {
+    /// Parses a status dictionary as returned by [`SCBondStatus::interface_status`]. Values of
+    /// the wrong type are treated as absent.
+    pub fn from_plist(dictionary: &Dictionary) -> Self {
+        let integer = |key: &str| dictionary.get(key).and_then(|value| value.as_i64());
+        BondDeviceStatus {
+            aggregation_status: integer(AGGREGATION_STATUS).map(BondMemberStatus::from),
+            collecting: integer(COLLECTING).map(|collecting| collecting != 0),
+            distributing: integer(DISTRIBUTING).map(|distributing| distributing != 0),
+        }
+    }
+
+    /// Returns `true` if the link is aggregated and carries traffic in both directions.
+    pub fn is_active(&self) -> bool {
+        self.aggregation_status == Some(BondMemberStatus::Ok)
+            && self.collecting != Some(false)
+            && self.distributing != Some(false)
+    }
+}
+
+/// A member of a bond together with its status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondMember {
+    /// The member interface.
+    pub interface: SCNetworkInterface,
+    /// The status of the member.
+    pub status: BondDeviceStatus,
+}
+
+/// A snapshot of the status of an Ethernet bond interface, as returned by
+/// [`SCBondInterface::status`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondStatus {
+    /// The status of the bond as a whole.
+    pub bond: BondDeviceStatus,
+    /// The status of each member of the bond.
+    pub members: Vec<BondMember>,
+}
+
+impl From<&SCBondStatus> for BondStatus {
+    fn from(status: &SCBondStatus) -> Self {
+        BondStatus {
+            bond: status.device_status(None),
+            members: status
+                .member_interfaces()
+                .iter()
+                .map(|interface| BondMember {
+                    status: status.device_status(Some(&interface)),
+                    interface: interface.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plist::PlistValue;
+
+    #[test]
+    fn member_status_codes() {
+        for code in [0, 1, 2, 3, 999, 4, -1] {
+            assert_eq!(i64::from(BondMemberStatus::from(code)), code);
+        }
+        assert_eq!(BondMemberStatus::from(0), BondMemberStatus::Ok);
+        assert_eq!(BondMemberStatus::from(2), BondMemberStatus::NoPartner);
+        assert_eq!(BondMemberStatus::from(999), BondMemberStatus::Unknown);
+        assert_eq!(BondMemberStatus::from(4), BondMemberStatus::Other(4));
+    }
+
+    #[test]
+    fn parse_device_status() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(AGGREGATION_STATUS.to_owned(), PlistValue::Integer(0));
+        dictionary.insert(COLLECTING.to_owned(), PlistValue::Integer(1));
+        dictionary.insert(DISTRIBUTING.to_owned(), PlistValue::Integer(1));
+        let status = BondDeviceStatus::from_plist(&dictionary);
+        assert_eq!(status.aggregation_status, Some(BondMemberStatus::Ok));
+        assert!(status.is_active());
+
+        dictionary.insert(AGGREGATION_STATUS.to_owned(), PlistValue::Integer(3));
+        dictionary.insert(DISTRIBUTING.to_owned(), "yes".into());
+        let status = BondDeviceStatus::from_plist(&dictionary);
+        assert_eq!(
+            status.aggregation_status,
+            Some(BondMemberStatus::NotInActiveGroup)
+        );
+        assert_eq!(status.distributing, None);
+        assert!(!status.is_active());
+        assert_eq!(
+            BondDeviceStatus::from_plist(&Dictionary::new()),
+            BondDeviceStatus::default()
+        );
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use sys::network_configuration::{
+            kSCBondStatusDeviceAggregationStatus, kSCBondStatusDeviceCollecting,
+            kSCBondStatusDeviceDistributing,
+        };
+
+        let keys = unsafe {
+            [
+                (AGGREGATION_STATUS, kSCBondStatusDeviceAggregationStatus),
+                (COLLECTING, kSCBondStatusDeviceCollecting),
+                (DISTRIBUTING, kSCBondStatusDeviceDistributing),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
     }
 }
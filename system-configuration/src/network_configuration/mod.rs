@@ -7,6 +7,7 @@ mod bond_interface;
 mod network_protocol;
 mod network_service;
 mod network_set;
+mod vlan_interface;
 
 #[cfg(feature = "private")]
 mod private {
@@ -21,12 +22,13 @@ pub use bond_interface::*;
 pub use network_protocol::*;
 pub use network_service::*;
 pub use network_set::*;
+pub use vlan_interface::*;
 #[cfg(feature = "private")]
 pub use private::*;
 
 #[cfg(test)]
 mod test {
-    use core_foundation::string::CFString;
+    use core_foundation::{base::TCFType, string::CFString};
     use crate::preferences::SCPreferences;
 
     use crate::helpers::create_empty_array;
@@ -40,7 +42,10 @@ mod test {
     #[test]
     fn test_get_type() {
         for iface in get_interfaces().into_iter() {
-            if iface.interface_type().is_none() {
+            if matches!(
+                iface.interface_type(),
+                None | Some(SCNetworkInterfaceType::Other(_))
+            ) {
                 panic!(
                     "Interface  {:?} ({:?}) has unrecognized type {:?}",
                     iface.display_name(),
@@ -51,6 +56,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_layer_interfaces() {
+        for iface in get_interfaces().into_iter() {
+            assert_eq!(iface.interface_stack()[0], *iface);
+            for type_string in iface.supported_interface_type_strings().iter() {
+                let interface_type = SCNetworkInterfaceType::from_cfstring(&type_string);
+                let layered = iface.layer(interface_type).unwrap();
+                assert_eq!(layered.interface_type_string(), Some(type_string.clone()));
+                let stack = layered.interface_stack();
+                assert!(stack.len() >= 2);
+                assert_eq!(stack[1], *iface);
+            }
+            if iface.supported_interface_type_strings().is_empty() {
+                assert!(iface.layer(SCNetworkInterfaceType::PPP).is_none());
+            }
+        }
+    }
+
     #[test]
     fn test_service_order() {
         let prefs = SCPreferences::default(&CFString::new("test"));
@@ -65,6 +88,71 @@ mod test {
         }))
     }
 
+    #[test]
+    fn test_service_name_and_protocols() {
+        // The preferences are never committed, so the changes below only live in this session.
+        let prefs = SCPreferences::default(&CFString::new("test"));
+        for service in SCNetworkService::get_services(&prefs).iter() {
+            let mut service = service.clone();
+            let name = service.name();
+            service.set_name(name.as_ref()).unwrap();
+            assert_eq!(service.name(), name);
+
+            if service.protocol(SCNetworkProtocolType::SMB).is_some() {
+                service
+                    .remove_network_protocol(SCNetworkProtocolType::SMB)
+                    .unwrap();
+                assert!(service.protocol(SCNetworkProtocolType::SMB).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_set() {
+        // The preferences are never committed, so the new set only lives in this session.
+        let prefs = SCPreferences::default(&CFString::new("test"));
+        let set = SCNetworkSet::new(&prefs);
+        let name = CFString::new("system-configuration-rs test location");
+        let copy = set.duplicate(&prefs, &name).unwrap();
+
+        assert_eq!(copy.name(), Some(name));
+        assert_ne!(copy.id(), set.id());
+        assert_eq!(copy.services().len(), set.services().len());
+        assert_eq!(copy.service_order().len(), set.service_order().len());
+        let names = |set: &SCNetworkSet| {
+            let mut names = set
+                .services()
+                .iter()
+                .map(|service| service.name().map(|name| name.to_string()))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&copy), names(&set));
+        for (id, copy_id) in set.service_order().iter().zip(copy.service_order().iter()) {
+            let interface = |id: &CFString| {
+                SCNetworkService::find_service(&prefs, id.clone())
+                    .and_then(|service| service.network_interface())
+                    .unwrap()
+            };
+            let configuration = |interface: SCNetworkInterface| {
+                interface
+                    .configuration()
+                    .map(|configuration| configuration.as_CFType())
+            };
+            assert_eq!(
+                configuration(interface(&copy_id)),
+                configuration(interface(&id))
+            );
+        }
+        for service in copy.services().iter() {
+            assert!(!set
+                .services()
+                .iter()
+                .any(|original| original.id() == service.id()));
+        }
+    }
+
     #[test]
     fn test_empty_array() {
         let empty = create_empty_array::<CFString>();
@@ -1,14 +1,18 @@
 #![allow(non_snake_case)]
 
-use std::{mem, ptr};
+use std::{convert::Infallible, fmt, hash::{Hash, Hasher}, mem, ptr, str::FromStr};
 use core_foundation::{
-    array::CFArray,
-    base::{TCFType, TCFTypeRef},
+    array::{CFArray, CFArrayRef},
+    base::{CFType, TCFType, TCFTypeRef},
+    dictionary::{CFDictionary, CFDictionaryRef},
     string::CFString,
 };
-use sys::network_configuration::{SCNetworkInterfaceCopyAll, SCNetworkInterfaceCopyMTU, SCNetworkInterfaceGetBSDName, SCNetworkInterfaceGetHardwareAddressString, SCNetworkInterfaceGetInterface, SCNetworkInterfaceGetInterfaceType, SCNetworkInterfaceGetLocalizedDisplayName, SCNetworkInterfaceGetSupportedInterfaceTypes, SCNetworkInterfaceGetSupportedProtocolTypes, SCNetworkInterfaceGetTypeID, SCNetworkInterfaceRef, SCNetworkInterfaceSetMTU};
+use sys::network_configuration::{kSCNetworkInterfaceIPv4, SCNetworkInterfaceCopyAll, SCNetworkInterfaceCopyMTU, SCNetworkInterfaceCopyMediaOptions, SCNetworkInterfaceCopyMediaSubTypeOptions, SCNetworkInterfaceCopyMediaSubTypes, SCNetworkInterfaceCreateWithInterface, SCNetworkInterfaceGetBSDName, SCNetworkInterfaceGetConfiguration, SCNetworkInterfaceGetExtendedConfiguration, SCNetworkInterfaceGetHardwareAddressString, SCNetworkInterfaceGetInterface, SCNetworkInterfaceGetInterfaceType, SCNetworkInterfaceGetLocalizedDisplayName, SCNetworkInterfaceGetSupportedInterfaceTypes, SCNetworkInterfaceGetSupportedProtocolTypes, SCNetworkInterfaceGetTypeID, SCNetworkInterfaceRef, SCNetworkInterfaceSetConfiguration, SCNetworkInterfaceSetExtendedConfiguration, SCNetworkInterfaceSetMTU, SCNetworkInterfaceSetMediaOptions};
 
+use crate::error::{to_result, SCError};
 use crate::helpers::create_empty_array;
+use crate::plist::{dictionary_from_cf, PlistValue};
+use crate::settings::{MediaOptions, MediaSelection, SettingsModel};
 
 /// Trait for all subclasses of [`SCNetworkInterface`].
 ///
@@ -67,6 +71,17 @@ impl SCNetworkInterface {
         get_interfaces()
     }
 
+    /// Returns the IPv4 interface. It is not listed by [`get_interfaces`], but is the root that
+    /// VPN interfaces such as L2TP, PPTP and IPSec are [layered](Self::layer) on.
+    ///
+    /// See [`kSCNetworkInterfaceIPv4`] for details.
+    ///
+    /// [`get_interfaces`]: #method.get_interfaces
+    /// [`kSCNetworkInterfaceIPv4`]: https://developer.apple.com/documentation/systemconfiguration/kscnetworkinterfaceipv4?language=objc
+    pub fn ipv4() -> Self {
+        unsafe { Self::wrap_under_get_rule(kSCNetworkInterfaceIPv4) }
+    }
+
     /// Try to downcast the [`SCNetworkInterface`] to a subclass. Checking if the instance is the
     /// correct subclass happens at runtime and `None` is returned if it is not the correct type.
     /// Works similar to [`CFPropertyList::downcast`](core_foundation::propertylist::CFPropertyList::downcast)
@@ -98,13 +113,16 @@ impl SCNetworkInterface {
         }
     }
 
-    /// Get type of the network interface, if the type is recognized, returns `None` otherwise.
+    /// Get type of the network interface. Types not known to this crate are returned as
+    /// [`SCNetworkInterfaceType::Other`].
     ///
     /// See [`SCNetworkInterfaceGetInterfaceType`] for details.
     ///
     /// [`SCNetworkInterfaceGetInterfaceType`]: https://developer.apple.com/documentation/systemconfiguration/1517371-scnetworkinterfacegetinterfacety?language=objc
     pub fn interface_type(&self) -> Option<SCNetworkInterfaceType> {
-        SCNetworkInterfaceType::from_cfstring(&self.interface_type_string()?)
+        Some(SCNetworkInterfaceType::from_cfstring(
+            &self.interface_type_string()?,
+        ))
     }
 
     /// Returns the raw interface type identifier.
@@ -156,6 +174,45 @@ impl SCNetworkInterface {
         }
     }
 
+    /// Returns this interface followed by every interface it is layered on, down to the leaf
+    /// interface. For PPP over Ethernet that is the PPP interface followed by the Ethernet one.
+    pub fn interface_stack(&self) -> Vec<Self> {
+        let mut stack = vec![self.clone()];
+        while let Some(underlying) = stack.last().and_then(Self::underlying_interface) {
+            stack.push(underlying);
+        }
+        stack
+    }
+
+    /// Creates a new interface of the given type layered on top of this interface, such as PPP
+    /// on top of a modem or L2TP on top of [IPv4](Self::ipv4). Or `None` if this interface does
+    /// not support that type being layered on top of it, see
+    /// [`supported_interface_type_strings`].
+    ///
+    /// See [`SCNetworkInterfaceCreateWithInterface`] for details.
+    ///
+    /// [`supported_interface_type_strings`]: #method.supported_interface_type_strings
+    /// [`SCNetworkInterfaceCreateWithInterface`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacecreatewithinterface(_:_:)?language=objc
+    pub fn layer(&self, interface_type: SCNetworkInterfaceType) -> Option<Self> {
+        let interface_type = interface_type.to_cfstring();
+        if !self
+            .supported_interface_type_strings()
+            .iter()
+            .any(|supported| *supported == interface_type)
+        {
+            return None;
+        }
+        unsafe {
+            let ptr =
+                SCNetworkInterfaceCreateWithInterface(self.0, interface_type.as_concrete_TypeRef());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Self::wrap_under_create_rule(ptr))
+            }
+        }
+    }
+
     /// Returns a displayable link layer address for the specified interface, i.e. the hardware
     /// MAC (Media Access Control) address for the interface.
     ///
@@ -247,11 +304,241 @@ impl SCNetworkInterface {
     }
 
     /// Sets the requested MTU setting for the specified network interface.
+    pub fn set_mtu(&mut self, mtu: u32) -> Result<(), SCError> {
+        let Ok(mtu) = TryInto::<std::ffi::c_int>::try_into(mtu) else {
+            return Err(SCError::InvalidArgument);
+        };
+        to_result(unsafe { SCNetworkInterfaceSetMTU(self.0, mtu) })
+    }
+
+    /// Returns the configuration settings associated with the interface. Or `None` if no
+    /// configuration settings are associated with the interface.
     ///
-    /// Returns: `true` if the configuration was updated; `false` if an error occurred.
-    pub fn set_mtu(&mut self, mtu: u32) -> bool {
-        let Ok(mtu) = TryInto::<std::ffi::c_int>::try_into(mtu) else { return false; };
-        (unsafe { SCNetworkInterfaceSetMTU(self.0, mtu) }) != 0
+    /// See [`SCNetworkInterfaceGetConfiguration`] for details.
+    ///
+    /// [`SCNetworkInterfaceGetConfiguration`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacegetconfiguration(_:)?language=objc
+    pub fn configuration(&self) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe {
+            let dictionary_ref = SCNetworkInterfaceGetConfiguration(self.0);
+            if !dictionary_ref.is_null() {
+                Some(CFDictionary::wrap_under_get_rule(dictionary_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Stores the configuration settings for the interface.
+    pub fn set_configuration(
+        &mut self,
+        config: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCNetworkInterfaceSetConfiguration(self.0, config.as_concrete_TypeRef())
+        })
+    }
+
+    /// Returns the configuration settings of the given extended type, such as `EAPOL` for
+    /// 802.1X, associated with the interface. Or `None` if there are no such settings.
+    ///
+    /// See [`SCNetworkInterfaceGetExtendedConfiguration`] for details.
+    ///
+    /// [`SCNetworkInterfaceGetExtendedConfiguration`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacegetextendedconfiguration(_:_:)?language=objc
+    pub fn extended_configuration<S: Into<CFString>>(
+        &self,
+        extended_type: S,
+    ) -> Option<CFDictionary<CFString, CFType>> {
+        let extended_type = extended_type.into();
+        unsafe {
+            let dictionary_ref = SCNetworkInterfaceGetExtendedConfiguration(
+                self.0,
+                extended_type.as_concrete_TypeRef(),
+            );
+            if !dictionary_ref.is_null() {
+                Some(CFDictionary::wrap_under_get_rule(dictionary_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Stores the configuration settings of the given extended type for the interface.
+    pub fn set_extended_configuration<S: Into<CFString>>(
+        &mut self,
+        extended_type: S,
+        config: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        let extended_type = extended_type.into();
+        to_result(unsafe {
+            SCNetworkInterfaceSetExtendedConfiguration(
+                self.0,
+                extended_type.as_concrete_TypeRef(),
+                config.as_concrete_TypeRef(),
+            )
+        })
+    }
+
+    /// Returns the current, active and available media of the interface. Or `None` if the
+    /// interface does not support media selection. If `filter` is set, media that should not be
+    /// offered to the user are left out of the available media.
+    ///
+    /// Media dictionaries the framework returns without a subtype are skipped.
+    ///
+    /// See [`SCNetworkInterfaceCopyMediaOptions`] for details.
+    ///
+    /// [`SCNetworkInterfaceCopyMediaOptions`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacecopymediaoptions(_:_:_:_:_:)?language=objc
+    pub fn media_options(&self, filter: bool) -> Option<MediaOptions> {
+        let mut current = ptr::null();
+        let mut active = ptr::null();
+        let mut available = ptr::null();
+        let succeeded = unsafe {
+            SCNetworkInterfaceCopyMediaOptions(
+                self.0,
+                &mut current,
+                &mut active,
+                &mut available,
+                filter as u8,
+            )
+        } != 0;
+
+        let selection = |dictionary_ref: CFDictionaryRef| -> Option<MediaSelection> {
+            if dictionary_ref.is_null() {
+                return None;
+            }
+            let dictionary = unsafe { CFDictionary::wrap_under_create_rule(dictionary_ref) };
+            MediaSelection::from_plist(&dictionary_from_cf(&dictionary)?).ok()
+        };
+        let current = selection(current);
+        let active = selection(active);
+        let available = if available.is_null() {
+            Vec::new()
+        } else {
+            let available = unsafe { CFArray::<CFType>::wrap_under_create_rule(available) };
+            match PlistValue::from_cf_type(&available.as_CFType()) {
+                Some(PlistValue::Array(entries)) => entries
+                    .iter()
+                    .filter_map(PlistValue::as_dictionary)
+                    .filter_map(|dictionary| MediaSelection::from_plist(dictionary).ok())
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        if succeeded {
+            Some(MediaOptions {
+                current,
+                active,
+                available,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the available media subtypes of the interface, such as `autoselect` or
+    /// `1000baseT`. Or `None` if the interface does not support media selection. `filter` works
+    /// like for [`media_options`](Self::media_options).
+    ///
+    /// See [`SCNetworkInterfaceCopyMediaSubTypes`] for details.
+    ///
+    /// [`SCNetworkInterfaceCopyMediaSubTypes`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacecopymediasubtypes(_:)?language=objc
+    pub fn media_subtypes(&self, filter: bool) -> Option<Vec<String>> {
+        let available = self.available_media(filter)?;
+        let subtypes = copy_array(unsafe {
+            SCNetworkInterfaceCopyMediaSubTypes(available.as_concrete_TypeRef())
+        })?;
+        Some(
+            subtypes
+                .iter()
+                .filter_map(PlistValue::as_str)
+                .map(str::to_owned)
+                .collect(),
+        )
+    }
+
+    /// Returns the combinations of options available for a media subtype of the interface. Or
+    /// `None` if the interface does not support media selection or does not have the subtype.
+    /// `filter` works like for [`media_options`](Self::media_options).
+    ///
+    /// See [`SCNetworkInterfaceCopyMediaSubTypeOptions`] for details.
+    ///
+    /// [`SCNetworkInterfaceCopyMediaSubTypeOptions`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacecopymediasubtypeoptions(_:_:)?language=objc
+    pub fn media_subtype_options(&self, subtype: &str, filter: bool) -> Option<Vec<Vec<String>>> {
+        let available = self.available_media(filter)?;
+        let subtype = CFString::new(subtype);
+        let options = copy_array(unsafe {
+            SCNetworkInterfaceCopyMediaSubTypeOptions(
+                available.as_concrete_TypeRef(),
+                subtype.as_concrete_TypeRef(),
+            )
+        })?;
+        Some(
+            options
+                .iter()
+                .filter_map(PlistValue::as_array)
+                .map(|options| {
+                    options
+                        .iter()
+                        .filter_map(PlistValue::as_str)
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the raw available media, as passed to the media subtype functions.
+    fn available_media(&self, filter: bool) -> Option<CFArray<CFType>> {
+        let mut available = ptr::null();
+        unsafe {
+            SCNetworkInterfaceCopyMediaOptions(
+                self.0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut available,
+                filter as u8,
+            );
+            if available.is_null() {
+                None
+            } else {
+                Some(CFArray::wrap_under_create_rule(available))
+            }
+        }
+    }
+
+    /// Sets the media subtype and options the interface should use. Use
+    /// [`MediaOptions::validate`] to check that the interface supports them first.
+    ///
+    /// See [`SCNetworkInterfaceSetMediaOptions`] for details.
+    ///
+    /// [`SCNetworkInterfaceSetMediaOptions`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkinterfacesetmediaoptions(_:_:_:)?language=objc
+    pub fn set_media_options(&mut self, selection: &MediaSelection) -> Result<(), SCError> {
+        let subtype = CFString::new(&selection.subtype);
+        let options = selection
+            .options
+            .iter()
+            .map(|option| CFString::new(option))
+            .collect::<Vec<_>>();
+        let options = CFArray::from_CFTypes(&options);
+        to_result(unsafe {
+            SCNetworkInterfaceSetMediaOptions(
+                self.0,
+                subtype.as_concrete_TypeRef(),
+                options.as_concrete_TypeRef(),
+            )
+        })
+    }
+}
+
+/// Takes ownership of an array returned by a copy function and converts its elements.
+fn copy_array(array_ref: CFArrayRef) -> Option<Vec<PlistValue>> {
+    if array_ref.is_null() {
+        return None;
+    }
+    let array = unsafe { CFArray::<CFType>::wrap_under_create_rule(array_ref) };
+    match PlistValue::from_cf_type(&array.as_CFType())? {
+        PlistValue::Array(elements) => Some(elements),
+        _ => None,
     }
 }
 
@@ -272,7 +559,7 @@ pub struct SCNetworkInterfaceMTU {
 /// See [_Network Interface Types_] documentation for details.
 ///
 /// [_Network Interface Types_]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconfiguration/network_interface_types?language=objc
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum SCNetworkInterfaceType {
     /// A 6to4 interface.
     SixToFour,
@@ -310,114 +597,118 @@ pub enum SCNetworkInterfaceType {
     WWAN,
     /// IPv4 interface.
     IPv4,
+    /// Loopback interface, referred to as `kSCNetworkInterfaceTypeLoopback` in private headers.
+    Loopback,
+    /// VPN interface, referred to as `kSCNetworkInterfaceTypeVPN` in private headers.
+    VPN,
+    /// An interface type not known to this crate, holding its raw identifier.
+    Other(String),
 }
 
-/// Bridge interface type referred to as `kSCNetworkInterfaceTypeBridge` in private headers.
-#[cfg(not(feature = "private"))]
-static BRIDGE_INTERFACE_TYPE_ID: &str = "Bridge";
-
-/// IrDA interface referenced as `kSCNetworkInterfaceTypeIrDA` but deprecated since macOS 12.
-static IRDA_INTERFACE_TYPE_ID: &str = "IrDA";
-
 impl SCNetworkInterfaceType {
-    /// Tries to construct a type by matching it to string constants used to identify a network
-    /// interface type. If no constants match it, `None` is returned.
-    pub fn from_cfstring(type_id: &CFString) -> Option<Self> {
-        #[cfg(feature = "private")]
-        use system_configuration_sys::network_configuration_private::*;
-        use system_configuration_sys::network_configuration::*;
+    /// Constructs a type from the string constant used to identify a network interface type.
+    /// Identifiers not known to this crate are kept as [`Other`](Self::Other).
+    pub fn from_cfstring(type_id: &CFString) -> Self {
+        Self::from(type_id.to_string().as_str())
+    }
 
-        let id_is_equal_to = |const_str| -> bool {
-            let const_str = unsafe { CFString::wrap_under_get_rule(const_str) };
-            &const_str == type_id
-        };
-        unsafe {
-            if id_is_equal_to(kSCNetworkInterfaceType6to4) {
-                Some(SCNetworkInterfaceType::SixToFour)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeBluetooth) {
-                Some(SCNetworkInterfaceType::Bluetooth)
-            } else if {
-                #[cfg(feature = "private")]
-                let matches = id_is_equal_to(kSCNetworkInterfaceTypeBridge);
-
-                #[cfg(not(feature = "private"))]
-                let matches = type_id == &BRIDGE_INTERFACE_TYPE_ID;
-
-                matches
-            } {
-                Some(SCNetworkInterfaceType::Bridge)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeBond) {
-                Some(SCNetworkInterfaceType::Bond)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeEthernet) {
-                Some(SCNetworkInterfaceType::Ethernet)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeFireWire) {
-                Some(SCNetworkInterfaceType::FireWire)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeIEEE80211) {
-                Some(SCNetworkInterfaceType::IEEE80211)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeIPSec) {
-                Some(SCNetworkInterfaceType::IPSec)
-            } else if type_id == &IRDA_INTERFACE_TYPE_ID {
-                Some(SCNetworkInterfaceType::IrDA)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeL2TP) {
-                Some(SCNetworkInterfaceType::L2TP)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeModem) {
-                Some(SCNetworkInterfaceType::Modem)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypePPP) {
-                Some(SCNetworkInterfaceType::PPP)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypePPTP) {
-                Some(SCNetworkInterfaceType::PPTP)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeSerial) {
-                Some(SCNetworkInterfaceType::Serial)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeVLAN) {
-                Some(SCNetworkInterfaceType::VLAN)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeWWAN) {
-                Some(SCNetworkInterfaceType::WWAN)
-            } else if id_is_equal_to(kSCNetworkInterfaceTypeIPv4) {
-                Some(SCNetworkInterfaceType::IPv4)
-            } else {
-                None
-            }
+    /// Returns the string constant used to identify this network interface type.
+    pub fn to_cfstring(&self) -> CFString {
+        CFString::new(self.as_str())
+    }
+
+    /// Returns the identifier of this network interface type, the value of the matching
+    /// `kSCNetworkInterfaceType*` constant.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SCNetworkInterfaceType::SixToFour => "6to4",
+            SCNetworkInterfaceType::Bluetooth => "Bluetooth",
+            SCNetworkInterfaceType::Bridge => "Bridge",
+            SCNetworkInterfaceType::Bond => "Bond",
+            SCNetworkInterfaceType::Ethernet => "Ethernet",
+            SCNetworkInterfaceType::FireWire => "FireWire",
+            SCNetworkInterfaceType::IEEE80211 => "IEEE80211",
+            SCNetworkInterfaceType::IPSec => "IPSec",
+            SCNetworkInterfaceType::IrDA => "IrDA",
+            SCNetworkInterfaceType::L2TP => "L2TP",
+            SCNetworkInterfaceType::Modem => "Modem",
+            SCNetworkInterfaceType::PPP => "PPP",
+            SCNetworkInterfaceType::PPTP => "PPTP",
+            SCNetworkInterfaceType::Serial => "Serial",
+            SCNetworkInterfaceType::VLAN => "VLAN",
+            SCNetworkInterfaceType::WWAN => "WWAN",
+            SCNetworkInterfaceType::IPv4 => "IPv4",
+            SCNetworkInterfaceType::Loopback => "Loopback",
+            SCNetworkInterfaceType::VPN => "VPN",
+            SCNetworkInterfaceType::Other(type_id) => type_id,
         }
     }
+}
 
-    /// Returns the string constants used to identify this network interface type.
-    pub fn to_cfstring(&self) -> CFString {
-        #[cfg(feature = "private")]
-        use system_configuration_sys::network_configuration_private::*;
-        use system_configuration_sys::network_configuration::*;
-        let wrap_const = |const_str| unsafe { CFString::wrap_under_get_rule(const_str) };
-        unsafe {
-            match self {
-                SCNetworkInterfaceType::SixToFour => wrap_const(kSCNetworkInterfaceType6to4),
-                SCNetworkInterfaceType::Bluetooth => wrap_const(kSCNetworkInterfaceTypeBluetooth),
-                SCNetworkInterfaceType::Bridge => {
-                    #[cfg(feature = "private")]
-                    let val = wrap_const(kSCNetworkInterfaceTypeBridge);
-
-                    #[cfg(not(feature = "private"))]
-                    let val = BRIDGE_INTERFACE_TYPE_ID.into();
-
-                    val
-                },
-                SCNetworkInterfaceType::Bond => wrap_const(kSCNetworkInterfaceTypeBond),
-                SCNetworkInterfaceType::Ethernet => wrap_const(kSCNetworkInterfaceTypeEthernet),
-                SCNetworkInterfaceType::FireWire => wrap_const(kSCNetworkInterfaceTypeFireWire),
-                SCNetworkInterfaceType::IEEE80211 => wrap_const(kSCNetworkInterfaceTypeIEEE80211),
-                SCNetworkInterfaceType::IPSec => wrap_const(kSCNetworkInterfaceTypeIPSec),
-                SCNetworkInterfaceType::IrDA => IRDA_INTERFACE_TYPE_ID.into(),
-                SCNetworkInterfaceType::L2TP => wrap_const(kSCNetworkInterfaceTypeL2TP),
-                SCNetworkInterfaceType::Modem => wrap_const(kSCNetworkInterfaceTypeModem),
-                SCNetworkInterfaceType::PPP => wrap_const(kSCNetworkInterfaceTypePPP),
-                SCNetworkInterfaceType::PPTP => wrap_const(kSCNetworkInterfaceTypePPTP),
-                SCNetworkInterfaceType::Serial => wrap_const(kSCNetworkInterfaceTypeSerial),
-                SCNetworkInterfaceType::VLAN => wrap_const(kSCNetworkInterfaceTypeVLAN),
-                SCNetworkInterfaceType::WWAN => wrap_const(kSCNetworkInterfaceTypeWWAN),
-                SCNetworkInterfaceType::IPv4 => wrap_const(kSCNetworkInterfaceTypeIPv4),
-            }
+impl PartialEq for SCNetworkInterfaceType {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SCNetworkInterfaceType {}
+
+impl Hash for SCNetworkInterfaceType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl From<&str> for SCNetworkInterfaceType {
+    fn from(type_id: &str) -> Self {
+        match type_id {
+            "6to4" => SCNetworkInterfaceType::SixToFour,
+            "Bluetooth" => SCNetworkInterfaceType::Bluetooth,
+            "Bridge" => SCNetworkInterfaceType::Bridge,
+            "Bond" => SCNetworkInterfaceType::Bond,
+            "Ethernet" => SCNetworkInterfaceType::Ethernet,
+            "FireWire" => SCNetworkInterfaceType::FireWire,
+            "IEEE80211" => SCNetworkInterfaceType::IEEE80211,
+            "IPSec" => SCNetworkInterfaceType::IPSec,
+            "IrDA" => SCNetworkInterfaceType::IrDA,
+            "L2TP" => SCNetworkInterfaceType::L2TP,
+            "Modem" => SCNetworkInterfaceType::Modem,
+            "PPP" => SCNetworkInterfaceType::PPP,
+            "PPTP" => SCNetworkInterfaceType::PPTP,
+            "Serial" => SCNetworkInterfaceType::Serial,
+            "VLAN" => SCNetworkInterfaceType::VLAN,
+            "WWAN" => SCNetworkInterfaceType::WWAN,
+            "IPv4" => SCNetworkInterfaceType::IPv4,
+            "Loopback" => SCNetworkInterfaceType::Loopback,
+            "VPN" => SCNetworkInterfaceType::VPN,
+            other => SCNetworkInterfaceType::Other(other.to_owned()),
         }
     }
 }
 
+impl FromStr for SCNetworkInterfaceType {
+    type Err = Infallible;
+
+    fn from_str(type_id: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(type_id))
+    }
+}
+
+impl fmt::Display for SCNetworkInterfaceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<SCNetworkInterfaceType> for CFString {
+    fn from(interface_type: SCNetworkInterfaceType) -> Self {
+        interface_type.to_cfstring()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serde_as_str!(SCNetworkInterfaceType);
+
 /// Retrieve all current network interfaces
 ///
 /// See [`SCNetworkInterfaceCopyAll`] for more details.
@@ -426,3 +717,141 @@ impl SCNetworkInterfaceType {
 pub fn get_interfaces() -> CFArray<SCNetworkInterface> {
     unsafe { CFArray::<SCNetworkInterface>::wrap_under_create_rule(SCNetworkInterfaceCopyAll()) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_TYPES: [SCNetworkInterfaceType; 19] = [
+        SCNetworkInterfaceType::SixToFour,
+        SCNetworkInterfaceType::Bluetooth,
+        SCNetworkInterfaceType::Bridge,
+        SCNetworkInterfaceType::Bond,
+        SCNetworkInterfaceType::Ethernet,
+        SCNetworkInterfaceType::FireWire,
+        SCNetworkInterfaceType::IEEE80211,
+        SCNetworkInterfaceType::IPSec,
+        SCNetworkInterfaceType::IrDA,
+        SCNetworkInterfaceType::L2TP,
+        SCNetworkInterfaceType::Modem,
+        SCNetworkInterfaceType::PPP,
+        SCNetworkInterfaceType::PPTP,
+        SCNetworkInterfaceType::Serial,
+        SCNetworkInterfaceType::VLAN,
+        SCNetworkInterfaceType::WWAN,
+        SCNetworkInterfaceType::IPv4,
+        SCNetworkInterfaceType::Loopback,
+        SCNetworkInterfaceType::VPN,
+    ];
+
+    #[test]
+    fn interface_type_round_trip() {
+        for interface_type in ALL_TYPES {
+            let type_id = interface_type.to_string();
+            assert_eq!(type_id.parse(), Ok(interface_type.clone()));
+            assert!(!matches!(
+                SCNetworkInterfaceType::from(type_id.as_str()),
+                SCNetworkInterfaceType::Other(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn unknown_interface_type() {
+        let interface_type = SCNetworkInterfaceType::from("Thunderbolt");
+        assert_eq!(
+            interface_type,
+            SCNetworkInterfaceType::Other("Thunderbolt".to_owned())
+        );
+        assert_eq!(interface_type.as_str(), "Thunderbolt");
+        assert_eq!(interface_type.to_string(), "Thunderbolt");
+
+        let ethernet = SCNetworkInterfaceType::Other("Ethernet".to_owned());
+        assert_eq!(ethernet, SCNetworkInterfaceType::Ethernet);
+        let types = ALL_TYPES.into_iter().collect::<std::collections::HashSet<_>>();
+        assert!(types.contains(&ethernet));
+    }
+
+    #[test]
+    fn media_subtypes_match_media_options() {
+        for interface in SCNetworkInterface::get_interfaces().iter() {
+            let options = match interface.media_options(false) {
+                Some(options) => options,
+                None => continue,
+            };
+            let subtypes = interface.media_subtypes(false).unwrap_or_default();
+            assert_eq!(subtypes, options.subtypes());
+            for subtype in &subtypes {
+                assert_eq!(
+                    interface.media_subtype_options(subtype, false).unwrap(),
+                    options.subtype_options(subtype)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layer_l2tp_over_ipv4() {
+        let ipv4 = SCNetworkInterface::ipv4();
+        assert_eq!(ipv4.interface_type(), Some(SCNetworkInterfaceType::IPv4));
+        let ppp = ipv4
+            .layer(SCNetworkInterfaceType::L2TP)
+            .and_then(|l2tp| l2tp.layer(SCNetworkInterfaceType::PPP))
+            .unwrap();
+        let stack = ppp
+            .interface_stack()
+            .iter()
+            .map(SCNetworkInterface::interface_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stack,
+            [
+                Some(SCNetworkInterfaceType::PPP),
+                Some(SCNetworkInterfaceType::L2TP),
+                Some(SCNetworkInterfaceType::IPv4),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::network_configuration::*;
+        #[cfg(feature = "private")]
+        use crate::sys::network_configuration_private::*;
+
+        let check = |interface_type: SCNetworkInterfaceType, const_str| {
+            let const_str = unsafe { CFString::wrap_under_get_rule(const_str) };
+            assert_eq!(SCNetworkInterfaceType::from_cfstring(&const_str), interface_type);
+            assert_eq!(interface_type.to_cfstring(), const_str);
+        };
+        let constants = unsafe {
+            [
+                (SCNetworkInterfaceType::SixToFour, kSCNetworkInterfaceType6to4),
+                (SCNetworkInterfaceType::Bluetooth, kSCNetworkInterfaceTypeBluetooth),
+                (SCNetworkInterfaceType::Bond, kSCNetworkInterfaceTypeBond),
+                (SCNetworkInterfaceType::Ethernet, kSCNetworkInterfaceTypeEthernet),
+                (SCNetworkInterfaceType::FireWire, kSCNetworkInterfaceTypeFireWire),
+                (SCNetworkInterfaceType::IEEE80211, kSCNetworkInterfaceTypeIEEE80211),
+                (SCNetworkInterfaceType::IPSec, kSCNetworkInterfaceTypeIPSec),
+                (SCNetworkInterfaceType::L2TP, kSCNetworkInterfaceTypeL2TP),
+                (SCNetworkInterfaceType::Modem, kSCNetworkInterfaceTypeModem),
+                (SCNetworkInterfaceType::PPP, kSCNetworkInterfaceTypePPP),
+                (SCNetworkInterfaceType::PPTP, kSCNetworkInterfaceTypePPTP),
+                (SCNetworkInterfaceType::Serial, kSCNetworkInterfaceTypeSerial),
+                (SCNetworkInterfaceType::VLAN, kSCNetworkInterfaceTypeVLAN),
+                (SCNetworkInterfaceType::WWAN, kSCNetworkInterfaceTypeWWAN),
+                (SCNetworkInterfaceType::IPv4, kSCNetworkInterfaceTypeIPv4),
+            ]
+        };
+        for (interface_type, const_str) in constants {
+            check(interface_type, const_str);
+        }
+
+        #[cfg(feature = "private")]
+        unsafe {
+            check(SCNetworkInterfaceType::Bridge, kSCNetworkInterfaceTypeBridge);
+            check(SCNetworkInterfaceType::Loopback, kSCNetworkInterfaceTypeLoopback);
+            check(SCNetworkInterfaceType::VPN, kSCNetworkInterfaceTypeVPN);
+        }
+    }
+}
@@ -1,3 +1,10 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
 use core_foundation::{
     base::{Boolean, TCFType, CFType},
     string::CFString,
@@ -8,6 +15,8 @@ use sys::network_configuration::{
     SCNetworkProtocolGetTypeID, SCNetworkProtocolRef, SCNetworkProtocolSetConfiguration, SCNetworkProtocolSetEnabled
 };
 
+use crate::error::{to_result, SCError};
+
 core_foundation::declare_TCFType!(
     /// Represents a network protocol.
     ///
@@ -32,13 +41,16 @@ impl SCNetworkProtocol {
         unsafe { SCNetworkProtocolGetEnabled(self.0) != 0 }
     }
 
-    /// Get type of the network protocol, if the type is recognized, returns `None` otherwise.
+    /// Get type of the network protocol. Types not known to this crate are returned as
+    /// [`SCNetworkProtocolType::Other`].
     ///
     /// See [`SCNetworkProtocolGetProtocolType`] for details.
     ///
     /// [`SCNetworkProtocolGetProtocolType`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkprotocolgetprotocoltype(_:)?language=objc
     pub fn protocol_type(&self) -> Option<SCNetworkProtocolType> {
-        SCNetworkProtocolType::from_cfstring(&self.protocol_type_string()?)
+        Some(SCNetworkProtocolType::from_cfstring(
+            &self.protocol_type_string()?,
+        ))
     }
 
     /// Returns the raw protocol type identifier.
@@ -75,17 +87,18 @@ impl SCNetworkProtocol {
     }
 
     /// Enables or disables the specified protocol.
-    ///
-    /// Returns: `true` if the enabled status was saved; `false` if an error occurred.
-    pub fn set_enabled(&mut self, enabled: bool) -> bool {
-        (unsafe { SCNetworkProtocolSetEnabled(self.0, enabled as Boolean) }) != 0
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkProtocolSetEnabled(self.0, enabled as Boolean) })
     }
 
     /// Stores the configuration settings for the specified network protocol.
-    ///
-    /// Returns: `true` if the configuration was stored; `false` if an error occurred.
-    pub fn set_configuration(&mut self, config: &CFDictionary<CFString, CFType>) -> bool {
-        (unsafe { SCNetworkProtocolSetConfiguration(self.0, config.as_concrete_TypeRef()) }) != 0
+    pub fn set_configuration(
+        &mut self,
+        config: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCNetworkProtocolSetConfiguration(self.0, config.as_concrete_TypeRef())
+        })
     }
 }
 
@@ -94,7 +107,7 @@ impl SCNetworkProtocol {
 /// See [_Network Protocol Types_] documentation for details.
 ///
 /// [_Network Protocol Types_]: https://developer.apple.com/documentation/systemconfiguration/network-protocol-types?language=objc
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum SCNetworkProtocolType {
     /// DNS protocol.
     DNS,
@@ -106,47 +119,128 @@ pub enum SCNetworkProtocolType {
     Proxies,
     /// SMB protocol.
     SMB,
+    /// A protocol type not known to this crate, holding its raw identifier.
+    Other(String),
 }
 
 impl SCNetworkProtocolType {
-    /// Tries to construct a type by matching it to string constants used to identify a network
-    /// protocol type. If no constants match it, `None` is returned.
-    pub fn from_cfstring(type_id: &CFString) -> Option<Self> {
-        use system_configuration_sys::network_configuration::*;
+    /// Constructs a type from the string constant used to identify a network protocol type.
+    /// Identifiers not known to this crate are kept as [`Other`](Self::Other).
+    pub fn from_cfstring(type_id: &CFString) -> Self {
+        Self::from(type_id.to_string().as_str())
+    }
 
-        let id_is_equal_to = |const_str| -> bool {
-            let const_str = unsafe { CFString::wrap_under_get_rule(const_str) };
-            &const_str == type_id
-        };
-        unsafe {
-            if id_is_equal_to(kSCNetworkProtocolTypeDNS) {
-                Some(SCNetworkProtocolType::DNS)
-            } else if id_is_equal_to(kSCNetworkProtocolTypeIPv4) {
-                Some(SCNetworkProtocolType::IPv4)
-            } else if id_is_equal_to(kSCNetworkProtocolTypeIPv6) {
-                Some(SCNetworkProtocolType::IPv6)
-            } else if id_is_equal_to(kSCNetworkProtocolTypeProxies) {
-                Some(SCNetworkProtocolType::Proxies)
-            } else if id_is_equal_to(kSCNetworkProtocolTypeSMB) {
-                Some(SCNetworkProtocolType::SMB)
-            } else {
-                None
-            }
+    /// Returns the string constant used to identify this network protocol type.
+    pub fn to_cfstring(&self) -> CFString {
+        CFString::new(self.as_str())
+    }
+
+    /// Returns the identifier of this network protocol type, the value of the matching
+    /// `kSCNetworkProtocolType*` constant.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SCNetworkProtocolType::DNS => "DNS",
+            SCNetworkProtocolType::IPv4 => "IPv4",
+            SCNetworkProtocolType::IPv6 => "IPv6",
+            SCNetworkProtocolType::Proxies => "Proxies",
+            SCNetworkProtocolType::SMB => "SMB",
+            SCNetworkProtocolType::Other(type_id) => type_id,
         }
     }
+}
 
-    /// Returns the string constants used to identify this network protocol type.
-    pub fn to_cfstring(&self) -> CFString {
-        use system_configuration_sys::network_configuration::*;
-        let wrap_const = |const_str| unsafe { CFString::wrap_under_get_rule(const_str) };
-        unsafe {
-            match self {
-                SCNetworkProtocolType::DNS => wrap_const(kSCNetworkProtocolTypeDNS),
-                SCNetworkProtocolType::IPv4 => wrap_const(kSCNetworkProtocolTypeIPv4),
-                SCNetworkProtocolType::IPv6 => wrap_const(kSCNetworkProtocolTypeIPv6),
-                SCNetworkProtocolType::Proxies => wrap_const(kSCNetworkProtocolTypeProxies),
-                SCNetworkProtocolType::SMB => wrap_const(kSCNetworkProtocolTypeSMB),
-            }
+impl PartialEq for SCNetworkProtocolType {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SCNetworkProtocolType {}
+
+impl Hash for SCNetworkProtocolType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl From<&str> for SCNetworkProtocolType {
+    fn from(type_id: &str) -> Self {
+        match type_id {
+            "DNS" => SCNetworkProtocolType::DNS,
+            "IPv4" => SCNetworkProtocolType::IPv4,
+            "IPv6" => SCNetworkProtocolType::IPv6,
+            "Proxies" => SCNetworkProtocolType::Proxies,
+            "SMB" => SCNetworkProtocolType::SMB,
+            other => SCNetworkProtocolType::Other(other.to_owned()),
+        }
+    }
+}
+
+impl FromStr for SCNetworkProtocolType {
+    type Err = Infallible;
+
+    fn from_str(type_id: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(type_id))
+    }
+}
+
+impl fmt::Display for SCNetworkProtocolType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<SCNetworkProtocolType> for CFString {
+    fn from(protocol_type: SCNetworkProtocolType) -> Self {
+        protocol_type.to_cfstring()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serde_as_str!(SCNetworkProtocolType);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_type_round_trip() {
+        for type_id in ["DNS", "IPv4", "IPv6", "Proxies", "SMB"] {
+            let protocol_type: SCNetworkProtocolType = type_id.parse().unwrap();
+            assert!(!matches!(protocol_type, SCNetworkProtocolType::Other(_)));
+            assert_eq!(protocol_type.to_string(), type_id);
+        }
+        assert_eq!(
+            SCNetworkProtocolType::from("AppleTalk"),
+            SCNetworkProtocolType::Other("AppleTalk".to_owned())
+        );
+        assert_eq!(SCNetworkProtocolType::from("AppleTalk").as_str(), "AppleTalk");
+
+        let dns = SCNetworkProtocolType::Other("DNS".to_owned());
+        assert_eq!(dns, SCNetworkProtocolType::DNS);
+        let types = [SCNetworkProtocolType::DNS]
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert!(types.contains(&dns));
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::network_configuration::*;
+
+        let constants = unsafe {
+            [
+                (SCNetworkProtocolType::DNS, kSCNetworkProtocolTypeDNS),
+                (SCNetworkProtocolType::IPv4, kSCNetworkProtocolTypeIPv4),
+                (SCNetworkProtocolType::IPv6, kSCNetworkProtocolTypeIPv6),
+                (SCNetworkProtocolType::Proxies, kSCNetworkProtocolTypeProxies),
+                (SCNetworkProtocolType::SMB, kSCNetworkProtocolTypeSMB),
+            ]
+        };
+        for (protocol_type, const_str) in constants {
+            let const_str = unsafe { CFString::wrap_under_get_rule(const_str) };
+            assert_eq!(SCNetworkProtocolType::from_cfstring(&const_str), protocol_type);
+            assert_eq!(protocol_type.to_cfstring(), const_str);
         }
     }
 }
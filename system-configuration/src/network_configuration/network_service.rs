@@ -6,11 +6,13 @@ use core_foundation::{
 use sys::network_configuration::{
     SCNetworkServiceAddProtocolType, SCNetworkServiceCopy, SCNetworkServiceCopyAll, SCNetworkServiceCopyProtocol,
     SCNetworkServiceCopyProtocols, SCNetworkServiceCreate, SCNetworkServiceEstablishDefaultConfiguration,
-    SCNetworkServiceGetEnabled, SCNetworkServiceGetInterface, SCNetworkServiceGetServiceID, SCNetworkServiceGetTypeID,
-    SCNetworkServiceRef, SCNetworkServiceRemove, SCNetworkServiceSetEnabled
+    SCNetworkServiceGetEnabled, SCNetworkServiceGetInterface, SCNetworkServiceGetName, SCNetworkServiceGetServiceID,
+    SCNetworkServiceGetTypeID, SCNetworkServiceRef, SCNetworkServiceRemove, SCNetworkServiceRemoveProtocolType,
+    SCNetworkServiceSetEnabled, SCNetworkServiceSetName
 };
 
-use super::{SCNetworkInterface, SCNetworkProtocol};
+use super::{SCNetworkInterface, SCNetworkProtocol, SCNetworkProtocolType};
+use crate::error::{to_result, SCError};
 use crate::preferences::SCPreferences;
 
 use crate::helpers::create_empty_array;
@@ -103,6 +105,22 @@ impl SCNetworkService {
         }
     }
 
+    /// Returns the user-specified name of the service. Or `None` if it has no name.
+    ///
+    /// See [`SCNetworkServiceGetName`] for details.
+    ///
+    /// [`SCNetworkServiceGetName`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkservicegetname(_:)?language=objc
+    pub fn name(&self) -> Option<CFString> {
+        unsafe {
+            let ptr = SCNetworkServiceGetName(self.0);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CFString::wrap_under_get_rule(ptr))
+            }
+        }
+    }
+
     /// Returns the network interface backing this network service, if it has one.
     pub fn network_interface(&self) -> Option<SCNetworkInterface> {
         unsafe {
@@ -140,9 +158,9 @@ impl SCNetworkService {
         &self,
         protocol_type: S,
     ) -> Option<SCNetworkProtocol> {
-        let protocol_type_ref = protocol_type.into().as_concrete_TypeRef();
+        let protocol_type = protocol_type.into();
         unsafe {
-            let ptr = SCNetworkServiceCopyProtocol(self.0, protocol_type_ref);
+            let ptr = SCNetworkServiceCopyProtocol(self.0, protocol_type.as_concrete_TypeRef());
             if ptr.is_null() {
                 None
             } else {
@@ -151,37 +169,71 @@ impl SCNetworkService {
         }
     }
 
+    /// Returns the network protocol of the given type for the specified service. Or `None` if this
+    /// protocol has not been added or if an error occurred.
+    pub fn protocol(&self, protocol_type: SCNetworkProtocolType) -> Option<SCNetworkProtocol> {
+        self.find_network_protocol(protocol_type)
+    }
+
     /// Establishes the default configuration for the specified network service. The default
     /// configuration includes the addition of network protocols for the service (with default
     /// configuration options).
-    ///
-    /// Returns: `true` if the configuration was updated; `false` if an error occurred.
-    pub fn establish_default_configuration(&mut self) -> bool {
-        (unsafe { SCNetworkServiceEstablishDefaultConfiguration(self.0) }) != 0
+    pub fn establish_default_configuration(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkServiceEstablishDefaultConfiguration(self.0) })
     }
 
     /// Adds the network protocol of the specified type to the specified service. The protocol
     /// configuration is set to default values that are appropriate for the interface associated
     /// with the service.
     ///
-    /// Returns: `true` if the protocol was added to the service; `false` if the protocol was
-    ///          already present or an error occurred.
-    pub fn add_network_protocol<S: Into<CFString>>(&mut self, protocol_type: S) -> bool {
-        let protocol_type_ref = protocol_type.into().as_concrete_TypeRef();
-        (unsafe { SCNetworkServiceAddProtocolType(self.0, protocol_type_ref) }) != 0
+    /// Fails with [`SCError::KeyExists`] if the protocol is already present.
+    pub fn add_network_protocol<S: Into<CFString>>(
+        &mut self,
+        protocol_type: S,
+    ) -> Result<(), SCError> {
+        let protocol_type = protocol_type.into();
+        to_result(unsafe {
+            SCNetworkServiceAddProtocolType(self.0, protocol_type.as_concrete_TypeRef())
+        })
     }
 
-    /// Removes the specified network service from the configuration.
+    /// Removes the network protocol of the specified type from the specified service.
     ///
-    /// Returns: `true` if the service was removed; `false` if an error occurred.
-    pub fn remove(self) -> bool {
-        (unsafe { SCNetworkServiceRemove(self.0) }) != 0
+    /// Fails with [`SCError::NoKey`] if the protocol is not present.
+    pub fn remove_network_protocol<S: Into<CFString>>(
+        &mut self,
+        protocol_type: S,
+    ) -> Result<(), SCError> {
+        let protocol_type = protocol_type.into();
+        to_result(unsafe {
+            SCNetworkServiceRemoveProtocolType(self.0, protocol_type.as_concrete_TypeRef())
+        })
+    }
+
+    /// Removes the specified network service from the configuration.
+    pub fn remove(self) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkServiceRemove(self.0) })
     }
 
     /// Enables or disables the specified service.
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkServiceSetEnabled(self.0, enabled as Boolean) })
+    }
+
+    /// Sets the user-specified name of the service. Passing `None` removes the name, after which
+    /// the framework derives one from the interface.
+    ///
+    /// Fails with [`SCError::KeyExists`] if another service in the same set already has the name.
+    ///
+    /// See [`SCNetworkServiceSetName`] for details.
     ///
-    /// Returns: `true` if the enabled status was saved; `false` if an error occurred.
-    pub fn set_enabled(&mut self, enabled: bool) -> bool {
-        (unsafe { SCNetworkServiceSetEnabled(self.0, enabled as Boolean) }) != 0
+    /// [`SCNetworkServiceSetName`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkservicesetname(_:_:)?language=objc
+    pub fn set_name(&mut self, name: Option<&CFString>) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCNetworkServiceSetName(
+                self.0,
+                name.map_or(std::ptr::null(), |name| name.as_concrete_TypeRef()),
+            )
+        })
     }
 }
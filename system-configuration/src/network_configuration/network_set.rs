@@ -5,12 +5,13 @@ use core_foundation::{
 };
 use sys::network_configuration::{
     SCNetworkSetAddService, SCNetworkSetContainsInterface, SCNetworkSetCopy, SCNetworkSetCopyAll,
-    SCNetworkSetCopyCurrent, SCNetworkSetCopyServices, SCNetworkSetGetName, SCNetworkSetGetServiceOrder,
-    SCNetworkSetGetSetID, SCNetworkSetGetTypeID, SCNetworkSetRef, SCNetworkSetRemove, SCNetworkSetRemoveService,
-    SCNetworkSetSetCurrent, SCNetworkSetSetServiceOrder
+    SCNetworkSetCopyCurrent, SCNetworkSetCopyServices, SCNetworkSetCreate, SCNetworkSetGetName,
+    SCNetworkSetGetServiceOrder, SCNetworkSetGetSetID, SCNetworkSetGetTypeID, SCNetworkSetRef, SCNetworkSetRemove,
+    SCNetworkSetRemoveService, SCNetworkSetSetCurrent, SCNetworkSetSetName, SCNetworkSetSetServiceOrder
 };
 
 use super::{SCNetworkInterface, SCNetworkService};
+use crate::error::{to_result, SCError};
 use crate::preferences::SCPreferences;
 
 use crate::helpers::create_empty_array;
@@ -70,6 +71,24 @@ impl SCNetworkSet {
         }
     }
 
+    /// Creates a new, empty set with the given name in the configuration.
+    ///
+    /// See [`SCNetworkSetCreate`] for details.
+    ///
+    /// [`SCNetworkSetCreate`]: https://developer.apple.com/documentation/systemconfiguration/scnetworksetcreate(_:)?language=objc
+    pub fn create_named(prefs: &SCPreferences, name: &CFString) -> Result<Self, SCError> {
+        let set_ref = unsafe { SCNetworkSetCreate(prefs.as_concrete_TypeRef()) };
+        if set_ref.is_null() {
+            return Err(SCError::last());
+        }
+        let mut set = unsafe { Self::wrap_under_create_rule(set_ref) };
+        if let Err(error) = set.set_name(Some(name)) {
+            let _ = set.remove();
+            return Err(error);
+        }
+        Ok(set)
+    }
+
     /// Constructs a new set of network services from the preferences.
     pub fn new(prefs: &SCPreferences) -> Self {
         let ptr = unsafe { SCNetworkSetCopyCurrent(prefs.to_void()) };
@@ -132,41 +151,135 @@ impl SCNetworkSet {
 
     /// Adds the specified network service to the specified set.
     ///
-    /// Returns: `true` if the service was added to the set; `false` if the service was already
-    ///          present or an error occurred.
-    pub fn add_service(&mut self, service: &SCNetworkService) -> bool {
+    /// Fails with [`SCError::KeyExists`] if the service is already present.
+    pub fn add_service(&mut self, service: &SCNetworkService) -> Result<(), SCError> {
         let service_ref = service.as_concrete_TypeRef();
-        (unsafe { SCNetworkSetAddService(self.0, service_ref) }) != 0
+        to_result(unsafe { SCNetworkSetAddService(self.0, service_ref) })
     }
 
     /// Removes the specified set from the configuration.
-    ///
-    /// Returns: `true` if the set was removed; `false` if an error occurred.
-    pub fn remove(self) -> bool {
-        (unsafe { SCNetworkSetRemove(self.0) }) != 0
+    pub fn remove(self) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkSetRemove(self.0) })
     }
 
     /// Removes the specified network service from the specified set.
     ///
-    /// Returns: `true` if the service was removed from the set; `false` if the service was not
-    ///          already present or an error occurred.
-    pub fn remove_service(&mut self, service: &SCNetworkService) -> bool {
+    /// Fails with [`SCError::NoKey`] if the service is not present.
+    pub fn remove_service(&mut self, service: &SCNetworkService) -> Result<(), SCError> {
         let service_ref = service.as_concrete_TypeRef();
-        (unsafe { SCNetworkSetRemoveService(self.0, service_ref) }) != 0
+        to_result(unsafe { SCNetworkSetRemoveService(self.0, service_ref) })
     }
 
     /// Specifies the set that should be the current set.
-    ///
-    /// Returns: `true` if the current set was updated; `false` if an error occurred.
-    pub fn set_current(&mut self) -> bool {
-        (unsafe { SCNetworkSetSetCurrent(self.0) }) != 0
+    pub fn set_current(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkSetSetCurrent(self.0) })
     }
 
     /// Stores the user-specified ordering of network services for the specified set.
-    ///
-    /// Returns: `true` if the new service order was saved; `false` if an error occurred.
-    pub fn set_service_order(&mut self, new_order: CFArray<CFString>) -> bool {
+    pub fn set_service_order(&mut self, new_order: CFArray<CFString>) -> Result<(), SCError> {
         let cf_order_ref = new_order.as_concrete_TypeRef();
-        (unsafe { SCNetworkSetSetServiceOrder(self.0, cf_order_ref) }) != 0
+        to_result(unsafe { SCNetworkSetSetServiceOrder(self.0, cf_order_ref) })
+    }
+
+    /// Sets the user-specified name of the set. Passing `None` removes the name.
+    ///
+    /// Fails with [`SCError::KeyExists`] if another set already has the name.
+    ///
+    /// See [`SCNetworkSetSetName`] for details.
+    ///
+    /// [`SCNetworkSetSetName`]: https://developer.apple.com/documentation/systemconfiguration/scnetworksetsetname(_:_:)?language=objc
+    pub fn set_name(&mut self, name: Option<&CFString>) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCNetworkSetSetName(
+                self.0,
+                name.map_or(std::ptr::null(), |name| name.as_concrete_TypeRef()),
+            )
+        })
+    }
+
+    /// Creates a new set named `new_name` holding a copy of every service of this set, in the
+    /// same order. Each copy is a new service on the same interface, with the name, enabled state,
+    /// protocol configurations and interface configurations of the original, including the
+    /// `EAPOL` and `IPSec` extended configurations. This is what duplicating a location in System
+    /// Settings does. Services without an interface cannot be created again and are left out.
+    ///
+    /// `prefs` has to be the preferences session this set was read from. A set does not expose
+    /// its session, and the new set and services have to be created in it. If an error occurs,
+    /// the parts that were already copied are left in the session, discard them by not committing
+    /// the changes.
+    pub fn duplicate(&self, prefs: &SCPreferences, new_name: &CFString) -> Result<Self, SCError> {
+        let mut copy = Self::create_named(prefs, new_name)?;
+        let mut copied_ids = Vec::new();
+        for service in self.services().iter() {
+            let interface = match service.network_interface() {
+                Some(interface) => interface,
+                None => continue,
+            };
+            let mut service_copy =
+                SCNetworkService::create(prefs, &interface).ok_or_else(SCError::last)?;
+            let interface_copy = service_copy
+                .network_interface()
+                .ok_or_else(SCError::last)?;
+            for (layer, mut layer_copy) in interface
+                .interface_stack()
+                .into_iter()
+                .zip(interface_copy.interface_stack())
+            {
+                copy_interface_configuration(&layer, &mut layer_copy)?;
+            }
+            if let Some(name) = service.name() {
+                service_copy.set_name(Some(&name))?;
+            }
+            for protocol in service.network_protocols().iter() {
+                let protocol_type = protocol
+                    .protocol_type_string()
+                    .ok_or(SCError::InvalidArgument)?;
+                service_copy.add_network_protocol(protocol_type.clone())?;
+                let mut protocol_copy = service_copy
+                    .find_network_protocol(protocol_type)
+                    .ok_or_else(SCError::last)?;
+                if let Some(configuration) = protocol.configuration() {
+                    protocol_copy.set_configuration(&configuration)?;
+                }
+                protocol_copy.set_enabled(protocol.enabled())?;
+            }
+            service_copy.set_enabled(service.enabled())?;
+            copy.add_service(&service_copy)?;
+            if let (Some(id), Some(copy_id)) = (service.id(), service_copy.id()) {
+                copied_ids.push((id, copy_id));
+            }
+        }
+
+        let order = self
+            .service_order()
+            .iter()
+            .filter_map(|id| {
+                copied_ids
+                    .iter()
+                    .find(|(original, _)| *original == *id)
+                    .map(|(_, copy_id)| copy_id.clone())
+            })
+            .collect::<Vec<_>>();
+        copy.set_service_order(CFArray::from_CFTypes(&order))?;
+        Ok(copy)
+    }
+}
+
+/// Extended configurations that are stored with the interface of a service, `kSCEntNetEAPOL` and
+/// `kSCEntNetIPSec`.
+const EXTENDED_CONFIGURATION_TYPES: [&str; 2] = ["EAPOL", "IPSec"];
+
+fn copy_interface_configuration(
+    interface: &SCNetworkInterface,
+    copy: &mut SCNetworkInterface,
+) -> Result<(), SCError> {
+    if let Some(configuration) = interface.configuration() {
+        copy.set_configuration(&configuration)?;
+    }
+    for extended_type in EXTENDED_CONFIGURATION_TYPES {
+        if let Some(configuration) = interface.extended_configuration(extended_type) {
+            copy.set_extended_configuration(extended_type, &configuration)?;
+        }
     }
+    Ok(())
 }
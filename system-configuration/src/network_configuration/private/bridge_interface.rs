@@ -18,6 +18,7 @@ use sys::{
 };
 
 use super::{SCNetworkInterface, SCNetworkInterfaceSubClass, SCNetworkInterfaceType};
+use crate::error::{to_result, SCError};
 use crate::preferences::SCPreferences;
 
 use crate::helpers::create_empty_array;
@@ -171,30 +172,30 @@ impl SCBridgeInterface {
     }
 
     /// Removes the SCBridgeInterface from the configuration.
-    ///
-    /// Returns: `true` if the interface was removed; `false` if an error was encountered.
-    pub fn remove(self) -> bool {
-        (unsafe { SCBridgeInterfaceRemove(self.0) }) != 0
+    pub fn remove(self) -> Result<(), SCError> {
+        to_result(unsafe { SCBridgeInterfaceRemove(self.0) })
     }
 
     /// Allow adding member interfaces to the bridge that have configured services.
-    ///
-    /// Returns: `true` if the change was successful; `false` otherwise.
-    pub fn set_configured_members_allowed(&mut self, enable: bool) -> bool {
-        (unsafe { SCBridgeInterfaceSetAllowConfiguredMembers(self.0, enable as Boolean) }) != 0
+    pub fn set_configured_members_allowed(&mut self, enable: bool) -> Result<(), SCError> {
+        to_result(unsafe { SCBridgeInterfaceSetAllowConfiguredMembers(self.0, enable as Boolean) })
     }
 
     /// Sets the member interfaces for the specified bridge interface.
-    ///
-    /// Returns: `true` if the configuration was stored; `false` if an error was encountered.
-    pub fn set_member_interfaces(&mut self, members: &CFArray<SCNetworkInterface>) -> bool {
-        (unsafe { SCBridgeInterfaceSetMemberInterfaces(self.0, members.as_concrete_TypeRef()) }) != 0
+    pub fn set_member_interfaces(
+        &mut self,
+        members: &CFArray<SCNetworkInterface>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCBridgeInterfaceSetMemberInterfaces(self.0, members.as_concrete_TypeRef())
+        })
     }
 
     /// Sets the configuration settings for the specified bridge interface.
-    ///
-    /// Returns: `true` if the configuration was stored; `false` if an error occurred.
-    pub fn set_options(&mut self, new_options: &CFDictionary<CFString, CFType>) -> bool {
-        (unsafe { SCBridgeInterfaceSetOptions(self.0, new_options.as_concrete_TypeRef()) }) != 0
+    pub fn set_options(
+        &mut self,
+        new_options: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe { SCBridgeInterfaceSetOptions(self.0, new_options.as_concrete_TypeRef()) })
     }
 }
@@ -0,0 +1,358 @@
+#![allow(non_snake_case)]
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    os,
+};
+use core_foundation::{
+    array::CFArray,
+    base::{CFRetain, CFType, CFTypeID, CFTypeRef, TCFType, TCFTypeRef, ToVoid},
+    dictionary::CFDictionary,
+    number::CFNumber,
+    string::CFString,
+};
+use sys::network_configuration::{
+    SCNetworkInterfaceGetTypeID, SCVLANInterfaceCopyAll, SCVLANInterfaceCopyAvailablePhysicalInterfaces,
+    SCVLANInterfaceCreate, SCVLANInterfaceGetOptions, SCVLANInterfaceGetPhysicalInterface, SCVLANInterfaceGetTag,
+    SCVLANInterfaceRef, SCVLANInterfaceRemove, SCVLANInterfaceSetLocalizedDisplayName,
+    SCVLANInterfaceSetOptions, SCVLANInterfaceSetPhysicalInterfaceAndTag
+};
+use super::{SCNetworkInterface, SCNetworkInterfaceSubClass, SCNetworkInterfaceType};
+use crate::error::{to_result, SCError};
+use crate::preferences::SCPreferences;
+
+use crate::helpers::create_empty_array;
+
+/// A valid IEEE 802.1Q VLAN identifier, in the range `1..=4094`.
+///
+/// The identifiers `0` and `4095` are reserved by the standard and cannot be used to tag traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VLANTag(u16);
+
+impl VLANTag {
+    /// The lowest usable VLAN identifier.
+    pub const MIN: VLANTag = VLANTag(1);
+    /// The highest usable VLAN identifier.
+    pub const MAX: VLANTag = VLANTag(4094);
+
+    /// Returns the tag for the given identifier. Or `None` if it is outside `1..=4094`.
+    pub fn new(tag: u16) -> Option<Self> {
+        if (Self::MIN.0..=Self::MAX.0).contains(&tag) {
+            Some(VLANTag(tag))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the numeric identifier of this tag.
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for VLANTag {
+    type Error = InvalidVLANTagError;
+
+    fn try_from(tag: u16) -> Result<Self, Self::Error> {
+        VLANTag::new(tag).ok_or(InvalidVLANTagError(i64::from(tag)))
+    }
+}
+
+impl TryFrom<i64> for VLANTag {
+    type Error = InvalidVLANTagError;
+
+    fn try_from(tag: i64) -> Result<Self, Self::Error> {
+        u16::try_from(tag)
+            .ok()
+            .and_then(VLANTag::new)
+            .ok_or(InvalidVLANTagError(tag))
+    }
+}
+
+impl From<VLANTag> for u16 {
+    fn from(tag: VLANTag) -> Self {
+        tag.0
+    }
+}
+
+impl Display for VLANTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Failure to use a number as a [`VLANTag`], because it is outside `1..=4094`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidVLANTagError(pub i64);
+
+impl Display for InvalidVLANTagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid VLAN tag {}, must be between {} and {}",
+            self.0,
+            VLANTag::MIN,
+            VLANTag::MAX
+        )
+    }
+}
+
+impl Error for InvalidVLANTagError {}
+
+core_foundation::declare_TCFType! {
+    /// Represents a VLAN interface, which is a subclass of [`SCNetworkInterface`](SCNetworkInterface).
+    ///
+    /// See [`SCVLANInterfaceRef`] and its [methods] for details.
+    ///
+    /// [`SCVLANInterfaceRef`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterface?language=objc
+    /// [methods]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconfiguration?language=objc
+    SCVLANInterface, SCVLANInterfaceRef
+}
+core_foundation::impl_CFTypeDescription!(SCVLANInterface);
+
+// default implementation copied verbatim from `core_foundation::impl_TCFType!(...)` expansion.
+//
+// only difference is the lack of `ConcreteCFType` implementation, to prevent `CFType::downcast`
+// from being implemented, as that would be unsound behavior.
+//
+// also implements `SCNetworkInterfaceSubClass` to allow up/downcasting to/from `SCNetworkInterface`
+const _: () = {
+    impl TCFType for SCVLANInterface {
+        type Ref = SCVLANInterfaceRef;
+
+        #[inline]
+        fn as_concrete_TypeRef(&self) -> SCVLANInterfaceRef {
+            self.0
+        }
+
+        #[inline]
+        unsafe fn wrap_under_create_rule(reference: SCVLANInterfaceRef) -> Self {
+            assert!(!reference.is_null(), "Attempted to create a NULL object.");
+            SCVLANInterface(reference)
+        }
+
+        #[inline]
+        fn type_id() -> CFTypeID {
+            unsafe {
+                SCNetworkInterfaceGetTypeID()
+            }
+        }
+
+        #[inline]
+        fn as_CFTypeRef(&self) -> CFTypeRef {
+            self.as_concrete_TypeRef() as CFTypeRef
+        }
+
+        #[inline]
+        unsafe fn wrap_under_get_rule(reference: SCVLANInterfaceRef) -> Self {
+            assert!(!reference.is_null(), "Attempted to create a NULL object.");
+            let reference = CFRetain(reference) as SCVLANInterfaceRef;
+            TCFType::wrap_under_create_rule(reference)
+        }
+    }
+    impl Clone for SCVLANInterface {
+        #[inline]
+        fn clone(&self) -> SCVLANInterface {
+            unsafe {
+                SCVLANInterface::wrap_under_get_rule(self.0)
+            }
+        }
+    }
+    impl PartialEq for SCVLANInterface {
+        #[inline]
+        fn eq(&self, other: &SCVLANInterface) -> bool {
+            self.as_CFType().eq(&other.as_CFType())
+        }
+    }
+    impl Eq for SCVLANInterface {}
+    unsafe impl ToVoid<SCVLANInterface> for &SCVLANInterface {
+        fn to_void(&self) -> *const os::raw::c_void {
+            use TCFTypeRef;
+            self.as_concrete_TypeRef().as_void_ptr()
+        }
+    }
+    unsafe impl ToVoid<SCVLANInterface> for SCVLANInterface {
+        fn to_void(&self) -> *const os::raw::c_void {
+            use TCFTypeRef;
+            self.as_concrete_TypeRef().as_void_ptr()
+        }
+    }
+    unsafe impl ToVoid<SCVLANInterface> for SCVLANInterfaceRef {
+        fn to_void(&self) -> *const os::raw::c_void {
+            use TCFTypeRef;
+            self.as_void_ptr()
+        }
+    }
+    unsafe impl SCNetworkInterfaceSubClass for SCVLANInterface {
+        const INTERFACE_TYPE: SCNetworkInterfaceType = SCNetworkInterfaceType::VLAN;
+    }
+};
+
+impl SCVLANInterface {
+    /// Retrieve all network capable devices on the system that can be used as the physical
+    /// interface of a VLAN interface.
+    ///
+    /// See [`SCVLANInterfaceCopyAvailablePhysicalInterfaces`] for more details.
+    ///
+    /// [`SCVLANInterfaceCopyAvailablePhysicalInterfaces`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacecopyavailablephysicalinterfaces()?language=objc
+    pub fn get_available_physical_interfaces() -> CFArray<SCNetworkInterface> {
+        unsafe {
+            let array_ptr = SCVLANInterfaceCopyAvailablePhysicalInterfaces();
+            if array_ptr.is_null() {
+                return create_empty_array();
+            }
+            CFArray::<SCNetworkInterface>::wrap_under_create_rule(array_ptr)
+        }
+    }
+
+    /// Retrieve all VLAN interfaces on the system.
+    ///
+    /// See [`SCVLANInterfaceCopyAll`] for more details.
+    ///
+    /// [`SCVLANInterfaceCopyAll`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacecopyall(_:)?language=objc
+    pub fn get_interfaces(prefs: &SCPreferences) -> CFArray<Self> {
+        unsafe {
+            let array_ptr = SCVLANInterfaceCopyAll(prefs.as_concrete_TypeRef());
+            if array_ptr.is_null() {
+                return create_empty_array();
+            }
+            CFArray::<Self>::wrap_under_create_rule(array_ptr)
+        }
+    }
+
+    /// Creates a new VLAN interface on top of the given physical interface. Or `None` if an error
+    /// occurred.
+    ///
+    /// See [`SCVLANInterfaceCreate`] for more details.
+    ///
+    /// [`SCVLANInterfaceCreate`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacecreate(_:_:_:)?language=objc
+    pub fn create(
+        prefs: &SCPreferences,
+        physical: &SCNetworkInterface,
+        tag: VLANTag,
+    ) -> Option<Self> {
+        let tag = CFNumber::from(i32::from(tag.get()));
+        unsafe {
+            let vlan_ref = SCVLANInterfaceCreate(
+                prefs.as_concrete_TypeRef(),
+                physical.as_concrete_TypeRef(),
+                tag.as_concrete_TypeRef(),
+            );
+            if !vlan_ref.is_null() {
+                Some(Self::wrap_under_create_rule(vlan_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns the physical interface the VLAN interface is on.
+    ///
+    /// See [`SCVLANInterfaceGetPhysicalInterface`] for more details.
+    ///
+    /// [`SCVLANInterfaceGetPhysicalInterface`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacegetphysicalinterface(_:)?language=objc
+    pub fn physical_interface(&self) -> Option<SCNetworkInterface> {
+        unsafe {
+            let interface_ref = SCVLANInterfaceGetPhysicalInterface(self.0);
+            if !interface_ref.is_null() {
+                Some(SCNetworkInterface::wrap_under_get_rule(interface_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns the VLAN tag of the VLAN interface. Or `None` if it has none, or it is not a valid
+    /// [`VLANTag`].
+    ///
+    /// See [`SCVLANInterfaceGetTag`] for more details.
+    ///
+    /// [`SCVLANInterfaceGetTag`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacegettag(_:)?language=objc
+    pub fn tag(&self) -> Option<VLANTag> {
+        unsafe {
+            let number_ref = SCVLANInterfaceGetTag(self.0);
+            if number_ref.is_null() {
+                return None;
+            }
+            let tag = CFNumber::wrap_under_get_rule(number_ref).to_i64()?;
+            VLANTag::try_from(tag).ok()
+        }
+    }
+
+    /// Returns the configuration settings associated with the specified VLAN interface.
+    /// Or `None` if no changes to the default configuration have been saved.
+    ///
+    /// See [`SCVLANInterfaceGetOptions`] for more details.
+    ///
+    /// [`SCVLANInterfaceGetOptions`]: https://developer.apple.com/documentation/systemconfiguration/scvlaninterfacegetoptions(_:)?language=objc
+    pub fn options(&self) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe {
+            let dictionary_ref = SCVLANInterfaceGetOptions(self.as_concrete_TypeRef());
+            if !dictionary_ref.is_null() {
+                Some(CFDictionary::wrap_under_get_rule(dictionary_ref))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Removes the VLAN interface from the configuration.
+    pub fn remove(self) -> Result<(), SCError> {
+        to_result(unsafe { SCVLANInterfaceRemove(self.0) })
+    }
+
+    /// Moves the VLAN interface to the given physical interface and VLAN tag.
+    pub fn set_physical_interface_and_tag(
+        &mut self,
+        physical: &SCNetworkInterface,
+        tag: VLANTag,
+    ) -> Result<(), SCError> {
+        let tag = CFNumber::from(i32::from(tag.get()));
+        to_result(unsafe {
+            SCVLANInterfaceSetPhysicalInterfaceAndTag(
+                self.0,
+                physical.as_concrete_TypeRef(),
+                tag.as_concrete_TypeRef(),
+            )
+        })
+    }
+
+    /// Sets the localized display name of the VLAN interface.
+    pub fn set_localized_display_name(&mut self, name: &CFString) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCVLANInterfaceSetLocalizedDisplayName(self.0, name.as_concrete_TypeRef())
+        })
+    }
+
+    /// Sets the configuration settings for the specified VLAN interface.
+    pub fn set_options(
+        &mut self,
+        new_options: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        to_result(unsafe { SCVLANInterfaceSetOptions(self.0, new_options.as_concrete_TypeRef()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_range() {
+        assert_eq!(VLANTag::new(0), None);
+        assert_eq!(VLANTag::new(1), Some(VLANTag::MIN));
+        assert_eq!(VLANTag::new(4094), Some(VLANTag::MAX));
+        assert_eq!(VLANTag::new(4095), None);
+        assert_eq!(VLANTag::try_from(100u16).map(u16::from), Ok(100));
+        assert_eq!(VLANTag::try_from(-1i64), Err(InvalidVLANTagError(-1)));
+        assert_eq!(
+            VLANTag::try_from(65_537i64),
+            Err(InvalidVLANTagError(65_537))
+        );
+        assert_eq!(
+            InvalidVLANTagError(0).to_string(),
+            "Invalid VLAN tag 0, must be between 1 and 4094"
+        );
+    }
+}
@@ -0,0 +1,830 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bindings for [`SCNetworkConnection`].
+//!
+//! A network connection controls a service that has to be dialed before it can be used, such as
+//! a PPP or VPN service. The connection can be started and stopped, and reports its status as it
+//! changes. The extended status and the statistics of a connection are untyped dictionaries,
+//! [`ExtendedStatus`] and [`ConnectionStatistics`] parse them.
+//!
+//! [`SCNetworkConnection`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconnection?language=objc
+
+#![allow(non_upper_case_globals)]
+
+use crate::{
+    error::{to_result, SCError},
+    plist::{Dictionary, PlistValue},
+    settings::{
+        ConfigError, DictionaryReader, DictionaryWriter, DnsConfig, Ipv4Config, Ipv6Config,
+        SettingsModel,
+    },
+};
+use core_foundation::{
+    base::{CFType, TCFType, ToVoid},
+    dictionary::{CFDictionary, CFDictionaryRef},
+    runloop::CFRunLoop,
+    string::{CFString, CFStringRef},
+};
+use std::{collections::BTreeMap, ffi::c_void, ptr, sync::Arc};
+use sys::{
+    dispatch_queue_t,
+    network_configuration::{
+        kSCNetworkConnectionConnected, kSCNetworkConnectionConnecting,
+        kSCNetworkConnectionDisconnected, kSCNetworkConnectionDisconnecting,
+        kSCNetworkConnectionInvalid, kSCNetworkConnectionPPPAuthenticating,
+        kSCNetworkConnectionPPPConnected, kSCNetworkConnectionPPPConnectingLink,
+        kSCNetworkConnectionPPPDialOnTraffic, kSCNetworkConnectionPPPDisconnected,
+        kSCNetworkConnectionPPPDisconnectingLink, kSCNetworkConnectionPPPHoldingLinkOff,
+        kSCNetworkConnectionPPPInitializing, kSCNetworkConnectionPPPNegotiatingLink,
+        kSCNetworkConnectionPPPNegotiatingNetwork, kSCNetworkConnectionPPPSuspended,
+        kSCNetworkConnectionPPPTerminating, kSCNetworkConnectionPPPWaitingForCallBack,
+        kSCNetworkConnectionPPPWaitingForRedial, SCNetworkConnectionContext,
+        SCNetworkConnectionCopyExtendedStatus, SCNetworkConnectionCopyServiceID,
+        SCNetworkConnectionCopyStatistics, SCNetworkConnectionCopyUserOptions,
+        SCNetworkConnectionCopyUserPreferences, SCNetworkConnectionCreateWithServiceID,
+        SCNetworkConnectionGetStatus, SCNetworkConnectionGetTypeID, SCNetworkConnectionRef,
+        SCNetworkConnectionScheduleWithRunLoop, SCNetworkConnectionSetDispatchQueue,
+        SCNetworkConnectionStart, SCNetworkConnectionStop,
+        SCNetworkConnectionUnscheduleFromRunLoop,
+    },
+};
+
+/// `kSCEntNetPPP`.
+const PPP: &str = "PPP";
+/// `kSCEntNetIPSec`.
+const IPSEC: &str = "IPSec";
+/// The entity of VPN links that are not PPP or IPSec based.
+const VPN: &str = "VPN";
+/// `kSCEntNetIPv4`.
+const IPV4: &str = "IPv4";
+/// `kSCEntNetIPv6`.
+const IPV6: &str = "IPv6";
+/// `kSCEntNetDNS`.
+const DNS: &str = "DNS";
+/// `kSCPropNetPPPStatus`.
+const STATUS: &str = "Status";
+/// `kSCPropNetPPPLastCause`.
+const LAST_CAUSE: &str = "LastCause";
+/// `kSCPropNetPPPConnectTime`.
+const CONNECT_TIME: &str = "ConnectTime";
+/// `kSCNetworkConnectionBytesIn`.
+const BYTES_IN: &str = "BytesIn";
+/// `kSCNetworkConnectionBytesOut`.
+const BYTES_OUT: &str = "BytesOut";
+/// `kSCNetworkConnectionPacketsIn`.
+const PACKETS_IN: &str = "PacketsIn";
+/// `kSCNetworkConnectionPacketsOut`.
+const PACKETS_OUT: &str = "PacketsOut";
+/// `kSCNetworkConnectionErrorsIn`.
+const ERRORS_IN: &str = "ErrorsIn";
+/// `kSCNetworkConnectionErrorsOut`.
+const ERRORS_OUT: &str = "ErrorsOut";
+
+/// The status of a network connection.
+///
+/// See [`SCNetworkConnectionStatus`] for details.
+///
+/// [`SCNetworkConnectionStatus`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconnectionstatus?language=objc
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SCNetworkConnectionStatus {
+    /// The service is not configured or the status could not be determined.
+    Invalid,
+    /// The connection is not active.
+    Disconnected,
+    /// The connection is being established.
+    Connecting,
+    /// The connection is established.
+    Connected,
+    /// The connection is being torn down.
+    Disconnecting,
+    /// A status not known to this crate.
+    Other(i32),
+}
+
+impl From<i32> for SCNetworkConnectionStatus {
+    fn from(status: i32) -> Self {
+        match status {
+            kSCNetworkConnectionInvalid => SCNetworkConnectionStatus::Invalid,
+            kSCNetworkConnectionDisconnected => SCNetworkConnectionStatus::Disconnected,
+            kSCNetworkConnectionConnecting => SCNetworkConnectionStatus::Connecting,
+            kSCNetworkConnectionConnected => SCNetworkConnectionStatus::Connected,
+            kSCNetworkConnectionDisconnecting => SCNetworkConnectionStatus::Disconnecting,
+            other => SCNetworkConnectionStatus::Other(other),
+        }
+    }
+}
+
+impl From<SCNetworkConnectionStatus> for i32 {
+    fn from(status: SCNetworkConnectionStatus) -> Self {
+        match status {
+            SCNetworkConnectionStatus::Invalid => kSCNetworkConnectionInvalid,
+            SCNetworkConnectionStatus::Disconnected => kSCNetworkConnectionDisconnected,
+            SCNetworkConnectionStatus::Connecting => kSCNetworkConnectionConnecting,
+            SCNetworkConnectionStatus::Connected => kSCNetworkConnectionConnected,
+            SCNetworkConnectionStatus::Disconnecting => kSCNetworkConnectionDisconnecting,
+            SCNetworkConnectionStatus::Other(other) => other,
+        }
+    }
+}
+
+/// The detailed status of the PPP link of a connection, found in its [`ExtendedStatus`].
+///
+/// See [`SCNetworkConnectionPPPStatus`] for details.
+///
+/// [`SCNetworkConnectionPPPStatus`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconnectionpppstatus?language=objc
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PppStatus {
+    /// The link is disconnected.
+    Disconnected,
+    /// The link is being initialized.
+    Initializing,
+    /// The link is being connected, for example a modem is dialing.
+    ConnectingLink,
+    /// The link will be connected as soon as there is traffic.
+    DialOnTraffic,
+    /// The link protocol is being negotiated.
+    NegotiatingLink,
+    /// The user is being authenticated.
+    Authenticating,
+    /// The link is waiting for the peer to call back.
+    WaitingForCallBack,
+    /// The network protocols are being negotiated.
+    NegotiatingNetwork,
+    /// The link is connected.
+    Connected,
+    /// The link is being terminated.
+    Terminating,
+    /// The link is being disconnected.
+    DisconnectingLink,
+    /// The link is disconnected and held off for a while.
+    HoldingLinkOff,
+    /// The link is suspended.
+    Suspended,
+    /// The link is waiting before redialing.
+    WaitingForRedial,
+    /// A status not known to this crate.
+    Other(i32),
+}
+
+impl From<i32> for PppStatus {
+    fn from(status: i32) -> Self {
+        match status {
+            kSCNetworkConnectionPPPDisconnected => PppStatus::Disconnected,
+            kSCNetworkConnectionPPPInitializing => PppStatus::Initializing,
+            kSCNetworkConnectionPPPConnectingLink => PppStatus::ConnectingLink,
+            kSCNetworkConnectionPPPDialOnTraffic => PppStatus::DialOnTraffic,
+            kSCNetworkConnectionPPPNegotiatingLink => PppStatus::NegotiatingLink,
+            kSCNetworkConnectionPPPAuthenticating => PppStatus::Authenticating,
+            kSCNetworkConnectionPPPWaitingForCallBack => PppStatus::WaitingForCallBack,
+            kSCNetworkConnectionPPPNegotiatingNetwork => PppStatus::NegotiatingNetwork,
+            kSCNetworkConnectionPPPConnected => PppStatus::Connected,
+            kSCNetworkConnectionPPPTerminating => PppStatus::Terminating,
+            kSCNetworkConnectionPPPDisconnectingLink => PppStatus::DisconnectingLink,
+            kSCNetworkConnectionPPPHoldingLinkOff => PppStatus::HoldingLinkOff,
+            kSCNetworkConnectionPPPSuspended => PppStatus::Suspended,
+            kSCNetworkConnectionPPPWaitingForRedial => PppStatus::WaitingForRedial,
+            other => PppStatus::Other(other),
+        }
+    }
+}
+
+impl From<PppStatus> for i32 {
+    fn from(status: PppStatus) -> Self {
+        match status {
+            PppStatus::Disconnected => kSCNetworkConnectionPPPDisconnected,
+            PppStatus::Initializing => kSCNetworkConnectionPPPInitializing,
+            PppStatus::ConnectingLink => kSCNetworkConnectionPPPConnectingLink,
+            PppStatus::DialOnTraffic => kSCNetworkConnectionPPPDialOnTraffic,
+            PppStatus::NegotiatingLink => kSCNetworkConnectionPPPNegotiatingLink,
+            PppStatus::Authenticating => kSCNetworkConnectionPPPAuthenticating,
+            PppStatus::WaitingForCallBack => kSCNetworkConnectionPPPWaitingForCallBack,
+            PppStatus::NegotiatingNetwork => kSCNetworkConnectionPPPNegotiatingNetwork,
+            PppStatus::Connected => kSCNetworkConnectionPPPConnected,
+            PppStatus::Terminating => kSCNetworkConnectionPPPTerminating,
+            PppStatus::DisconnectingLink => kSCNetworkConnectionPPPDisconnectingLink,
+            PppStatus::HoldingLinkOff => kSCNetworkConnectionPPPHoldingLinkOff,
+            PppStatus::Suspended => kSCNetworkConnectionPPPSuspended,
+            PppStatus::WaitingForRedial => kSCNetworkConnectionPPPWaitingForRedial,
+            PppStatus::Other(other) => other,
+        }
+    }
+}
+
+/// The status of one link of a connection, such as the `PPP` or `IPSec` entry of the
+/// [`ExtendedStatus`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkStatus {
+    /// `kSCPropNetPPPStatus`, the raw status of the link. Its meaning depends on the kind of
+    /// link, for PPP links it is a [`PppStatus`].
+    pub status: Option<i32>,
+    /// `kSCPropNetPPPLastCause`, the reason the link was last disconnected.
+    pub last_cause: Option<i32>,
+    /// `kSCPropNetPPPConnectTime`, when the link was connected, in seconds since boot.
+    pub connect_time: Option<u64>,
+    /// Keys not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl SettingsModel for LinkStatus {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(LinkStatus {
+            status: reader.bounded_integer(STATUS)?,
+            last_cause: reader.bounded_integer(LAST_CAUSE)?,
+            connect_time: reader.bounded_integer(CONNECT_TIME)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        writer.integer(STATUS, self.status);
+        writer.integer(LAST_CAUSE, self.last_cause);
+        writer.value(
+            CONNECT_TIME,
+            self.connect_time
+                .map(|time| PlistValue::Integer(time as i64)),
+        );
+        writer.finish()
+    }
+}
+
+/// The extended status of a connection, as returned by [`SCNetworkConnection::extended_status`].
+///
+/// The dictionary is keyed by entity. The link entities describe the state of the connection
+/// itself, while the network entities hold the configuration the connection has brought up.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtendedStatus {
+    /// `kSCEntNetPPP`, the status of the PPP link.
+    pub ppp: Option<LinkStatus>,
+    /// `kSCEntNetIPSec`, the status of the IPSec link.
+    pub ipsec: Option<LinkStatus>,
+    /// `VPN`, the status of a VPN link that is neither PPP nor IPSec based.
+    pub vpn: Option<LinkStatus>,
+    /// `kSCEntNetIPv4`, the IPv4 configuration of the connection.
+    pub ipv4: Option<Ipv4Config>,
+    /// `kSCEntNetIPv6`, the IPv6 configuration of the connection.
+    pub ipv6: Option<Ipv6Config>,
+    /// `kSCEntNetDNS`, the DNS configuration of the connection.
+    pub dns: Option<DnsConfig>,
+    /// Entities not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl ExtendedStatus {
+    /// Returns the status of the PPP link, if the connection has one.
+    pub fn ppp_status(&self) -> Option<PppStatus> {
+        self.ppp.as_ref()?.status.map(PppStatus::from)
+    }
+}
+
+impl SettingsModel for ExtendedStatus {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(ExtendedStatus {
+            ppp: reader.model(PPP)?,
+            ipsec: reader.model(IPSEC)?,
+            vpn: reader.model(VPN)?,
+            ipv4: reader.model(IPV4)?,
+            ipv6: reader.model(IPV6)?,
+            dns: reader.model(DNS)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        writer.model(PPP, &self.ppp);
+        writer.model(IPSEC, &self.ipsec);
+        writer.model(VPN, &self.vpn);
+        writer.model(IPV4, &self.ipv4);
+        writer.model(IPV6, &self.ipv6);
+        writer.model(DNS, &self.dns);
+        writer.finish()
+    }
+}
+
+/// Traffic counters of one link of a connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionCounters {
+    /// `kSCNetworkConnectionBytesIn`.
+    pub bytes_in: Option<u64>,
+    /// `kSCNetworkConnectionBytesOut`.
+    pub bytes_out: Option<u64>,
+    /// `kSCNetworkConnectionPacketsIn`.
+    pub packets_in: Option<u64>,
+    /// `kSCNetworkConnectionPacketsOut`.
+    pub packets_out: Option<u64>,
+    /// `kSCNetworkConnectionErrorsIn`.
+    pub errors_in: Option<u64>,
+    /// `kSCNetworkConnectionErrorsOut`.
+    pub errors_out: Option<u64>,
+    /// Keys not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl SettingsModel for ConnectionCounters {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(ConnectionCounters {
+            bytes_in: reader.bounded_integer(BYTES_IN)?,
+            bytes_out: reader.bounded_integer(BYTES_OUT)?,
+            packets_in: reader.bounded_integer(PACKETS_IN)?,
+            packets_out: reader.bounded_integer(PACKETS_OUT)?,
+            errors_in: reader.bounded_integer(ERRORS_IN)?,
+            errors_out: reader.bounded_integer(ERRORS_OUT)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        for (key, value) in [
+            (BYTES_IN, self.bytes_in),
+            (BYTES_OUT, self.bytes_out),
+            (PACKETS_IN, self.packets_in),
+            (PACKETS_OUT, self.packets_out),
+            (ERRORS_IN, self.errors_in),
+            (ERRORS_OUT, self.errors_out),
+        ] {
+            writer.value(
+                key,
+                value.map(|counter| PlistValue::Integer(counter as i64)),
+            );
+        }
+        writer.finish()
+    }
+}
+
+/// The statistics of a connection, as returned by [`SCNetworkConnection::statistics`].
+///
+/// The framework reports counters per link, keyed by entity, usually `PPP`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionStatistics {
+    /// The counters of every link, keyed by entity.
+    pub links: BTreeMap<String, ConnectionCounters>,
+}
+
+impl ConnectionStatistics {
+    /// Returns the counters of the PPP link, if the connection has one.
+    pub fn ppp(&self) -> Option<&ConnectionCounters> {
+        self.links.get(PPP)
+    }
+}
+
+impl SettingsModel for ConnectionStatistics {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        let mut links = BTreeMap::new();
+        for entity in dictionary.keys() {
+            if let Some(counters) = reader.model(entity)? {
+                links.insert(entity.clone(), counters);
+            }
+        }
+        Ok(ConnectionStatistics { links })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&Dictionary::new());
+        for (entity, counters) in &self.links {
+            writer.value(entity, Some(PlistValue::Dictionary(counters.to_plist())));
+        }
+        writer.finish()
+    }
+}
+
+core_foundation::declare_TCFType!(
+    /// A connection to a network service that has to be dialed, such as a PPP or VPN service.
+    ///
+    /// See [`SCNetworkConnectionRef`] for details.
+    ///
+    /// [`SCNetworkConnectionRef`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconnection?language=objc
+    SCNetworkConnection,
+    SCNetworkConnectionRef
+);
+
+core_foundation::impl_TCFType!(
+    SCNetworkConnection,
+    SCNetworkConnectionRef,
+    SCNetworkConnectionGetTypeID
+);
+
+impl SCNetworkConnection {
+    /// Creates a connection for the network service with the given service ID.
+    ///
+    /// See [`SCNetworkConnectionCreateWithServiceID`] for details.
+    ///
+    /// [`SCNetworkConnectionCreateWithServiceID`]: https://developer.apple.com/documentation/systemconfiguration/1516787-scnetworkconnectioncreatewithser?language=objc
+    pub fn with_service_id(service_id: &CFString) -> Option<Self> {
+        let ptr = unsafe {
+            SCNetworkConnectionCreateWithServiceID(
+                ptr::null(),
+                service_id.as_concrete_TypeRef(),
+                None,
+                ptr::null_mut(),
+            )
+        };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { Self::wrap_under_create_rule(ptr) })
+        }
+    }
+
+    /// Creates a connection for the network service with the given service ID. The callback is
+    /// run whenever the status of the connection changes. For the callback to be invoked, the
+    /// connection has to be scheduled on a run loop.
+    ///
+    /// See [`SCNetworkConnectionCreateWithServiceID`] for details.
+    ///
+    /// [`SCNetworkConnectionCreateWithServiceID`]: https://developer.apple.com/documentation/systemconfiguration/1516787-scnetworkconnectioncreatewithser?language=objc
+    pub fn with_service_id_and_callback<F>(service_id: &CFString, callback: F) -> Option<Self>
+    where
+        F: Fn(SCNetworkConnectionStatus) + Send + Sync + 'static,
+    {
+        let callback = Arc::new(NetworkConnectionCallbackContext { callback });
+        let mut context = SCNetworkConnectionContext {
+            version: 0,
+            info: Arc::into_raw(callback) as *mut _,
+            retain: Some(NetworkConnectionCallbackContext::<F>::retain_context),
+            release: Some(NetworkConnectionCallbackContext::<F>::release_context),
+            copyDescription: Some(NetworkConnectionCallbackContext::<F>::copy_ctx_description),
+        };
+
+        let ptr = unsafe {
+            SCNetworkConnectionCreateWithServiceID(
+                ptr::null(),
+                service_id.as_concrete_TypeRef(),
+                Some(NetworkConnectionCallbackContext::<F>::callback),
+                &mut context,
+            )
+        };
+
+        // The connection retains the context when it is created and releases it when it is
+        // dropped, so the reference taken by `Arc::into_raw` can be given up here. If the
+        // connection could not be created, this frees the context.
+        unsafe {
+            Arc::decrement_strong_count(context.info as *const NetworkConnectionCallbackContext<F>)
+        };
+
+        if ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { Self::wrap_under_create_rule(ptr) })
+        }
+    }
+
+    /// Returns the service ID and the user options of the service the user would connect to by
+    /// default, such as the service selected in the network menu.
+    ///
+    /// See [`SCNetworkConnectionCopyUserPreferences`] for details.
+    ///
+    /// [`SCNetworkConnectionCopyUserPreferences`]: https://developer.apple.com/documentation/systemconfiguration/1516788-scnetworkconnectioncopyuserprefe?language=objc
+    pub fn user_preferences(
+        selection_options: Option<&CFDictionary<CFString, CFType>>,
+    ) -> Option<(CFString, CFDictionary<CFString, CFType>)> {
+        let mut service_id: CFStringRef = ptr::null();
+        let mut user_options: CFDictionaryRef = ptr::null();
+        let found = unsafe {
+            SCNetworkConnectionCopyUserPreferences(
+                selection_options.map_or(ptr::null(), |options| options.as_concrete_TypeRef()),
+                &mut service_id,
+                &mut user_options,
+            )
+        };
+        if found == 0 || service_id.is_null() {
+            return None;
+        }
+        unsafe {
+            let service_id = CFString::wrap_under_create_rule(service_id);
+            let user_options = if user_options.is_null() {
+                CFDictionary::from_CFType_pairs(&[])
+            } else {
+                CFDictionary::wrap_under_create_rule(user_options)
+            };
+            Some((service_id, user_options))
+        }
+    }
+
+    /// Returns the ID of the service this connection is for.
+    ///
+    /// See [`SCNetworkConnectionCopyServiceID`] for details.
+    ///
+    /// [`SCNetworkConnectionCopyServiceID`]: https://developer.apple.com/documentation/systemconfiguration/1516783-scnetworkconnectioncopyserviceid?language=objc
+    pub fn service_id(&self) -> Option<CFString> {
+        unsafe {
+            let ptr = SCNetworkConnectionCopyServiceID(self.0);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CFString::wrap_under_create_rule(ptr))
+            }
+        }
+    }
+
+    /// Returns the status of the connection.
+    ///
+    /// See [`SCNetworkConnectionGetStatus`] for details.
+    ///
+    /// [`SCNetworkConnectionGetStatus`]: https://developer.apple.com/documentation/systemconfiguration/1516779-scnetworkconnectiongetstatus?language=objc
+    pub fn status(&self) -> SCNetworkConnectionStatus {
+        SCNetworkConnectionStatus::from(unsafe { SCNetworkConnectionGetStatus(self.0) })
+    }
+
+    /// Returns the extended status of the connection. Use [`ExtendedStatus`] to parse it.
+    ///
+    /// See [`SCNetworkConnectionCopyExtendedStatus`] for details.
+    ///
+    /// [`SCNetworkConnectionCopyExtendedStatus`]: https://developer.apple.com/documentation/systemconfiguration/1516789-scnetworkconnectioncopyextendeds?language=objc
+    pub fn extended_status(&self) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe { Self::wrap_dictionary(SCNetworkConnectionCopyExtendedStatus(self.0)) }
+    }
+
+    /// Returns the statistics of the connection. Use [`ConnectionStatistics`] to parse them.
+    ///
+    /// See [`SCNetworkConnectionCopyStatistics`] for details.
+    ///
+    /// [`SCNetworkConnectionCopyStatistics`]: https://developer.apple.com/documentation/systemconfiguration/1516785-scnetworkconnectioncopystatistic?language=objc
+    pub fn statistics(&self) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe { Self::wrap_dictionary(SCNetworkConnectionCopyStatistics(self.0)) }
+    }
+
+    /// Returns the user options the connection was last started with.
+    ///
+    /// See [`SCNetworkConnectionCopyUserOptions`] for details.
+    ///
+    /// [`SCNetworkConnectionCopyUserOptions`]: https://developer.apple.com/documentation/systemconfiguration/1516781-scnetworkconnectioncopyuseroptio?language=objc
+    pub fn user_options(&self) -> Option<CFDictionary<CFString, CFType>> {
+        unsafe { Self::wrap_dictionary(SCNetworkConnectionCopyUserOptions(self.0)) }
+    }
+
+    /// Starts the connection. The user options override the configuration of the service for
+    /// this connection attempt, `None` uses the configuration as it is. If `linger` is `false`,
+    /// the connection is stopped when this process exits.
+    ///
+    /// See [`SCNetworkConnectionStart`] for details.
+    ///
+    /// [`SCNetworkConnectionStart`]: https://developer.apple.com/documentation/systemconfiguration/1516794-scnetworkconnectionstart?language=objc
+    pub fn start(
+        &self,
+        user_options: Option<&CFDictionary<CFString, CFType>>,
+        linger: bool,
+    ) -> Result<(), SCError> {
+        to_result(unsafe {
+            SCNetworkConnectionStart(
+                self.0,
+                user_options.map_or(ptr::null(), |options| options.as_concrete_TypeRef()),
+                linger as u8,
+            )
+        })
+    }
+
+    /// Stops the connection. Unless `force_disconnect` is set, the connection stays up if other
+    /// processes still use it.
+    ///
+    /// See [`SCNetworkConnectionStop`] for details.
+    ///
+    /// [`SCNetworkConnectionStop`]: https://developer.apple.com/documentation/systemconfiguration/1516796-scnetworkconnectionstop?language=objc
+    pub fn stop(&self, force_disconnect: bool) -> Result<(), SCError> {
+        to_result(unsafe { SCNetworkConnectionStop(self.0, force_disconnect as u8) })
+    }
+
+    /// Schedules the status callback of the connection on a run loop.
+    ///
+    /// See [`SCNetworkConnectionScheduleWithRunLoop`] for details.
+    ///
+    /// [`SCNetworkConnectionScheduleWithRunLoop`]: https://developer.apple.com/documentation/systemconfiguration/1516791-scnetworkconnectionschedulewithr?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `run_loop_mode` must not be NULL and must be a pointer to a valid run loop mode.
+    /// Use `core_foundation::runloop::kCFRunLoopCommonModes` if you are unsure.
+    pub unsafe fn schedule_with_runloop(
+        &self,
+        run_loop: &CFRunLoop,
+        run_loop_mode: CFStringRef,
+    ) -> Result<(), SCError> {
+        to_result(SCNetworkConnectionScheduleWithRunLoop(
+            self.0,
+            run_loop.to_void() as *mut _,
+            run_loop_mode,
+        ))
+    }
+
+    /// Unschedules the status callback of the connection from a run loop.
+    ///
+    /// See [`SCNetworkConnectionUnscheduleFromRunLoop`] for details.
+    ///
+    /// [`SCNetworkConnectionUnscheduleFromRunLoop`]: https://developer.apple.com/documentation/systemconfiguration/1516798-scnetworkconnectionunschedulefro?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `run_loop_mode` must not be NULL and must be a pointer to a valid run loop mode.
+    /// Use `core_foundation::runloop::kCFRunLoopCommonModes` if you are unsure.
+    pub unsafe fn unschedule_from_runloop(
+        &self,
+        run_loop: &CFRunLoop,
+        run_loop_mode: CFStringRef,
+    ) -> Result<(), SCError> {
+        to_result(SCNetworkConnectionUnscheduleFromRunLoop(
+            self.0,
+            run_loop.to_void() as *mut _,
+            run_loop_mode,
+        ))
+    }
+
+    /// Schedules the status callback of the connection on a dispatch queue, or unschedules it if
+    /// `queue` is NULL.
+    ///
+    /// See [`SCNetworkConnectionSetDispatchQueue`] for details.
+    ///
+    /// [`SCNetworkConnectionSetDispatchQueue`]: https://developer.apple.com/documentation/systemconfiguration/scnetworkconnectionsetdispatchqueue(_:_:)?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `queue` must be NULL or a valid dispatch queue.
+    pub unsafe fn set_dispatch_queue(&self, queue: dispatch_queue_t) -> Result<(), SCError> {
+        to_result(SCNetworkConnectionSetDispatchQueue(self.0, queue))
+    }
+
+    unsafe fn wrap_dictionary(ptr: CFDictionaryRef) -> Option<CFDictionary<CFString, CFType>> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(CFDictionary::wrap_under_create_rule(ptr))
+        }
+    }
+}
+
+struct NetworkConnectionCallbackContext<F: Fn(SCNetworkConnectionStatus) + Send + Sync> {
+    callback: F,
+}
+
+impl<F: Fn(SCNetworkConnectionStatus) + Send + Sync> NetworkConnectionCallbackContext<F> {
+    extern "C" fn callback(_connection: SCNetworkConnectionRef, status: i32, info: *mut c_void) {
+        let context: &Self = unsafe { &*(info as *const Self) };
+        (context.callback)(SCNetworkConnectionStatus::from(status));
+    }
+
+    extern "C" fn copy_ctx_description(_ctx: *const c_void) -> CFStringRef {
+        let description = CFString::from_static_string("NetworkConnection's callback context");
+        let description_ref = description.as_concrete_TypeRef();
+        std::mem::forget(description);
+        description_ref
+    }
+
+    extern "C" fn release_context(ctx: *const c_void) {
+        unsafe {
+            Arc::decrement_strong_count(ctx as *mut Self);
+        }
+    }
+
+    extern "C" fn retain_context(ctx_ptr: *const c_void) -> *const c_void {
+        unsafe {
+            Arc::increment_strong_count(ctx_ptr as *mut Self);
+        }
+        ctx_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(entries: &[(&str, PlistValue)]) -> Dictionary {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn status_round_trip() {
+        for raw in -2..5 {
+            assert_eq!(i32::from(SCNetworkConnectionStatus::from(raw)), raw);
+        }
+        assert_eq!(
+            SCNetworkConnectionStatus::from(2),
+            SCNetworkConnectionStatus::Connected
+        );
+        assert_eq!(
+            SCNetworkConnectionStatus::from(7),
+            SCNetworkConnectionStatus::Other(7)
+        );
+        for raw in 0..15 {
+            assert_eq!(i32::from(PppStatus::from(raw)), raw);
+        }
+        assert_eq!(PppStatus::from(8), PppStatus::Connected);
+        assert_eq!(PppStatus::from(14), PppStatus::Other(14));
+    }
+
+    #[test]
+    fn parse_extended_status() {
+        let raw = dictionary(&[
+            (
+                "PPP",
+                PlistValue::Dictionary(dictionary(&[
+                    ("Status", PlistValue::Integer(8)),
+                    ("LastCause", PlistValue::Integer(0)),
+                    ("ConnectTime", PlistValue::Integer(3600)),
+                    ("CommRemoteAddress", "vpn.example.com".into()),
+                ])),
+            ),
+            (
+                "IPv4",
+                PlistValue::Dictionary(dictionary(&[(
+                    "Addresses",
+                    PlistValue::Array(vec!["10.8.0.2".into()]),
+                )])),
+            ),
+            ("Status", PlistValue::Integer(2)),
+        ]);
+
+        let status = ExtendedStatus::from_plist(&raw).unwrap();
+        assert_eq!(status.ppp_status(), Some(PppStatus::Connected));
+        let ppp = status.ppp.as_ref().unwrap();
+        assert_eq!(ppp.connect_time, Some(3600));
+        assert_eq!(ppp.extra.len(), 1);
+        assert_eq!(
+            status.ipv4.as_ref().unwrap().addresses,
+            Some(vec!["10.8.0.2".parse().unwrap()])
+        );
+        assert!(status.ipsec.is_none());
+        assert_eq!(status.extra.len(), 1);
+        assert_eq!(status.to_plist(), raw);
+    }
+
+    #[test]
+    fn parse_extended_status_wrong_type() {
+        let raw = dictionary(&[("PPP", "connected".into())]);
+        assert_eq!(
+            ExtendedStatus::from_plist(&raw),
+            Err(ConfigError::InvalidType {
+                key: "PPP".to_owned(),
+                expected: "dictionary",
+                found: "string",
+            })
+        );
+    }
+
+    #[test]
+    fn parse_statistics() {
+        let raw = dictionary(&[(
+            "PPP",
+            PlistValue::Dictionary(dictionary(&[
+                ("BytesIn", PlistValue::Integer(1024)),
+                ("BytesOut", PlistValue::Integer(512)),
+                ("PacketsIn", PlistValue::Integer(8)),
+                ("PacketsOut", PlistValue::Integer(4)),
+                ("ErrorsIn", PlistValue::Integer(0)),
+                ("ErrorsOut", PlistValue::Integer(1)),
+            ])),
+        )]);
+
+        let statistics = ConnectionStatistics::from_plist(&raw).unwrap();
+        let ppp = statistics.ppp().unwrap();
+        assert_eq!(ppp.bytes_in, Some(1024));
+        assert_eq!(ppp.errors_out, Some(1));
+        assert!(ppp.extra.is_empty());
+        assert_eq!(statistics.to_plist(), raw);
+
+        let negative = dictionary(&[(
+            "PPP",
+            PlistValue::Dictionary(dictionary(&[("BytesIn", PlistValue::Integer(-1))])),
+        )]);
+        assert!(matches!(
+            ConnectionStatistics::from_plist(&negative),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+
+        let keys = unsafe {
+            [
+                (PPP, kSCEntNetPPP),
+                (IPSEC, kSCEntNetIPSec),
+                (IPV4, kSCEntNetIPv4),
+                (IPV6, kSCEntNetIPv6),
+                (DNS, kSCEntNetDNS),
+                (STATUS, kSCPropNetPPPStatus),
+                (LAST_CAUSE, kSCPropNetPPPLastCause),
+                (CONNECT_TIME, kSCPropNetPPPConnectTime),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
+    }
+}
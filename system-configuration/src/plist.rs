@@ -0,0 +1,379 @@
+// Copyright 2017 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Owned, pure Rust representation of property list values.
+//!
+//! Configuration in SystemConfiguration is exchanged as property lists: dictionaries with string
+//! keys, holding strings, numbers, booleans, data, dates and nested arrays and dictionaries.
+//! [`PlistValue`] mirrors that data model so configuration can be inspected and built without
+//! going through CoreFoundation, and converted losslessly to and from the CoreFoundation types.
+//!
+//! [`PlistValue::from_slice`] reads the XML and binary property list formats without
+//! CoreFoundation, for example to inspect preference files copied from another machine.
+//!
+//! With the `serde` feature, [`PlistValue`] implements `Serialize` and `Deserialize`, so values
+//! read from the framework can be stored in formats like JSON and read back unchanged.
+
+use core_foundation::{
+    array::CFArray,
+    base::{CFType, TCFType},
+    boolean::CFBoolean,
+    data::CFData,
+    date::{CFAbsoluteTime, CFDate},
+    dictionary::CFDictionary,
+    number::{CFNumber, CFNumberIsFloatType},
+    propertylist::CFPropertyList,
+    string::CFString,
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+mod binary;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod xml;
+
+/// How deeply arrays and dictionaries may be nested in a parsed property list.
+const MAX_DEPTH: usize = 512;
+
+/// A property list dictionary. Keys are always strings.
+pub type Dictionary = BTreeMap<String, PlistValue>;
+
+/// A single property list value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlistValue {
+    /// A `CFDictionary` with string keys.
+    Dictionary(Dictionary),
+    /// A `CFArray`.
+    Array(Vec<PlistValue>),
+    /// A `CFString`.
+    String(String),
+    /// A `CFNumber` holding an integer.
+    Integer(i64),
+    /// A `CFNumber` holding a floating point value.
+    Real(f64),
+    /// A `CFBoolean`.
+    Boolean(bool),
+    /// A `CFData`.
+    Data(Vec<u8>),
+    /// A `CFDate`, as seconds relative to 2001-01-01 00:00:00 UTC.
+    Date(CFAbsoluteTime),
+}
+
+impl PlistValue {
+    /// Parses a property list in the XML or binary format. The format is detected from the data.
+    pub fn from_slice(data: &[u8]) -> Result<Self, ParseError> {
+        if data.starts_with(binary::MAGIC) {
+            binary::parse(data)
+        } else {
+            xml::parse(data)
+        }
+    }
+
+    /// Returns the string if this is a [`PlistValue::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PlistValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer if this is a [`PlistValue::Integer`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PlistValue::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if this is a [`PlistValue::Real`] or a [`PlistValue::Integer`].
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PlistValue::Real(real) => Some(*real),
+            PlistValue::Integer(integer) => Some(*integer as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean if this is a [`PlistValue::Boolean`].
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PlistValue::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes if this is a [`PlistValue::Data`].
+    pub fn as_data(&self) -> Option<&[u8]> {
+        match self {
+            PlistValue::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if this is a [`PlistValue::Array`].
+    pub fn as_array(&self) -> Option<&[PlistValue]> {
+        match self {
+            PlistValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if this is a [`PlistValue::Dictionary`].
+    pub fn as_dictionary(&self) -> Option<&Dictionary> {
+        match self {
+            PlistValue::Dictionary(dictionary) => Some(dictionary),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the kind of value this is, for use in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PlistValue::Dictionary(_) => "dictionary",
+            PlistValue::Array(_) => "array",
+            PlistValue::String(_) => "string",
+            PlistValue::Integer(_) => "integer",
+            PlistValue::Real(_) => "real",
+            PlistValue::Boolean(_) => "boolean",
+            PlistValue::Data(_) => "data",
+            PlistValue::Date(_) => "date",
+        }
+    }
+
+    /// Converts a CoreFoundation value into a [`PlistValue`].
+    ///
+    /// Returns `None` if the value, or any value nested in it, is not a property list type, or if
+    /// a dictionary has keys that are not strings.
+    pub fn from_cf_type(value: &CFType) -> Option<Self> {
+        if let Some(string) = value.downcast::<CFString>() {
+            Some(PlistValue::String(string.to_string()))
+        } else if let Some(boolean) = value.downcast::<CFBoolean>() {
+            Some(PlistValue::Boolean(boolean.into()))
+        } else if let Some(number) = value.downcast::<CFNumber>() {
+            if unsafe { CFNumberIsFloatType(number.as_concrete_TypeRef()) } != 0 {
+                number.to_f64().map(PlistValue::Real)
+            } else {
+                number.to_i64().map(PlistValue::Integer)
+            }
+        } else if let Some(data) = value.downcast::<CFData>() {
+            Some(PlistValue::Data(data.bytes().to_vec()))
+        } else if let Some(date) = value.downcast::<CFDate>() {
+            Some(PlistValue::Date(date.abs_time()))
+        } else if let Some(array) = value.downcast::<CFArray>() {
+            array
+                .get_all_values()
+                .into_iter()
+                .map(|ptr| PlistValue::from_cf_type(&unsafe { CFType::wrap_under_get_rule(ptr) }))
+                .collect::<Option<Vec<_>>>()
+                .map(PlistValue::Array)
+        } else if let Some(dictionary) = value.downcast::<CFDictionary>() {
+            let (keys, values) = dictionary.get_keys_and_values();
+            let mut entries = Dictionary::new();
+            for (key, value) in keys.into_iter().zip(values) {
+                let key = unsafe { CFType::wrap_under_get_rule(key) }.downcast::<CFString>()?;
+                let value =
+                    PlistValue::from_cf_type(&unsafe { CFType::wrap_under_get_rule(value) })?;
+                entries.insert(key.to_string(), value);
+            }
+            Some(PlistValue::Dictionary(entries))
+        } else {
+            None
+        }
+    }
+
+    /// Converts a CoreFoundation property list into a [`PlistValue`].
+    ///
+    /// Returns `None` under the same conditions as [`PlistValue::from_cf_type`].
+    pub fn from_property_list(value: &CFPropertyList) -> Option<Self> {
+        PlistValue::from_cf_type(&value.as_CFType())
+    }
+
+    /// Converts this value into the corresponding CoreFoundation value.
+    pub fn to_cf_type(&self) -> CFType {
+        match self {
+            PlistValue::Dictionary(dictionary) => dictionary_to_cf(dictionary).into_CFType(),
+            PlistValue::Array(array) => {
+                let values = array.iter().map(PlistValue::to_cf_type).collect::<Vec<_>>();
+                CFArray::from_CFTypes(&values).into_CFType()
+            }
+            PlistValue::String(string) => CFString::new(string).into_CFType(),
+            PlistValue::Integer(integer) => CFNumber::from(*integer).into_CFType(),
+            PlistValue::Real(real) => CFNumber::from(*real).into_CFType(),
+            PlistValue::Boolean(boolean) => CFBoolean::from(*boolean).into_CFType(),
+            PlistValue::Data(data) => CFData::from_buffer(data).into_CFType(),
+            PlistValue::Date(date) => CFDate::new(*date).into_CFType(),
+        }
+    }
+
+    /// Converts this value into the corresponding CoreFoundation property list.
+    pub fn to_property_list(&self) -> CFPropertyList {
+        let value = self.to_cf_type();
+        unsafe { CFPropertyList::wrap_under_get_rule(value.as_CFTypeRef()) }
+    }
+}
+
+impl From<&str> for PlistValue {
+    fn from(string: &str) -> Self {
+        PlistValue::String(string.to_owned())
+    }
+}
+
+impl From<String> for PlistValue {
+    fn from(string: String) -> Self {
+        PlistValue::String(string)
+    }
+}
+
+impl From<i64> for PlistValue {
+    fn from(integer: i64) -> Self {
+        PlistValue::Integer(integer)
+    }
+}
+
+impl From<f64> for PlistValue {
+    fn from(real: f64) -> Self {
+        PlistValue::Real(real)
+    }
+}
+
+impl From<bool> for PlistValue {
+    fn from(boolean: bool) -> Self {
+        PlistValue::Boolean(boolean)
+    }
+}
+
+impl From<Vec<u8>> for PlistValue {
+    fn from(data: Vec<u8>) -> Self {
+        PlistValue::Data(data)
+    }
+}
+
+impl From<Vec<PlistValue>> for PlistValue {
+    fn from(array: Vec<PlistValue>) -> Self {
+        PlistValue::Array(array)
+    }
+}
+
+impl From<Dictionary> for PlistValue {
+    fn from(dictionary: Dictionary) -> Self {
+        PlistValue::Dictionary(dictionary)
+    }
+}
+
+/// Failure to parse a property list with [`PlistValue::from_slice`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset: usize,
+    reason: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(offset: usize, reason: impl Into<String>) -> Self {
+        ParseError {
+            offset,
+            reason: reason.into(),
+        }
+    }
+
+    /// The byte offset in the data where the error was detected.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// A description of the problem.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid property list at byte {}: {}",
+            self.offset, self.reason
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// Converts a CoreFoundation dictionary, such as the ones returned by
+/// [`SCNetworkProtocol::configuration`], into a [`Dictionary`].
+///
+/// Returns `None` if any value in the dictionary is not a property list type.
+///
+/// [`SCNetworkProtocol::configuration`]: crate::network_configuration::SCNetworkProtocol::configuration
+pub fn dictionary_from_cf(dictionary: &CFDictionary<CFString, CFType>) -> Option<Dictionary> {
+    let value = unsafe { CFType::wrap_under_get_rule(dictionary.as_CFTypeRef()) };
+    match PlistValue::from_cf_type(&value)? {
+        PlistValue::Dictionary(dictionary) => Some(dictionary),
+        _ => None,
+    }
+}
+
+/// Converts a [`Dictionary`] into a CoreFoundation dictionary, such as the ones accepted by
+/// [`SCNetworkProtocol::set_configuration`].
+///
+/// [`SCNetworkProtocol::set_configuration`]: crate::network_configuration::SCNetworkProtocol::set_configuration
+pub fn dictionary_to_cf(dictionary: &Dictionary) -> CFDictionary<CFString, CFType> {
+    let pairs = dictionary
+        .iter()
+        .map(|(key, value)| (CFString::new(key), value.to_cf_type()))
+        .collect::<Vec<_>>();
+    CFDictionary::from_CFType_pairs(&pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        assert_eq!(PlistValue::from("a").as_str(), Some("a"));
+        assert_eq!(PlistValue::from(1).as_i64(), Some(1));
+        assert_eq!(PlistValue::from(1).as_f64(), Some(1.0));
+        assert_eq!(PlistValue::from(1.5).as_i64(), None);
+        assert_eq!(PlistValue::from(true).as_bool(), Some(true));
+        assert_eq!(
+            PlistValue::from(vec![1u8, 2]).as_data(),
+            Some(&[1u8, 2][..])
+        );
+        assert_eq!(PlistValue::from("1").as_i64(), None);
+        assert_eq!(PlistValue::Date(0.0).type_name(), "date");
+    }
+
+    #[test]
+    fn cf_round_trip() {
+        let mut nested = Dictionary::new();
+        nested.insert("Real".to_owned(), PlistValue::Real(0.5));
+        nested.insert("Date".to_owned(), PlistValue::Date(600_000_000.0));
+        nested.insert("Data".to_owned(), PlistValue::Data(vec![0, 1, 255]));
+        let mut dictionary = Dictionary::new();
+        dictionary.insert("String".to_owned(), "value".into());
+        dictionary.insert("Integer".to_owned(), PlistValue::Integer(-3));
+        dictionary.insert("Boolean".to_owned(), PlistValue::Boolean(false));
+        dictionary.insert(
+            "Array".to_owned(),
+            PlistValue::Array(vec![PlistValue::Integer(1), "two".into()]),
+        );
+        dictionary.insert("Nested".to_owned(), PlistValue::Dictionary(nested));
+
+        let cf_dictionary = dictionary_to_cf(&dictionary);
+        assert_eq!(dictionary_from_cf(&cf_dictionary), Some(dictionary.clone()));
+        let value = PlistValue::Dictionary(dictionary);
+        assert_eq!(
+            PlistValue::from_property_list(&value.to_property_list()),
+            Some(value)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Reader for the binary property list format, `bplist00`.
+//!
+//! A binary property list is a header, a table of objects, a table with the offset of every object
+//! and a trailer describing the sizes of the offsets and object references. Collections refer to
+//! their elements by index into the offset table.
+//!
+//! Several collections may refer to the same object. [`PlistValue`] owns its elements, so shared
+//! objects are copied, and the total size of the parsed value is limited to keep small files from
+//! expanding into huge values.
+
+use super::{Dictionary, ParseError, PlistValue, MAX_DEPTH};
+
+/// The header of a binary property list.
+pub(super) const MAGIC: &[u8] = b"bplist00";
+/// Size of the trailer at the end of the data.
+const TRAILER_SIZE: usize = 32;
+/// How large a parsed value may become, counting every value plus the bytes of strings and data.
+const MAX_SIZE: usize = 1 << 24;
+
+/// Parses a binary property list.
+pub(super) fn parse(data: &[u8]) -> Result<PlistValue, ParseError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ParseError::new(0, "missing `bplist00` header"));
+    }
+    if data.len() < MAGIC.len() + TRAILER_SIZE {
+        return Err(ParseError::new(data.len(), "missing trailer"));
+    }
+    let trailer_start = data.len() - TRAILER_SIZE;
+    let trailer = &data[trailer_start..];
+    let offset_size = usize::from(trailer[6]);
+    let reference_size = usize::from(trailer[7]);
+    let object_count = read_uint(&trailer[8..16]);
+    let top_object = read_uint(&trailer[16..24]);
+    let offset_table = read_uint(&trailer[24..32]);
+
+    let invalid_trailer = || ParseError::new(trailer_start, "invalid trailer");
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&reference_size) {
+        return Err(invalid_trailer());
+    }
+    let offset_table_end = object_count
+        .checked_mul(offset_size as u64)
+        .and_then(|size| size.checked_add(offset_table))
+        .ok_or_else(invalid_trailer)?;
+    if top_object >= object_count
+        || offset_table < MAGIC.len() as u64
+        || offset_table_end > trailer_start as u64
+    {
+        return Err(invalid_trailer());
+    }
+
+    let mut parser = Parser {
+        objects: &data[..offset_table as usize],
+        offset_table: &data[offset_table as usize..offset_table_end as usize],
+        offset_table_start: offset_table as usize,
+        offset_size,
+        reference_size,
+        parsed: vec![None; object_count as usize],
+        size: 0,
+    };
+    parser.object(top_object as usize, &mut Vec::new())
+}
+
+struct Parser<'a> {
+    /// The data up to the offset table, which holds all objects.
+    objects: &'a [u8],
+    offset_table: &'a [u8],
+    offset_table_start: usize,
+    offset_size: usize,
+    reference_size: usize,
+    /// The objects parsed so far, with their size, by index.
+    parsed: Vec<Option<(PlistValue, usize)>>,
+    /// The size of all values returned so far, see [`MAX_SIZE`].
+    size: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns `length` bytes at `offset`, failing if they are not within the object table.
+    fn bytes(&self, offset: usize, length: usize) -> Result<&'a [u8], ParseError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.objects.get(offset..end))
+            .ok_or_else(|| ParseError::new(offset, "object extends past the object table"))
+    }
+
+    /// Adds `size` to the size of the parsed value, failing if it grows past [`MAX_SIZE`].
+    fn grow(&mut self, offset: usize, size: usize) -> Result<(), ParseError> {
+        self.size = self.size.saturating_add(size);
+        if self.size > MAX_SIZE {
+            return Err(ParseError::new(offset, "value is too large"));
+        }
+        Ok(())
+    }
+
+    /// Returns the object with the given index, parsing it only the first time it is referred to.
+    /// `parents` holds the collections being parsed, to detect objects containing themselves.
+    fn object(&mut self, index: usize, parents: &mut Vec<usize>) -> Result<PlistValue, ParseError> {
+        if let Some((value, size)) = &self.parsed[index] {
+            let (value, size) = (value.clone(), *size);
+            self.grow(self.offset_table_start + index * self.offset_size, size)?;
+            return Ok(value);
+        }
+        let start_size = self.size;
+        let value = self.parse_object(index, parents)?;
+        self.parsed[index] = Some((value.clone(), self.size - start_size));
+        Ok(value)
+    }
+
+    /// Parses the object with the given index.
+    fn parse_object(
+        &mut self,
+        index: usize,
+        parents: &mut Vec<usize>,
+    ) -> Result<PlistValue, ParseError> {
+        let entry = index * self.offset_size;
+        let offset = read_uint(&self.offset_table[entry..entry + self.offset_size]) as usize;
+        if offset < MAGIC.len() || offset >= self.objects.len() {
+            return Err(ParseError::new(
+                self.offset_table_start + entry,
+                "object offset out of range",
+            ));
+        }
+        let marker = self.objects[offset];
+        let info = usize::from(marker & 0x0f);
+        self.grow(offset, 1)?;
+        let invalid = |what: &str| ParseError::new(offset, format!("invalid {}", what));
+        match marker >> 4 {
+            0x0 => match marker {
+                0x08 => Ok(PlistValue::Boolean(false)),
+                0x09 => Ok(PlistValue::Boolean(true)),
+                _ => Err(invalid("object marker")),
+            },
+            0x1 => {
+                if info > 4 {
+                    return Err(invalid("integer"));
+                }
+                let bytes = self.bytes(offset + 1, 1 << info)?;
+                read_int(bytes)
+                    .map(PlistValue::Integer)
+                    .ok_or_else(|| invalid("integer"))
+            }
+            0x2 => match info {
+                2 => {
+                    let bytes = self.bytes(offset + 1, 4)?;
+                    let real = f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    Ok(PlistValue::Real(f64::from(real)))
+                }
+                3 => Ok(PlistValue::Real(read_f64(self.bytes(offset + 1, 8)?))),
+                _ => Err(invalid("real")),
+            },
+            0x3 if marker == 0x33 => Ok(PlistValue::Date(read_f64(self.bytes(offset + 1, 8)?))),
+            0x4 => {
+                let (start, length) = self.length(offset, info)?;
+                let bytes = self.bytes(start, length)?;
+                self.grow(offset, length)?;
+                Ok(PlistValue::Data(bytes.to_vec()))
+            }
+            0x5 => {
+                let (start, length) = self.length(offset, info)?;
+                let bytes = self.bytes(start, length)?;
+                self.grow(offset, length)?;
+                Ok(PlistValue::String(
+                    bytes.iter().map(|&byte| char::from(byte)).collect(),
+                ))
+            }
+            0x6 => {
+                let (start, length) = self.length(offset, info)?;
+                let size = length
+                    .checked_mul(2)
+                    .ok_or_else(|| invalid("string length"))?;
+                let units = self
+                    .bytes(start, size)?
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>();
+                self.grow(offset, size)?;
+                String::from_utf16(&units)
+                    .map(PlistValue::String)
+                    .map_err(|_| invalid("string"))
+            }
+            0xa | 0xd => {
+                if parents.contains(&index) {
+                    return Err(ParseError::new(offset, "collection contains itself"));
+                }
+                if parents.len() >= MAX_DEPTH {
+                    return Err(ParseError::new(offset, "values are nested too deeply"));
+                }
+                let (start, length) = self.length(offset, info)?;
+                let count = if marker >> 4 == 0xa {
+                    length
+                } else {
+                    length.checked_mul(2).ok_or_else(|| invalid("dictionary"))?
+                };
+                let size = count
+                    .checked_mul(self.reference_size)
+                    .ok_or_else(|| invalid("collection length"))?;
+                self.bytes(start, size)?;
+                let references = (0..count)
+                    .map(|i| self.reference(start + i * self.reference_size))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                parents.push(index);
+                let value = if marker >> 4 == 0xa {
+                    references
+                        .iter()
+                        .map(|&element| self.object(element, parents))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(PlistValue::Array)
+                } else {
+                    let (keys, values) = references.split_at(length);
+                    let mut dictionary = Dictionary::new();
+                    for (&key, &value) in keys.iter().zip(values) {
+                        let key = match self.object(key, parents)? {
+                            PlistValue::String(key) => key,
+                            _ => return Err(invalid("dictionary key")),
+                        };
+                        dictionary.insert(key, self.object(value, parents)?);
+                    }
+                    Ok(PlistValue::Dictionary(dictionary))
+                };
+                parents.pop();
+                value
+            }
+            _ => Err(invalid("object marker")),
+        }
+    }
+
+    /// Reads the length of the object at `offset`, returning the offset of its contents and the
+    /// length. Lengths of 15 and more are stored as an integer object after the marker.
+    fn length(&self, offset: usize, info: usize) -> Result<(usize, usize), ParseError> {
+        if info != 0x0f {
+            return Ok((offset + 1, info));
+        }
+        let marker = self.bytes(offset + 1, 1)?[0];
+        let size_exponent = marker & 0x0f;
+        if marker >> 4 != 0x1 || size_exponent > 3 {
+            return Err(ParseError::new(offset + 1, "invalid length"));
+        }
+        let size = 1 << size_exponent;
+        let length = read_uint(self.bytes(offset + 2, size)?);
+        let length =
+            usize::try_from(length).map_err(|_| ParseError::new(offset + 1, "invalid length"))?;
+        Ok((offset + 2 + size, length))
+    }
+
+    /// Reads the object reference at `offset`, checking that it refers to an object in the offset
+    /// table.
+    fn reference(&self, offset: usize) -> Result<usize, ParseError> {
+        let index = read_uint(self.bytes(offset, self.reference_size)?);
+        if index < (self.offset_table.len() / self.offset_size) as u64 {
+            Ok(index as usize)
+        } else {
+            Err(ParseError::new(offset, "object reference out of range"))
+        }
+    }
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes.
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte))
+}
+
+/// Reads an integer object. Integers of 1, 2 and 4 bytes are unsigned, integers of 8 bytes are
+/// signed and integers of 16 bytes are signed 128-bit values. Returns `None` if the value does not
+/// fit in an `i64`.
+fn read_int(bytes: &[u8]) -> Option<i64> {
+    match bytes.len() {
+        8 => Some(read_uint(bytes) as i64),
+        16 => {
+            let value = (i128::from(read_uint(&bytes[..8]) as i64) << 64)
+                | i128::from(read_uint(&bytes[8..]));
+            i64::try_from(value).ok()
+        }
+        _ => Some(read_uint(bytes) as i64),
+    }
+}
+
+fn read_f64(bytes: &[u8]) -> f64 {
+    f64::from_bits(read_uint(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written by Python's `plistlib`.
+    const EVERY_TYPE: &[u8] = &[
+        0x62, 0x70, 0x6c, 0x69, 0x73, 0x74, 0x30, 0x30, 0xd7, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+        0x07, 0x08, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x55, 0x41, 0x72, 0x72, 0x61, 0x79, 0x53,
+        0x42, 0x69, 0x67, 0x57, 0x42, 0x6f, 0x6f, 0x6c, 0x65, 0x61, 0x6e, 0x54, 0x44, 0x61, 0x74,
+        0x61, 0x54, 0x44, 0x61, 0x74, 0x65, 0x57, 0x49, 0x6e, 0x74, 0x65, 0x67, 0x65, 0x72, 0x54,
+        0x52, 0x65, 0x61, 0x6c, 0xa2, 0x09, 0x0a, 0x51, 0x61, 0x63, 0x00, 0xe9, 0x00, 0x74, 0x00,
+        0xe9, 0x11, 0x01, 0x2c, 0x08, 0x43, 0x00, 0xab, 0xff, 0x33, 0x41, 0xc4, 0xb0, 0x82, 0x40,
+        0x00, 0x00, 0x00, 0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfd, 0x23, 0x3f, 0xe0,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x17, 0x1d, 0x21, 0x29, 0x2e, 0x33, 0x3b, 0x40,
+        0x43, 0x45, 0x4c, 0x4f, 0x50, 0x54, 0x5d, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6f,
+    ];
+
+    #[test]
+    fn parse_every_type() {
+        let mut expected = Dictionary::new();
+        expected.insert(
+            "Array".to_owned(),
+            PlistValue::Array(vec!["a".into(), "été".into()]),
+        );
+        expected.insert("Big".to_owned(), PlistValue::Integer(300));
+        expected.insert("Boolean".to_owned(), PlistValue::Boolean(false));
+        expected.insert("Data".to_owned(), PlistValue::Data(vec![0, 0xab, 0xff]));
+        expected.insert("Date".to_owned(), PlistValue::Date(694_224_000.0));
+        expected.insert("Integer".to_owned(), PlistValue::Integer(-3));
+        expected.insert("Real".to_owned(), PlistValue::Real(0.5));
+        assert_eq!(parse(EVERY_TYPE).unwrap(), PlistValue::Dictionary(expected));
+    }
+
+    /// Builds a binary property list with one-byte offsets and references.
+    fn bplist(objects: &[&[u8]], top_object: u8) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        let mut offsets = Vec::new();
+        for object in objects {
+            offsets.push(data.len() as u8);
+            data.extend_from_slice(object);
+        }
+        let offset_table = data.len() as u64;
+        data.extend_from_slice(&offsets);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        data.extend_from_slice(&(objects.len() as u64).to_be_bytes());
+        data.extend_from_slice(&u64::from(top_object).to_be_bytes());
+        data.extend_from_slice(&offset_table.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn parse_long_collection() {
+        let mut array = vec![0xaf, 0x10, 16];
+        array.extend(std::iter::repeat(1).take(16));
+        let data = bplist(&[&array, &[0x09]], 0);
+        assert_eq!(
+            parse(&data).unwrap(),
+            PlistValue::Array(vec![PlistValue::Boolean(true); 16])
+        );
+    }
+
+    /// Builds a binary property list of `depth` nested arrays, each referring to the next one
+    /// twice, which expands to `2^depth` strings.
+    fn shared_references(depth: u8) -> Vec<u8> {
+        let mut objects = (1..=depth)
+            .map(|next| vec![0xa2, next, next])
+            .collect::<Vec<_>>();
+        objects.push(b"\x5dConfiguration".to_vec());
+        bplist(&objects.iter().map(Vec::as_slice).collect::<Vec<_>>(), 0)
+    }
+
+    #[test]
+    fn parse_shared_references() {
+        let mut expected = PlistValue::String("Configuration".to_owned());
+        for _ in 0..4 {
+            expected = PlistValue::Array(vec![expected.clone(), expected]);
+        }
+        assert_eq!(parse(&shared_references(4)).unwrap(), expected);
+
+        let start = std::time::Instant::now();
+        let error = parse(&shared_references(64)).unwrap_err();
+        assert!(error.to_string().contains("too large"), "{}", error);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let truncated = &EVERY_TYPE[..EVERY_TYPE.len() - 1];
+        let cycle = bplist(&[&[0xa1, 0x00]], 0);
+        let bad_reference = bplist(&[&[0xa1, 0x05]], 0);
+        let bad_key = bplist(&[&[0xd1, 0x01, 0x01], &[0x09]], 0);
+        let too_long = bplist(&[&[0x45, 0x00]], 0);
+        let bad_top = bplist(&[&[0x09]], 1);
+        for data in [
+            &b"bplist00"[..],
+            truncated,
+            &cycle,
+            &bad_reference,
+            &bad_key,
+            &too_long,
+            &bad_top,
+        ] {
+            assert!(parse(data).is_err(), "{:?}", data);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! `Serialize` and `Deserialize` for [`PlistValue`].
+//!
+//! Values are represented by the closest serde data type: dictionaries as maps, arrays as
+//! sequences, and strings, integers, reals and booleans as themselves. Data and dates have no
+//! counterpart in most formats, so they are written as a map with a single [`DATA_KEY`] or
+//! [`DATE_KEY`] entry, holding the data as a hex string or the date as seconds relative to
+//! 2001-01-01 00:00:00 UTC. Reals and dates that are NaN or infinite are written as a map with a
+//! single [`REAL_KEY`] entry holding `NaN`, `inf` or `-inf`, as JSON has no number for them.
+//! Dictionary keys starting with `$` get another `$` prepended, so a dictionary can never be
+//! mistaken for data, a date or a real. This keeps every value intact through formats like JSON.
+
+use super::{Dictionary, PlistValue};
+use serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeMap, Serializer},
+};
+use std::{borrow::Cow, fmt};
+
+/// Key of the map a [`PlistValue::Data`] is serialized as.
+const DATA_KEY: &str = "$data";
+/// Key of the map a [`PlistValue::Date`] is serialized as.
+const DATE_KEY: &str = "$date";
+/// Key of the map a non-finite [`PlistValue::Real`] is serialized as.
+const REAL_KEY: &str = "$real";
+
+impl Serialize for PlistValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PlistValue::Dictionary(dictionary) => {
+                let mut map = serializer.serialize_map(Some(dictionary.len()))?;
+                for (key, value) in dictionary {
+                    map.serialize_entry(&escape_key(key), value)?;
+                }
+                map.end()
+            }
+            PlistValue::Array(array) => array.serialize(serializer),
+            PlistValue::String(string) => serializer.serialize_str(string),
+            PlistValue::Integer(integer) => serializer.serialize_i64(*integer),
+            PlistValue::Real(real) if real.is_finite() => serializer.serialize_f64(*real),
+            PlistValue::Real(real) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(REAL_KEY, &real.to_string())?;
+                map.end()
+            }
+            PlistValue::Boolean(boolean) => serializer.serialize_bool(*boolean),
+            PlistValue::Data(data) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(DATA_KEY, &encode_hex(data))?;
+                map.end()
+            }
+            PlistValue::Date(date) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(DATE_KEY, &PlistValue::Real(*date))?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PlistValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PlistValueVisitor)
+    }
+}
+
+struct PlistValueVisitor;
+
+impl<'de> Visitor<'de> for PlistValueVisitor {
+    type Value = PlistValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a property list value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(PlistValue::Boolean(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(PlistValue::Integer(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        i64::try_from(value)
+            .map(PlistValue::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &"a 64-bit integer"))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(PlistValue::Real(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(PlistValue::String(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(PlistValue::String(value))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(PlistValue::Data(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(PlistValue::Data(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            array.push(value);
+        }
+        Ok(PlistValue::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry::<String, PlistValue>()? {
+            entries.push(entry);
+        }
+        if let [(key, value)] = entries.as_slice() {
+            match (key.as_str(), value) {
+                (DATA_KEY, PlistValue::String(hex)) => {
+                    return decode_hex(hex).map(PlistValue::Data).ok_or_else(|| {
+                        de::Error::invalid_value(de::Unexpected::Str(hex), &"a hex string")
+                    })
+                }
+                (DATE_KEY, PlistValue::Real(date)) => return Ok(PlistValue::Date(*date)),
+                (DATE_KEY, PlistValue::Integer(date)) => return Ok(PlistValue::Date(*date as f64)),
+                (REAL_KEY, PlistValue::String(real)) => {
+                    return real.parse().map(PlistValue::Real).map_err(|_| {
+                        de::Error::invalid_value(de::Unexpected::Str(real), &"NaN, inf or -inf")
+                    })
+                }
+                _ => (),
+            }
+        }
+        let mut dictionary = Dictionary::new();
+        for (key, value) in entries {
+            let expected = match key.as_str() {
+                DATA_KEY => "hex string",
+                DATE_KEY => "number",
+                REAL_KEY => "string",
+                _ => {
+                    dictionary.insert(unescape_key(key), value);
+                    continue;
+                }
+            };
+            return Err(de::Error::custom(format_args!(
+                "`{}` has to be the only key of its map and hold a {}",
+                key, expected
+            )));
+        }
+        Ok(PlistValue::Dictionary(dictionary))
+    }
+}
+
+/// Prepends a `$` to dictionary keys starting with `$`, so they never collide with
+/// [`DATA_KEY`], [`DATE_KEY`] and [`REAL_KEY`].
+fn escape_key(key: &str) -> Cow<'_, str> {
+    if key.starts_with('$') {
+        Cow::Owned(format!("${}", key))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Reverses [`escape_key`]. Keys with a single leading `$` are kept as they are.
+fn unescape_key(key: String) -> String {
+    if key.starts_with("$$") {
+        key[1..].to_owned()
+    } else {
+        key
+    }
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_type() -> PlistValue {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert("String".to_owned(), "value".into());
+        dictionary.insert("Integer".to_owned(), PlistValue::Integer(-3));
+        dictionary.insert("Real".to_owned(), PlistValue::Real(0.5));
+        dictionary.insert("Boolean".to_owned(), PlistValue::Boolean(true));
+        dictionary.insert("Data".to_owned(), PlistValue::Data(vec![0, 0xab, 0xff]));
+        dictionary.insert("Date".to_owned(), PlistValue::Date(694224000.0));
+        dictionary.insert(
+            "Array".to_owned(),
+            PlistValue::Array(vec!["a".into(), PlistValue::Integer(1)]),
+        );
+        dictionary.insert(
+            "Dictionary".to_owned(),
+            PlistValue::Dictionary(Dictionary::new()),
+        );
+        PlistValue::Dictionary(dictionary)
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let value = every_type();
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<PlistValue>(&json).unwrap(), value);
+
+        for real in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            for value in [PlistValue::Real(real), PlistValue::Date(real)] {
+                let json = serde_json::to_string(&value).unwrap();
+                let read = serde_json::from_str::<PlistValue>(&json).unwrap();
+                let same = |a: f64, b: f64| a == b || a.is_nan() && b.is_nan();
+                assert!(
+                    match (&read, &value) {
+                        (PlistValue::Real(a), PlistValue::Real(b))
+                        | (PlistValue::Date(a), PlistValue::Date(b)) => same(*a, *b),
+                        _ => false,
+                    },
+                    "{}",
+                    json
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn json_representation() {
+        let json = serde_json::to_value(every_type()).unwrap();
+        assert_eq!(json["String"], "value");
+        assert_eq!(json["Integer"], -3);
+        assert_eq!(json["Real"], 0.5);
+        assert_eq!(json["Boolean"], true);
+        assert_eq!(json["Data"][DATA_KEY], "00abff");
+        assert_eq!(json["Date"][DATE_KEY], 694224000.0);
+        assert_eq!(json["Array"], serde_json::json!(["a", 1]));
+        assert_eq!(json["Dictionary"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn deserialize_invalid() {
+        assert!(serde_json::from_str::<PlistValue>("null").is_err());
+        assert!(serde_json::from_str::<PlistValue>("18446744073709551615").is_err());
+        assert!(serde_json::from_str::<PlistValue>(r#"{"$data": "abc"}"#).is_err());
+        assert!(serde_json::from_str::<PlistValue>(r#"{"$data": 1}"#).is_err());
+        assert!(serde_json::from_str::<PlistValue>(r#"{"$date": 1, "a": 1}"#).is_err());
+        assert!(serde_json::from_str::<PlistValue>(r#"{"$real": "infinite"}"#).is_err());
+        assert!(serde_json::from_str::<PlistValue>(r#"{"$real": 1}"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<PlistValue>(r#"{"$other": 1}"#).unwrap(),
+            PlistValue::Dictionary(
+                [("$other".to_owned(), PlistValue::Integer(1))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn json_round_trip_dollar_keys() {
+        for key in [DATA_KEY, DATE_KEY, REAL_KEY, "$$data", "$", "$other"] {
+            for value in [
+                "abc".into(),
+                PlistValue::Real(1.0),
+                PlistValue::Data(vec![1]),
+            ] {
+                let dictionary =
+                    PlistValue::Dictionary([(key.to_owned(), value)].into_iter().collect());
+                let json = serde_json::to_string(&dictionary).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<PlistValue>(&json).unwrap(),
+                    dictionary,
+                    "{}",
+                    json
+                );
+            }
+        }
+    }
+}
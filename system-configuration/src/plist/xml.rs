@@ -0,0 +1,520 @@
+//! Reader for the XML property list format.
+//!
+//! Only the subset of XML produced by `CFPropertyListCreateData` and `plutil` is understood:
+//! elements, character data, the predefined and numeric character references, `CDATA`
+//! sections, comments, processing instructions and a document type declaration.
+
+use super::{Dictionary, ParseError, PlistValue, MAX_DEPTH};
+use core_foundation::date::CFAbsoluteTime;
+
+/// Seconds between 1970-01-01 00:00:00 UTC and 2001-01-01 00:00:00 UTC.
+const UNIX_TO_ABSOLUTE_TIME: i64 = 978_307_200;
+
+/// Parses an XML property list.
+pub(super) fn parse(data: &[u8]) -> Result<PlistValue, ParseError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|error| ParseError::new(error.valid_up_to(), "document is not valid UTF-8"))?;
+    let mut parser = Parser {
+        text,
+        position: text.strip_prefix('\u{feff}').map_or(0, |_| 3),
+    };
+    parser.skip_misc()?;
+    let tag = parser.tag()?;
+    let value = if tag.name == "plist" && tag.kind == TagKind::Open {
+        let tag = parser.next_tag()?;
+        let value = parser.value(tag, 0)?;
+        let tag = parser.next_tag()?;
+        parser.expect_close(&tag, "plist")?;
+        value
+    } else {
+        parser.value(tag, 0)?
+    };
+    parser.skip_misc()?;
+    if parser.position < text.len() {
+        return Err(parser.error("unexpected content after the root element"));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    /// `<name>`.
+    Open,
+    /// `</name>`.
+    Close,
+    /// `<name/>`.
+    Empty,
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    /// Offset of the `<` starting the tag.
+    position: usize,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, reason: impl Into<String>) -> ParseError {
+        ParseError::new(self.position, reason)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Moves past `terminator`, failing if it does not occur.
+    fn skip_past(&mut self, terminator: &str) -> Result<(), ParseError> {
+        match self.rest().find(terminator) {
+            Some(index) => {
+                self.position += index + terminator.len();
+                Ok(())
+            }
+            None => Err(self.error(format!("missing `{}`", terminator))),
+        }
+    }
+
+    /// Skips whitespace, comments, processing instructions and document type declarations.
+    fn skip_misc(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!DOCTYPE") {
+                let end = rest.find('>').unwrap_or(rest.len());
+                if rest[..end].contains('[') {
+                    self.skip_past("]")?;
+                }
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the tag at the current position.
+    fn tag(&mut self) -> Result<Tag<'a>, ParseError> {
+        let position = self.position;
+        let rest = self.rest();
+        if !rest.starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        let (closing, name_start) = match rest.starts_with("</") {
+            true => (true, 2),
+            false => (false, 1),
+        };
+        let name_len = rest[name_start..]
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .ok_or_else(|| self.error("unterminated tag"))?;
+        let name = &rest[name_start..name_start + name_len];
+        if name.is_empty() {
+            return Err(self.error("missing element name"));
+        }
+        // Skip the attributes, which are not needed for any plist element.
+        let mut quote = None;
+        for (index, c) in rest[name_start + name_len..].char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '>') => {
+                    let end = name_start + name_len + index;
+                    self.position += end + 1;
+                    let kind = match (closing, rest[..end].ends_with('/')) {
+                        (true, _) => TagKind::Close,
+                        (false, true) => TagKind::Empty,
+                        (false, false) => TagKind::Open,
+                    };
+                    return Ok(Tag {
+                        name,
+                        kind,
+                        position,
+                    });
+                }
+                (None, _) => (),
+            }
+        }
+        Err(self.error("unterminated tag"))
+    }
+
+    /// Skips whitespace and comments, then reads the next tag.
+    fn next_tag(&mut self) -> Result<Tag<'a>, ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else {
+                return self.tag();
+            }
+        }
+    }
+
+    fn expect_close(&self, tag: &Tag<'_>, name: &str) -> Result<(), ParseError> {
+        if tag.kind == TagKind::Close && tag.name == name {
+            Ok(())
+        } else {
+            Err(ParseError::new(
+                tag.position,
+                format!("expected `</{}>`", name),
+            ))
+        }
+    }
+
+    /// Reads the character data of an element up to and including its end tag.
+    fn text(&mut self, tag: &Tag<'_>) -> Result<String, ParseError> {
+        let mut text = String::new();
+        if tag.kind == TagKind::Empty {
+            return Ok(text);
+        }
+        loop {
+            let rest = self.rest();
+            let end = rest
+                .find('<')
+                .ok_or_else(|| self.error("unterminated element"))?;
+            decode_entities(&rest[..end], &mut text).map_err(|offset| {
+                ParseError::new(self.position + offset, "invalid character reference")
+            })?;
+            self.position += end;
+            let rest = self.rest();
+            if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let end = self
+                    .rest()
+                    .find("]]>")
+                    .ok_or_else(|| self.error("unterminated CDATA section"))?;
+                text.push_str(&self.rest()[..end]);
+                self.position += end + "]]>".len();
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else {
+                let close = self.tag()?;
+                self.expect_close(&close, tag.name)?;
+                return Ok(text);
+            }
+        }
+    }
+
+    /// Reads the value starting with `tag`.
+    fn value(&mut self, tag: Tag<'a>, depth: usize) -> Result<PlistValue, ParseError> {
+        if depth > MAX_DEPTH {
+            return Err(ParseError::new(
+                tag.position,
+                "values are nested too deeply",
+            ));
+        }
+        if tag.kind == TagKind::Close {
+            return Err(ParseError::new(tag.position, "unexpected end tag"));
+        }
+        let position = tag.position;
+        let invalid = |what: &str| ParseError::new(position, format!("invalid {}", what));
+        match tag.name {
+            "dict" => {
+                let mut dictionary = Dictionary::new();
+                if tag.kind == TagKind::Empty {
+                    return Ok(PlistValue::Dictionary(dictionary));
+                }
+                loop {
+                    let key_tag = self.next_tag()?;
+                    if key_tag.kind == TagKind::Close && key_tag.name == "dict" {
+                        return Ok(PlistValue::Dictionary(dictionary));
+                    }
+                    if key_tag.name != "key" || key_tag.kind == TagKind::Close {
+                        return Err(ParseError::new(key_tag.position, "expected `<key>`"));
+                    }
+                    let key = self.text(&key_tag)?;
+                    let value_tag = self.next_tag()?;
+                    let value = self.value(value_tag, depth + 1)?;
+                    dictionary.insert(key, value);
+                }
+            }
+            "array" => {
+                let mut array = Vec::new();
+                if tag.kind == TagKind::Empty {
+                    return Ok(PlistValue::Array(array));
+                }
+                loop {
+                    let element_tag = self.next_tag()?;
+                    if element_tag.kind == TagKind::Close && element_tag.name == "array" {
+                        return Ok(PlistValue::Array(array));
+                    }
+                    array.push(self.value(element_tag, depth + 1)?);
+                }
+            }
+            "string" => self.text(&tag).map(PlistValue::String),
+            "integer" => {
+                let text = self.text(&tag)?;
+                parse_integer(text.trim())
+                    .map(PlistValue::Integer)
+                    .ok_or_else(|| invalid("integer"))
+            }
+            "real" => {
+                let text = self.text(&tag)?;
+                text.trim()
+                    .parse()
+                    .map(PlistValue::Real)
+                    .map_err(|_| invalid("real"))
+            }
+            "true" | "false" => {
+                if !self.text(&tag)?.trim().is_empty() {
+                    return Err(invalid("boolean"));
+                }
+                Ok(PlistValue::Boolean(tag.name == "true"))
+            }
+            "data" => {
+                let text = self.text(&tag)?;
+                decode_base64(&text)
+                    .map(PlistValue::Data)
+                    .ok_or_else(|| invalid("data"))
+            }
+            "date" => {
+                let text = self.text(&tag)?;
+                parse_date(text.trim())
+                    .map(PlistValue::Date)
+                    .ok_or_else(|| invalid("date"))
+            }
+            name => Err(ParseError::new(
+                position,
+                format!("unknown element `{}`", name),
+            )),
+        }
+    }
+}
+
+/// Appends `text` to `output`, replacing character references. Returns the offset of an invalid
+/// reference on failure.
+fn decode_entities(text: &str, output: &mut String) -> Result<(), usize> {
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let offset = text.len() - rest.len() + start;
+        let end = rest[start..].find(';').ok_or(offset)?;
+        let reference = &rest[start + 1..start + end];
+        let c = match reference {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = reference.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = reference.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32).ok_or(offset)?
+            }
+        };
+        output.push(c);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(())
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal integer.
+fn parse_integer(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let magnitude = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u64>().ok()?,
+    };
+    if negative {
+        match i64::try_from(magnitude) {
+            Ok(magnitude) => Some(-magnitude),
+            Err(_) => (magnitude == i64::MIN.unsigned_abs()).then_some(i64::MIN),
+        }
+    } else {
+        i64::try_from(magnitude).ok()
+    }
+}
+
+/// Decodes standard base64, ignoring whitespace.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(text.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    let mut padding = 0;
+    for byte in text.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        let sextet = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            _ => return None,
+        };
+        if padding > 0 {
+            return None;
+        }
+        buffer = buffer << 6 | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+    if padding > 2 || bits >= 6 {
+        return None;
+    }
+    Some(data)
+}
+
+/// Parses an ISO 8601 date in UTC, as in `2001-01-01T00:00:00Z`.
+fn parse_date(text: &str) -> Option<CFAbsoluteTime> {
+    let bytes = text.as_bytes();
+    if bytes.len() != 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'Z'
+    {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = &text[range];
+        if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some((seconds - UNIX_TO_ABSOLUTE_TIME) as CFAbsoluteTime)
+}
+
+/// Number of days between 1970-01-01 and the given date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_every_type() {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<!-- comment -->
+	<key>Array</key>
+	<array>
+		<string>a &amp; b &lt;&#x41;&#66;&gt;</string>
+		<string/>
+		<string><![CDATA[<raw>]]></string>
+	</array>
+	<key>Boolean</key>
+	<true/>
+	<key>Data</key>
+	<data>
+	AKv/
+	</data>
+	<key>Date</key>
+	<date>2023-01-01T00:00:00Z</date>
+	<key>Empty</key>
+	<dict/>
+	<key>Integer</key>
+	<integer>-3</integer>
+	<key>Real</key>
+	<real>0.5</real>
+</dict>
+</plist>
+"#;
+        let mut expected = Dictionary::new();
+        expected.insert(
+            "Array".to_owned(),
+            PlistValue::Array(vec!["a & b <AB>".into(), "".into(), "<raw>".into()]),
+        );
+        expected.insert("Boolean".to_owned(), PlistValue::Boolean(true));
+        expected.insert("Data".to_owned(), PlistValue::Data(vec![0, 0xab, 0xff]));
+        expected.insert("Date".to_owned(), PlistValue::Date(694_224_000.0));
+        expected.insert(
+            "Empty".to_owned(),
+            PlistValue::Dictionary(Dictionary::new()),
+        );
+        expected.insert("Integer".to_owned(), PlistValue::Integer(-3));
+        expected.insert("Real".to_owned(), PlistValue::Real(0.5));
+        assert_eq!(parse(xml).unwrap(), PlistValue::Dictionary(expected));
+    }
+
+    #[test]
+    fn parse_without_plist_element() {
+        assert_eq!(parse(b"<integer>0x10</integer>").unwrap(), 16.into());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for xml in [
+            &b"<plist><dict><key>a</key></dict></plist>"[..],
+            b"<plist><dict><string>a</string></dict></plist>",
+            b"<plist><integer>1</integer><integer>2</integer></plist>",
+            b"<plist><integer>x</integer></plist>",
+            b"<plist><data>A</data></plist>",
+            b"<plist><date>2023-13-01T00:00:00Z</date></plist>",
+            b"<plist><string>&bogus;</string></plist>",
+            b"<plist><unknown/></plist>",
+            b"<plist><string>a</plist>",
+            b"\xff",
+        ] {
+            assert!(parse(xml).is_err(), "{:?}", String::from_utf8_lossy(xml));
+        }
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64(""), Some(vec![]));
+        assert_eq!(decode_base64("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode_base64("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(decode_base64("Zm9v\nYmFy"), Some(b"foobar".to_vec()));
+        assert_eq!(decode_base64("Zg=a"), None);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("2001-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_date("1970-01-01T00:00:00Z"), Some(-978_307_200.0));
+        assert_eq!(parse_date("2001-01-01 00:00:00Z"), None);
+    }
+}
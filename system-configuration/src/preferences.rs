@@ -10,16 +10,37 @@
 //!
 //! See the examples directory for examples how to use this module.
 //!
+//! [`PreferencesFile`] reads preference files without the framework, for example on another
+//! platform. [`PreferencesBackend`] abstracts over preferences sessions, so code using it can be
+//! tested against [`MemoryPreferences`] instead of the system preferences.
+//!
 //! [`SCPreferences`]: https://developer.apple.com/documentation/systemconfiguration/scpreferences-ft8
 
+mod backend;
+mod file;
+mod lock;
+mod memory;
+mod notification;
+mod path;
+mod signature;
+
+pub use backend::*;
+pub use file::*;
+pub use lock::*;
+pub use memory::*;
+pub use notification::*;
+pub use path::{ParsePathError, PrefsPath};
+pub use signature::*;
+
+use crate::error::{to_result, SCError};
 use crate::sys::preferences::{SCPreferencesCreate, SCPreferencesGetTypeID, SCPreferencesRef};
 use core_foundation::array::CFArray;
 use core_foundation::base::{Boolean, CFAllocator, CFType, TCFType};
-use core_foundation::propertylist::CFPropertyList;
+use core_foundation::propertylist::{CFPropertyList, CFPropertyListSubClass};
 use core_foundation::string::CFString;
 use std::ptr;
 use core_foundation::dictionary::CFDictionary;
-use sys::preferences::{AuthorizationRef, SCPreferencesApplyChanges, SCPreferencesCommitChanges, SCPreferencesCopyKeyList, SCPreferencesCreateWithAuthorization, SCPreferencesGetValue, SCPreferencesLock, SCPreferencesSynchronize, SCPreferencesUnlock};
+use sys::preferences::{AuthorizationRef, SCPreferencesAddValue, SCPreferencesApplyChanges, SCPreferencesCommitChanges, SCPreferencesCopyKeyList, SCPreferencesCreateWithAuthorization, SCPreferencesGetValue, SCPreferencesLock, SCPreferencesRemoveValue, SCPreferencesSetValue, SCPreferencesSynchronize, SCPreferencesUnlock};
 use sys::preferences_path::{SCPreferencesPathCreateUniqueChild, SCPreferencesPathGetLink, SCPreferencesPathGetValue, SCPreferencesPathRemoveValue, SCPreferencesPathSetLink, SCPreferencesPathSetValue};
 #[cfg(feature = "private")]
 use sys::preferences_private::kSCPreferencesUseEntitlementAuthorization;
@@ -171,40 +192,105 @@ impl SCPreferences {
         }
     }
 
+    /// Adds the value of the specified preference key.
+    ///
+    /// Fails with [`SCError::KeyExists`] if the key already has a value. The change is local to
+    /// this session until it is committed with [`commit_changes`](Self::commit_changes).
+    ///
+    /// See [`SCPreferencesAddValue`] for details.
+    ///
+    /// [`SCPreferencesAddValue`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesaddvalue(_:_:_:)?language=objc
+    pub fn add<S: Into<CFString>, V: CFPropertyListSubClass>(
+        &mut self,
+        key: S,
+        value: V,
+    ) -> Result<(), SCError> {
+        self.add_raw(key, &value.into_CFPropertyList())
+    }
+
+    /// Adds the value of the specified preference key. See [`add`](Self::add) for details.
+    pub fn add_raw<S: Into<CFString>>(
+        &mut self,
+        key: S,
+        value: &CFPropertyList,
+    ) -> Result<(), SCError> {
+        let cf_key = key.into();
+        to_result(unsafe {
+            SCPreferencesAddValue(self.0, cf_key.as_concrete_TypeRef(), value.as_concrete_TypeRef())
+        })
+    }
+
+    /// Sets the value of the specified preference key. Overwrites existing values.
+    ///
+    /// The change is local to this session until it is committed with
+    /// [`commit_changes`](Self::commit_changes).
+    ///
+    /// See [`SCPreferencesSetValue`] for details.
+    ///
+    /// [`SCPreferencesSetValue`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencessetvalue(_:_:_:)?language=objc
+    pub fn set<S: Into<CFString>, V: CFPropertyListSubClass>(
+        &mut self,
+        key: S,
+        value: V,
+    ) -> Result<(), SCError> {
+        self.set_raw(key, &value.into_CFPropertyList())
+    }
+
+    /// Sets the value of the specified preference key. See [`set`](Self::set) for details.
+    pub fn set_raw<S: Into<CFString>>(
+        &mut self,
+        key: S,
+        value: &CFPropertyList,
+    ) -> Result<(), SCError> {
+        let cf_key = key.into();
+        to_result(unsafe {
+            SCPreferencesSetValue(self.0, cf_key.as_concrete_TypeRef(), value.as_concrete_TypeRef())
+        })
+    }
+
+    /// Removes the value of the specified preference key.
+    ///
+    /// Fails with [`SCError::NoKey`] if the key has no value.
+    ///
+    /// See [`SCPreferencesRemoveValue`] for details.
+    ///
+    /// [`SCPreferencesRemoveValue`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesremovevalue(_:_:)?language=objc
+    pub fn remove<S: Into<CFString>>(&mut self, key: S) -> Result<(), SCError> {
+        let cf_key = key.into();
+        to_result(unsafe { SCPreferencesRemoveValue(self.0, cf_key.as_concrete_TypeRef()) })
+    }
+
     /// Obtains exclusive access to the configuration preferences. The `wait` flag indicates whether
     /// the calling process should block, waiting for another process to complete its update operation
     /// and release its lock.
     ///
-    /// Returns: `true` if the lock was obtained; `false` if an error occurred.
-    pub fn lock(&mut self, wait: bool) -> bool {
-        (unsafe { SCPreferencesLock(self.0, wait as Boolean) }) != 0
+    /// Fails with [`SCError::PrefsBusy`] if `wait` is `false` and another process holds the lock,
+    /// and with [`SCError::Locked`] if this session holds it already.
+    pub fn lock(&mut self, wait: bool) -> Result<(), SCError> {
+        to_result(unsafe { SCPreferencesLock(self.0, wait as Boolean) })
     }
 
     /// Releases exclusive access to the configuration preferences.
     ///
-    /// Returns: `true` if the lock was obtained; `false` if an error occurred.
-    pub fn unlock(&mut self) -> bool {
-        (unsafe { SCPreferencesUnlock(self.0) }) != 0
+    /// Fails with [`SCError::NeedLock`] if the lock is not held.
+    pub fn unlock(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCPreferencesUnlock(self.0) })
     }
 
     /// Commits changes made to the configuration preferences to persistent storage. Implicit calls
     /// to the [`lock`](Self::lock) and [`unlock`](Self::lock) functions are made if exclusive
     /// access has not already been established.
     ///
-    /// Returns: `true` if the lock was obtained; `false` if an error occurred.
-    ///
     /// Note: this function commits changes to persistent storage; to apply the changes to the
     ///       running system, use the [`apply_changes`](Self::apply_changes) function.
-    pub fn commit_changes(&mut self) -> bool {
-        (unsafe { SCPreferencesCommitChanges(self.0) }) != 0
+    pub fn commit_changes(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCPreferencesCommitChanges(self.0) })
     }
 
     /// Requests that the currently stored configuration preferences be applied to the active
     /// configuration.
-    ///
-    /// Returns: `true` if the lock was obtained; `false` if an error occurred.
-    pub fn apply_changes(&mut self) -> bool {
-        (unsafe { SCPreferencesApplyChanges(self.0) }) != 0
+    pub fn apply_changes(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCPreferencesApplyChanges(self.0) })
     }
 
     /// Synchronizes accessed preferences with committed changes. Any preference values that were
@@ -274,9 +360,9 @@ impl SCPreferences {
     ///
     /// [`SCPreferencesPathGetValue`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencespathgetvalue(_:_:)?language=objc
     pub fn path_get_value(&self, path: impl Into<CFString>) -> Option<CFDictionary<CFString, CFType>> {
-        let path_ref = path.into().as_concrete_TypeRef();
+        let path = path.into();
         unsafe {
-            let dictionary_ref = SCPreferencesPathGetValue(self.0, path_ref);
+            let dictionary_ref = SCPreferencesPathGetValue(self.0, path.as_concrete_TypeRef());
             if !dictionary_ref.is_null() {
                 Some(CFDictionary::wrap_under_get_rule(dictionary_ref))
             } else {
@@ -292,9 +378,9 @@ impl SCPreferences {
     ///
     /// [`SCPreferencesPathGetLink`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencespathgetlink(_:_:)?language=objc
     pub fn path_get_link(&self, path: impl Into<CFString>) -> Option<CFString> {
-        let path_ref = path.into().as_concrete_TypeRef();
+        let path = path.into();
         unsafe {
-            let string_ref = SCPreferencesPathGetLink(self.0, path_ref);
+            let string_ref = SCPreferencesPathGetLink(self.0, path.as_concrete_TypeRef());
             if !string_ref.is_null() {
                 Some(CFString::wrap_under_get_rule(string_ref))
             } else {
@@ -311,9 +397,9 @@ impl SCPreferences {
     ///
     /// [`SCPreferencesPathCreateUniqueChild`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencespathcreateuniquechild(_:_:)?language=objc
     pub fn path_create_unique_child(&mut self, prefix: impl Into<CFString>) -> Option<CFString> {
-        let prefix_ref = prefix.into().as_concrete_TypeRef();
+        let prefix = prefix.into();
         unsafe {
-            let string_ref = SCPreferencesPathCreateUniqueChild(self.0, prefix_ref);
+            let string_ref = SCPreferencesPathCreateUniqueChild(self.0, prefix.as_concrete_TypeRef());
             if !string_ref.is_null() {
                 Some(CFString::wrap_under_create_rule(string_ref))
             } else {
@@ -323,28 +409,38 @@ impl SCPreferences {
     }
 
     /// Associates the specified dictionary with the specified path.
-    ///
-    /// Returns: `true` if successful; `false` otherwise.
-    pub fn path_set_value(&mut self, path: impl Into<CFString>, value: &CFDictionary<CFString, CFType>) -> bool {
-        let path_ref = path.into().as_concrete_TypeRef();
-        (unsafe { SCPreferencesPathSetValue(self.0, path_ref, value.as_concrete_TypeRef()) }) != 0
+    pub fn path_set_value(
+        &mut self,
+        path: impl Into<CFString>,
+        value: &CFDictionary<CFString, CFType>,
+    ) -> Result<(), SCError> {
+        let path = path.into();
+        to_result(unsafe {
+            SCPreferencesPathSetValue(
+                self.0,
+                path.as_concrete_TypeRef(),
+                value.as_concrete_TypeRef(),
+            )
+        })
     }
 
     /// Removes the data associated with the specified path.
-    ///
-    /// Returns: `true` if successful; `false` otherwise.
-    pub fn path_remove_value(&mut self, path: impl Into<CFString>) -> bool {
-        let path_ref = path.into().as_concrete_TypeRef();
-        (unsafe { SCPreferencesPathRemoveValue(self.0, path_ref) }) != 0
+    pub fn path_remove_value(&mut self, path: impl Into<CFString>) -> Result<(), SCError> {
+        let path = path.into();
+        to_result(unsafe { SCPreferencesPathRemoveValue(self.0, path.as_concrete_TypeRef()) })
     }
 
     /// Associates a link to a second dictionary at the specified path.
-    ///
-    /// Returns: `true` if successful; `false` otherwise.
-    pub fn path_set_link(&mut self, path: impl Into<CFString>, link: impl Into<CFString>,) -> bool {
-        let path_ref = path.into().as_concrete_TypeRef();
-        let link_ref = link.into().as_concrete_TypeRef();
-        (unsafe { SCPreferencesPathSetLink(self.0, path_ref, link_ref) }) != 0
+    pub fn path_set_link(
+        &mut self,
+        path: impl Into<CFString>,
+        link: impl Into<CFString>,
+    ) -> Result<(), SCError> {
+        let path = path.into();
+        let link = link.into();
+        to_result(unsafe {
+            SCPreferencesPathSetLink(self.0, path.as_concrete_TypeRef(), link.as_concrete_TypeRef())
+        })
     }
 }
 
@@ -357,4 +453,23 @@ mod tests {
         let preferences = SCPreferences::default(&CFString::new("test"));
         assert_eq!(preferences.retain_count(), 1);
     }
+
+    #[test]
+    fn add_set_remove() {
+        use core_foundation::number::CFNumber;
+
+        let mut preferences = SCPreferences::default(&CFString::new("test"));
+        let key = "system-configuration-rs test";
+        preferences.add(key, CFString::new("a")).unwrap();
+        assert_eq!(
+            preferences.add(key, CFString::new("b")),
+            Err(SCError::KeyExists)
+        );
+        preferences.set(key, CFNumber::from(1)).unwrap();
+        let value = preferences.get(key).and_then(|value| value.downcast_into::<CFNumber>());
+        assert_eq!(value.and_then(|value| value.to_i32()), Some(1));
+        preferences.remove(key).unwrap();
+        assert_eq!(preferences.remove(key), Err(SCError::NoKey));
+        assert!(preferences.get(key).is_none());
+    }
 }
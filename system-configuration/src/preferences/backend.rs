@@ -0,0 +1,191 @@
+use super::{PreferencesSignature, SCPreferences};
+use crate::{
+    error::SCError,
+    plist::{dictionary_from_cf, dictionary_to_cf, Dictionary, PlistValue},
+};
+
+/// The operations of a preferences session, in terms of pure Rust values.
+///
+/// Implemented by [`SCPreferences`] and by [`MemoryPreferences`], so code written against this
+/// trait can be tested without the SystemConfiguration framework. The methods behave like the
+/// `SCPreferences` functions of the same name, including the [`SCError`] they fail with.
+///
+/// [`MemoryPreferences`]: super::MemoryPreferences
+pub trait PreferencesBackend {
+    /// Returns the top-level keys of the preferences.
+    fn get_keys(&self) -> Vec<String>;
+
+    /// Returns the value of a top-level key.
+    fn get(&self, key: &str) -> Option<PlistValue>;
+
+    /// Adds a top-level value. Fails with [`SCError::KeyExists`] if the key already has a value.
+    fn add(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError>;
+
+    /// Sets a top-level value, replacing the current value.
+    fn set(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError>;
+
+    /// Removes a top-level value. Fails with [`SCError::NoKey`] if the key has no value.
+    fn remove(&mut self, key: &str) -> Result<(), SCError>;
+
+    /// Returns the dictionary at `path`, following links.
+    fn path_get_value(&self, path: &str) -> Option<Dictionary>;
+
+    /// Returns the link stored at `path`, or `None` if the path is not a link or does not exist.
+    fn path_get_link(&self, path: &str) -> Option<String>;
+
+    /// Creates an empty dictionary with a unique name below `prefix`, returning its path.
+    fn path_create_unique_child(&mut self, prefix: &str) -> Result<String, SCError>;
+
+    /// Stores a dictionary at `path`, creating the dictionaries leading up to it.
+    fn path_set_value(&mut self, path: &str, value: &Dictionary) -> Result<(), SCError>;
+
+    /// Stores a link to the existing dictionary at `link` at `path`.
+    fn path_set_link(&mut self, path: &str, link: &str) -> Result<(), SCError>;
+
+    /// Removes the value at `path`.
+    fn path_remove_value(&mut self, path: &str) -> Result<(), SCError>;
+
+    /// Obtains exclusive access to the preferences, waiting for other sessions to release their
+    /// lock if `wait` is `true`. Fails with [`SCError::PrefsBusy`] if another session holds the lock
+    /// and `wait` is `false`, with [`SCError::Locked`] if this session holds it already, and with
+    /// [`SCError::Stale`] if the preferences were committed by another session since this session
+    /// read them.
+    fn lock(&mut self, wait: bool) -> Result<(), SCError>;
+
+    /// Releases exclusive access to the preferences. Fails with [`SCError::NeedLock`] if the lock
+    /// is not held.
+    fn unlock(&mut self) -> Result<(), SCError>;
+
+    /// Writes the changes made in this session to the stored preferences, locking them for the
+    /// duration of the call if the lock is not held.
+    fn commit_changes(&mut self) -> Result<(), SCError>;
+
+    /// Applies the stored preferences to the running system.
+    fn apply_changes(&mut self) -> Result<(), SCError>;
+
+    /// Discards the uncommitted changes of this session. The stored preferences are read again on
+    /// the next access.
+    fn synchronize(&mut self);
+
+    /// Returns the signature of the version of the stored preferences this session read. Or `None`
+    /// if the preferences cannot be accessed.
+    fn signature(&self) -> Option<PreferencesSignature>;
+
+    /// Returns `true` if the stored preferences are no longer the version identified by
+    /// `signature`, because this or any other session committed since the signature was taken.
+    /// Unlike [`signature`](Self::signature), this sees commits of other sessions without
+    /// synchronizing this session. Fails with [`SCError::PrefsBusy`] instead of waiting while
+    /// another session holds the lock.
+    fn has_changed_since(&mut self, signature: &PreferencesSignature) -> Result<bool, SCError>;
+
+    /// Commits the changes made in this session, provided the session read the version of the
+    /// preferences identified by `signature` and no other session committed since. Fails with
+    /// [`SCError::Stale`] otherwise, in which case nothing is written.
+    ///
+    /// The check is made with the lock held, taking the lock for the duration of the call if this
+    /// session does not hold it already, so no other session can commit in between.
+    fn commit_changes_if_unchanged(
+        &mut self,
+        signature: &PreferencesSignature,
+    ) -> Result<(), SCError> {
+        // Locking fails with `Stale` if another session committed since this session read the
+        // preferences, so with the lock held the signature of this session is the stored one.
+        let locked_here = match self.lock(true) {
+            Ok(()) => true,
+            // This session holds the lock already.
+            Err(SCError::Locked) => false,
+            Err(error) => return Err(error),
+        };
+        let result = if self.signature().as_ref() != Some(signature) {
+            Err(SCError::Stale)
+        } else {
+            self.commit_changes()
+        };
+        if locked_here {
+            let unlocked = self.unlock();
+            result.and(unlocked)
+        } else {
+            result
+        }
+    }
+}
+
+impl PreferencesBackend for SCPreferences {
+    fn get_keys(&self) -> Vec<String> {
+        SCPreferences::get_keys(self)
+            .iter()
+            .map(|key| key.to_string())
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> Option<PlistValue> {
+        SCPreferences::get(self, key).and_then(|value| PlistValue::from_property_list(&value))
+    }
+
+    fn add(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        SCPreferences::add_raw(self, key, &value.to_property_list())
+    }
+
+    fn set(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        SCPreferences::set_raw(self, key, &value.to_property_list())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), SCError> {
+        SCPreferences::remove(self, key)
+    }
+
+    fn path_get_value(&self, path: &str) -> Option<Dictionary> {
+        SCPreferences::path_get_value(self, path)
+            .and_then(|dictionary| dictionary_from_cf(&dictionary))
+    }
+
+    fn path_get_link(&self, path: &str) -> Option<String> {
+        SCPreferences::path_get_link(self, path).map(|link| link.to_string())
+    }
+
+    fn path_create_unique_child(&mut self, prefix: &str) -> Result<String, SCError> {
+        SCPreferences::path_create_unique_child(self, prefix)
+            .map(|path| path.to_string())
+            .ok_or_else(SCError::last)
+    }
+
+    fn path_set_value(&mut self, path: &str, value: &Dictionary) -> Result<(), SCError> {
+        SCPreferences::path_set_value(self, path, &dictionary_to_cf(value))
+    }
+
+    fn path_set_link(&mut self, path: &str, link: &str) -> Result<(), SCError> {
+        SCPreferences::path_set_link(self, path, link)
+    }
+
+    fn path_remove_value(&mut self, path: &str) -> Result<(), SCError> {
+        SCPreferences::path_remove_value(self, path)
+    }
+
+    fn lock(&mut self, wait: bool) -> Result<(), SCError> {
+        SCPreferences::lock(self, wait)
+    }
+
+    fn unlock(&mut self) -> Result<(), SCError> {
+        SCPreferences::unlock(self)
+    }
+
+    fn commit_changes(&mut self) -> Result<(), SCError> {
+        SCPreferences::commit_changes(self)
+    }
+
+    fn apply_changes(&mut self) -> Result<(), SCError> {
+        SCPreferences::apply_changes(self)
+    }
+
+    fn synchronize(&mut self) {
+        SCPreferences::synchronize(self)
+    }
+
+    fn signature(&self) -> Option<PreferencesSignature> {
+        SCPreferences::signature(self)
+    }
+
+    fn has_changed_since(&mut self, signature: &PreferencesSignature) -> Result<bool, SCError> {
+        SCPreferences::has_changed_since(self, signature)
+    }
+}
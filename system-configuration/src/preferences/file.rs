@@ -0,0 +1,583 @@
+use super::path::{
+    path_get_link, path_get_value, GLOBAL, NETWORK, NETWORK_SERVICES, SERVICE, SETS, SYSTEM,
+};
+use crate::{
+    apply::{InterfaceSpec, ProtocolSpec, ServiceSnapshot, SetSnapshot, Snapshot},
+    network_configuration::{SCNetworkInterfaceType, SCNetworkProtocolType},
+    plist::{Dictionary, ParseError, PlistValue},
+};
+use std::{fs, io, path::Path};
+
+/// `kSCPrefCurrentSet`.
+const CURRENT_SET: &str = "CurrentSet";
+/// `kSCResvInactive`.
+const INACTIVE: &str = "__INACTIVE__";
+/// `kSCEntNetIPv4`.
+const IPV4: &str = "IPv4";
+/// `kSCEntNetInterface`.
+const INTERFACE: &str = "Interface";
+/// `kSCPropNetServiceOrder`.
+const SERVICE_ORDER: &str = "ServiceOrder";
+/// `kSCPropUserDefinedName`.
+const USER_DEFINED_NAME: &str = "UserDefinedName";
+/// `kSCPropNetInterfaceDeviceName`.
+const DEVICE_NAME: &str = "DeviceName";
+/// `kSCPropNetInterfaceHardware`.
+const HARDWARE: &str = "Hardware";
+/// `kSCPropNetInterfaceType`.
+const TYPE: &str = "Type";
+/// `kSCPropNetInterfaceSubType`.
+const SUB_TYPE: &str = "SubType";
+/// `kSCValNetInterfaceSubTypePPPoE`.
+const SUB_TYPE_PPPOE: &str = "PPPoE";
+/// `kSCValNetInterfaceSubTypePPPSerial`.
+const SUB_TYPE_PPP_SERIAL: &str = "PPPSerial";
+/// `kSCValNetInterfaceSubTypeL2TP`.
+const SUB_TYPE_L2TP: &str = "L2TP";
+/// `kSCValNetInterfaceSubTypePPTP`.
+const SUB_TYPE_PPTP: &str = "PPTP";
+/// `kSCEntNetAirPort`, the hardware of Wi-Fi interfaces.
+const HARDWARE_AIRPORT: &str = "AirPort";
+/// The entities of a service that configure its interface rather than a protocol,
+/// `kSCEntNetInterface` and the `kSCEntNet*` entities of each interface type and extended
+/// configuration.
+const INTERFACE_ENTITIES: [&str; 14] = [
+    INTERFACE,
+    HARDWARE_AIRPORT,
+    "6to4",
+    "EAPOL",
+    "Ethernet",
+    "FireWire",
+    "IPSec",
+    "L2TP",
+    "Modem",
+    "PPP",
+    "PPPoE",
+    "PPPSerial",
+    "PPTP",
+    "VPN",
+];
+
+/// The contents of a preferences file, read without the SystemConfiguration framework.
+///
+/// This reads files such as `/Library/Preferences/SystemConfiguration/preferences.plist` that
+/// were copied from another machine, on any platform. Paths are looked up like
+/// [`SCPreferences::path_get_value`] does, following the links between the sets and the services
+/// they contain, and [`snapshot`] returns the same view of the network configuration as
+/// [`Snapshot::capture`].
+///
+/// [`SCPreferences::path_get_value`]: crate::preferences::SCPreferences::path_get_value
+/// [`snapshot`]: #method.snapshot
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreferencesFile {
+    values: Dictionary,
+}
+
+impl PreferencesFile {
+    /// Reads and parses the preferences file at `path`. Parse errors are reported with
+    /// [`io::ErrorKind::InvalidData`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Self::from_slice(&data).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Parses the contents of a preferences file in the XML or binary property list format.
+    pub fn from_slice(data: &[u8]) -> Result<Self, ParseError> {
+        match PlistValue::from_slice(data)? {
+            PlistValue::Dictionary(values) => Ok(PreferencesFile { values }),
+            _ => Err(ParseError::new(0, "root is not a dictionary")),
+        }
+    }
+
+    /// Returns the top-level values of the preferences.
+    pub fn values(&self) -> &Dictionary {
+        &self.values
+    }
+
+    /// Returns the top-level value for `key`, as [`SCPreferences::get`] does.
+    ///
+    /// [`SCPreferences::get`]: crate::preferences::SCPreferences::get
+    pub fn get(&self, key: &str) -> Option<&PlistValue> {
+        self.values.get(key)
+    }
+
+    /// Returns the dictionary at `path`, following links. See
+    /// [`SCPreferences::path_get_value`].
+    ///
+    /// [`SCPreferences::path_get_value`]: crate::preferences::SCPreferences::path_get_value
+    pub fn path_get_value(&self, path: &str) -> Option<&Dictionary> {
+        path_get_value(&self.values, path)
+    }
+
+    /// Returns the link stored at `path`, or `None` if the path is not a link or does not exist.
+    /// See [`SCPreferences::path_get_link`].
+    ///
+    /// [`SCPreferences::path_get_link`]: crate::preferences::SCPreferences::path_get_link
+    pub fn path_get_link(&self, path: &str) -> Option<&str> {
+        path_get_link(&self.values, path)
+    }
+
+    /// Returns the identifier of the current set, from the `kSCPrefCurrentSet` path.
+    pub fn current_set(&self) -> Option<&str> {
+        let path = self.get(CURRENT_SET)?.as_str()?;
+        path.strip_prefix('/')?
+            .strip_prefix(SETS)?
+            .strip_prefix('/')
+            .filter(|id| !id.is_empty() && !id.contains('/'))
+    }
+
+    /// Returns the `kSCPrefSystem` dictionary, holding settings such as the computer name.
+    pub fn system(&self) -> Option<&Dictionary> {
+        self.get(SYSTEM)?.as_dictionary()
+    }
+
+    /// Returns the `kSCPrefNetworkServices` dictionary, holding every service by identifier.
+    pub fn network_services(&self) -> Option<&Dictionary> {
+        self.get(NETWORK_SERVICES)?.as_dictionary()
+    }
+
+    /// Returns the network sets and services of the preferences. Services are listed in service
+    /// order, followed by services missing from the service order. The interfaces of the machine
+    /// are not part of the file, so [`Snapshot::interfaces`] is empty.
+    pub fn snapshot(&self) -> Snapshot {
+        let sets = self
+            .get(SETS)
+            .and_then(PlistValue::as_dictionary)
+            .map(|sets| sets.keys().map(|id| self.set_snapshot(id)).collect())
+            .unwrap_or_default();
+        Snapshot {
+            sets,
+            current_set: self.current_set().map(str::to_owned),
+            ..Default::default()
+        }
+    }
+
+    fn set_snapshot(&self, id: &str) -> SetSnapshot {
+        let set_path = format!("/{}/{}", SETS, id);
+        let service_path = format!("{}/{}/{}", set_path, NETWORK, SERVICE);
+        let mut services = self
+            .path_get_value(&service_path)
+            .map(|services| {
+                services
+                    .keys()
+                    .filter_map(|service_id| {
+                        let service =
+                            self.path_get_value(&format!("{}/{}", service_path, service_id))?;
+                        Some(service_snapshot(service_id, service))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let order = self
+            .path_get_value(&format!("{}/{}/{}/{}", set_path, NETWORK, GLOBAL, IPV4))
+            .and_then(|ipv4| ipv4.get(SERVICE_ORDER))
+            .and_then(PlistValue::as_array)
+            .map(|order| {
+                order
+                    .iter()
+                    .filter_map(PlistValue::as_str)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        services.sort_by_key(|service| {
+            order
+                .iter()
+                .position(|id| *id == service.id)
+                .unwrap_or(order.len())
+        });
+        SetSnapshot {
+            id: id.to_owned(),
+            name: self
+                .path_get_value(&set_path)
+                .and_then(|set| string(set, USER_DEFINED_NAME)),
+            services,
+        }
+    }
+}
+
+impl From<Dictionary> for PreferencesFile {
+    fn from(values: Dictionary) -> Self {
+        PreferencesFile { values }
+    }
+}
+
+fn service_snapshot(id: &str, service: &Dictionary) -> ServiceSnapshot {
+    let interface = service.get(INTERFACE).and_then(PlistValue::as_dictionary);
+    ServiceSnapshot {
+        id: id.to_owned(),
+        name: string(service, USER_DEFINED_NAME)
+            .or_else(|| interface.and_then(|interface| string(interface, USER_DEFINED_NAME))),
+        enabled: !service.contains_key(INACTIVE),
+        interface: interface.and_then(interface_spec),
+        protocols: service
+            .iter()
+            .filter(|(key, _)| !INTERFACE_ENTITIES.contains(&key.as_str()))
+            .filter_map(|(key, value)| {
+                let protocol_type = SCNetworkProtocolType::from(key.as_str());
+                let mut configuration = value.as_dictionary()?.clone();
+                let enabled = configuration.remove(INACTIVE).is_none();
+                Some(ProtocolSpec {
+                    protocol_type,
+                    enabled,
+                    configuration: Some(configuration),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Describes the interface stored in the `kSCEntNetInterface` entity of a service, with the
+/// interfaces it is layered on.
+fn interface_spec(entity: &Dictionary) -> Option<InterfaceSpec> {
+    let interface_type = entity.get(TYPE)?.as_str()?;
+    let hardware = |default: SCNetworkInterfaceType| InterfaceSpec {
+        interface_type: match entity.get(HARDWARE).and_then(PlistValue::as_str) {
+            Some(HARDWARE_AIRPORT) => SCNetworkInterfaceType::IEEE80211,
+            Some(hardware) => SCNetworkInterfaceType::from(hardware),
+            None => default,
+        },
+        bsd_name: string(entity, DEVICE_NAME),
+        underlying: None,
+    };
+    let over_ipv4 = |interface_type: SCNetworkInterfaceType| {
+        InterfaceSpec::new(interface_type)
+            .layered_on(InterfaceSpec::new(SCNetworkInterfaceType::IPv4))
+    };
+    let interface_type = SCNetworkInterfaceType::from(interface_type);
+    Some(match interface_type {
+        SCNetworkInterfaceType::PPP => {
+            let underlying = match entity.get(SUB_TYPE).and_then(PlistValue::as_str) {
+                Some(SUB_TYPE_L2TP) => Some(over_ipv4(SCNetworkInterfaceType::L2TP)),
+                Some(SUB_TYPE_PPTP) => Some(over_ipv4(SCNetworkInterfaceType::PPTP)),
+                Some(SUB_TYPE_PPPOE) => Some(hardware(SCNetworkInterfaceType::Ethernet)),
+                Some(SUB_TYPE_PPP_SERIAL) => Some(hardware(SCNetworkInterfaceType::Modem)),
+                _ => None,
+            };
+            InterfaceSpec {
+                interface_type,
+                bsd_name: None,
+                underlying: underlying.map(Box::new),
+            }
+        }
+        SCNetworkInterfaceType::IPSec
+        | SCNetworkInterfaceType::SixToFour
+        | SCNetworkInterfaceType::VPN => over_ipv4(interface_type),
+        _ => hardware(interface_type),
+    })
+}
+
+fn string(dictionary: &Dictionary, key: &str) -> Option<String> {
+    dictionary.get(key)?.as_str().map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::path::LINK, *};
+
+    const PREFERENCES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CurrentSet</key>
+	<string>/Sets/SET-1</string>
+	<key>NetworkServices</key>
+	<dict>
+		<key>SVC-ETHERNET</key>
+		<dict>
+			<key>AppleTalk</key>
+			<dict>
+				<key>ConfigMethod</key>
+				<string>Node</string>
+			</dict>
+			<key>DNS</key>
+			<dict>
+				<key>ServerAddresses</key>
+				<array>
+					<string>10.0.0.1</string>
+				</array>
+			</dict>
+			<key>IPv4</key>
+			<dict>
+				<key>ConfigMethod</key>
+				<string>DHCP</string>
+			</dict>
+			<key>Ethernet</key>
+			<dict>
+				<key>MediaSubType</key>
+				<string>autoselect</string>
+			</dict>
+			<key>IPv6</key>
+			<dict>
+				<key>ConfigMethod</key>
+				<string>Automatic</string>
+				<key>__INACTIVE__</key>
+				<integer>1</integer>
+			</dict>
+			<key>Interface</key>
+			<dict>
+				<key>DeviceName</key>
+				<string>en0</string>
+				<key>Hardware</key>
+				<string>Ethernet</string>
+				<key>Type</key>
+				<string>Ethernet</string>
+				<key>UserDefinedName</key>
+				<string>Ethernet</string>
+			</dict>
+			<key>UserDefinedName</key>
+			<string>Ethernet</string>
+		</dict>
+		<key>SVC-PPPOE</key>
+		<dict>
+			<key>Interface</key>
+			<dict>
+				<key>DeviceName</key>
+				<string>en0</string>
+				<key>Hardware</key>
+				<string>Ethernet</string>
+				<key>SubType</key>
+				<string>PPPoE</string>
+				<key>Type</key>
+				<string>PPP</string>
+			</dict>
+			<key>PPP</key>
+			<dict/>
+			<key>UserDefinedName</key>
+			<string>DSL</string>
+			<key>__INACTIVE__</key>
+			<integer>1</integer>
+		</dict>
+		<key>SVC-WIFI</key>
+		<dict>
+			<key>Interface</key>
+			<dict>
+				<key>DeviceName</key>
+				<string>en1</string>
+				<key>Hardware</key>
+				<string>AirPort</string>
+				<key>Type</key>
+				<string>Ethernet</string>
+				<key>UserDefinedName</key>
+				<string>Wi-Fi</string>
+			</dict>
+		</dict>
+	</dict>
+	<key>Sets</key>
+	<dict>
+		<key>SET-1</key>
+		<dict>
+			<key>Network</key>
+			<dict>
+				<key>Global</key>
+				<dict>
+					<key>IPv4</key>
+					<dict>
+						<key>ServiceOrder</key>
+						<array>
+							<string>SVC-WIFI</string>
+							<string>SVC-ETHERNET</string>
+						</array>
+					</dict>
+				</dict>
+				<key>Service</key>
+				<dict>
+					<key>SVC-ETHERNET</key>
+					<dict>
+						<key>__LINK__</key>
+						<string>/NetworkServices/SVC-ETHERNET</string>
+					</dict>
+					<key>SVC-PPPOE</key>
+					<dict>
+						<key>__LINK__</key>
+						<string>/NetworkServices/SVC-PPPOE</string>
+					</dict>
+					<key>SVC-WIFI</key>
+					<dict>
+						<key>__LINK__</key>
+						<string>/NetworkServices/SVC-WIFI</string>
+					</dict>
+				</dict>
+			</dict>
+			<key>UserDefinedName</key>
+			<string>Automatic</string>
+		</dict>
+	</dict>
+	<key>System</key>
+	<dict>
+		<key>System</key>
+		<dict>
+			<key>ComputerName</key>
+			<string>Mac</string>
+		</dict>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    fn preferences() -> PreferencesFile {
+        PreferencesFile::from_slice(PREFERENCES.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn paths_follow_links() {
+        let prefs = preferences();
+        let link_path = "/Sets/SET-1/Network/Service/SVC-ETHERNET";
+        assert_eq!(
+            prefs.path_get_link(link_path),
+            Some("/NetworkServices/SVC-ETHERNET")
+        );
+        assert_eq!(
+            prefs.path_get_value(link_path),
+            prefs.path_get_value("/NetworkServices/SVC-ETHERNET")
+        );
+        assert_eq!(
+            prefs
+                .path_get_value(&format!("{}/IPv4", link_path))
+                .and_then(|ipv4| ipv4.get("ConfigMethod")),
+            Some(&"DHCP".into())
+        );
+        assert_eq!(prefs.path_get_link("/NetworkServices/SVC-ETHERNET"), None);
+        assert_eq!(prefs.path_get_value("/"), Some(prefs.values()));
+        assert_eq!(prefs.path_get_value("/Missing"), None);
+        assert_eq!(prefs.path_get_value("CurrentSet"), None);
+        assert_eq!(prefs.path_get_value("/CurrentSet"), None);
+    }
+
+    #[test]
+    fn link_cycle() {
+        let link = |target: &str| {
+            PlistValue::Dictionary([(LINK.to_owned(), target.into())].into_iter().collect())
+        };
+        let prefs = PreferencesFile::from(
+            [("A".to_owned(), link("/B")), ("B".to_owned(), link("/A"))]
+                .into_iter()
+                .collect::<Dictionary>(),
+        );
+        assert_eq!(prefs.path_get_link("/A"), Some("/B"));
+        assert_eq!(prefs.path_get_value("/A/Key"), None);
+    }
+
+    #[test]
+    fn snapshot() {
+        let prefs = preferences();
+        assert_eq!(prefs.current_set(), Some("SET-1"));
+        assert_eq!(
+            prefs
+                .system()
+                .and_then(|system| system.get("System"))
+                .and_then(PlistValue::as_dictionary)
+                .and_then(|system| system.get("ComputerName")),
+            Some(&"Mac".into())
+        );
+
+        let snapshot = prefs.snapshot();
+        assert_eq!(snapshot.current_set.as_deref(), Some("SET-1"));
+        assert_eq!(snapshot.sets.len(), 1);
+        let set = &snapshot.sets[0];
+        assert_eq!(set.name.as_deref(), Some("Automatic"));
+        let ids = set
+            .services
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["SVC-WIFI", "SVC-ETHERNET", "SVC-PPPOE"]);
+
+        let wifi = &set.services[0];
+        assert_eq!(wifi.name.as_deref(), Some("Wi-Fi"));
+        assert_eq!(
+            wifi.interface,
+            Some(InterfaceSpec::new(SCNetworkInterfaceType::IEEE80211).with_bsd_name("en1"))
+        );
+
+        let ethernet = &set.services[1];
+        assert!(ethernet.enabled);
+        let protocols = ethernet
+            .protocols
+            .iter()
+            .map(|protocol| (protocol.protocol_type.clone(), protocol.enabled))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            protocols,
+            [
+                (SCNetworkProtocolType::Other("AppleTalk".to_owned()), true),
+                (SCNetworkProtocolType::DNS, true),
+                (SCNetworkProtocolType::IPv4, true),
+                (SCNetworkProtocolType::IPv6, false),
+            ]
+        );
+        assert_eq!(
+            ethernet.protocols[3].configuration,
+            Some(
+                [("ConfigMethod".to_owned(), "Automatic".into())]
+                    .into_iter()
+                    .collect()
+            )
+        );
+
+        let pppoe = &set.services[2];
+        assert!(!pppoe.enabled);
+        assert!(pppoe.protocols.is_empty());
+        assert_eq!(
+            pppoe.interface,
+            Some(InterfaceSpec::new(SCNetworkInterfaceType::PPP).layered_on(
+                InterfaceSpec::new(SCNetworkInterfaceType::Ethernet).with_bsd_name("en0")
+            ))
+        );
+    }
+
+    #[test]
+    fn not_a_dictionary() {
+        assert!(PreferencesFile::from_slice(b"<plist><array/></plist>").is_err());
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::{base::TCFType, string::CFString};
+
+        let keys = unsafe {
+            [
+                (CURRENT_SET, kSCPrefCurrentSet),
+                (INACTIVE, kSCResvInactive),
+                (IPV4, kSCEntNetIPv4),
+                (INTERFACE, kSCEntNetInterface),
+                (SERVICE_ORDER, kSCPropNetServiceOrder),
+                (USER_DEFINED_NAME, kSCPropUserDefinedName),
+                (DEVICE_NAME, kSCPropNetInterfaceDeviceName),
+                (HARDWARE, kSCPropNetInterfaceHardware),
+                (TYPE, kSCPropNetInterfaceType),
+                (SUB_TYPE, kSCPropNetInterfaceSubType),
+                (SUB_TYPE_PPPOE, kSCValNetInterfaceSubTypePPPoE),
+                (SUB_TYPE_PPP_SERIAL, kSCValNetInterfaceSubTypePPPSerial),
+                (SUB_TYPE_L2TP, kSCValNetInterfaceSubTypeL2TP),
+                (SUB_TYPE_PPTP, kSCValNetInterfaceSubTypePPTP),
+                (HARDWARE_AIRPORT, kSCEntNetAirPort),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
+
+        let interface_entities = unsafe {
+            [
+                kSCEntNet6to4,
+                kSCEntNetEthernet,
+                kSCEntNetFireWire,
+                kSCEntNetIPSec,
+                kSCEntNetL2TP,
+                kSCEntNetModem,
+                kSCEntNetPPP,
+                kSCEntNetPPPoE,
+                kSCEntNetPPPSerial,
+                kSCEntNetPPTP,
+            ]
+        };
+        for const_str in interface_entities {
+            let entity = unsafe { CFString::wrap_under_get_rule(const_str) }.to_string();
+            assert!(INTERFACE_ENTITIES.contains(&entity.as_str()), "{}", entity);
+        }
+    }
+}
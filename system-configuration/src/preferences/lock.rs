@@ -0,0 +1,218 @@
+use super::{PreferencesBackend, SCPreferences};
+use crate::error::SCError;
+use std::ops::{Deref, DerefMut};
+
+/// Exclusive access to a preferences session, released when the guard is dropped.
+///
+/// Created with [`PreferencesLock::acquire`] or [`SCPreferences::lock_guard`]. Changes are made
+/// through the guard, which dereferences to the session, and written with [`commit`]. A guard
+/// that is dropped without being committed discards the uncommitted changes of the session with
+/// [`synchronize`](PreferencesBackend::synchronize), so an early return can neither leak the lock
+/// nor leave half-made changes behind for a later commit.
+///
+/// [`commit`]: #method.commit
+pub struct PreferencesLock<'a, P: PreferencesBackend + ?Sized> {
+    preferences: &'a mut P,
+    apply: bool,
+    finished: bool,
+}
+
+impl<'a, P: PreferencesBackend + ?Sized> PreferencesLock<'a, P> {
+    /// Locks the preferences, waiting for other sessions to release their lock if `wait` is
+    /// `true`. Fails like [`PreferencesBackend::lock`], in particular with [`SCError::Stale`] if
+    /// the preferences changed since the session read them, in which case the session should be
+    /// synchronized before trying again.
+    pub fn acquire(preferences: &'a mut P, wait: bool) -> Result<Self, SCError> {
+        preferences.lock(wait)?;
+        Ok(PreferencesLock {
+            preferences,
+            apply: false,
+            finished: false,
+        })
+    }
+
+    /// Runs `f` with the preferences locked, waiting for the lock if another session holds it.
+    /// Commits the changes if `f` succeeds, and discards them if it fails. The lock is released in
+    /// both cases.
+    ///
+    /// `f` may call [`apply_on_commit`](Self::apply_on_commit) to also apply the changes.
+    pub fn transaction<T, E, F>(preferences: &'a mut P, f: F) -> Result<T, E>
+    where
+        E: From<SCError>,
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        let mut lock = Self::acquire(preferences, true)?;
+        match f(&mut lock) {
+            Ok(value) => {
+                lock.commit()?;
+                Ok(value)
+            }
+            Err(error) => {
+                lock.rollback();
+                Err(error)
+            }
+        }
+    }
+
+    /// Makes [`commit`](Self::commit) also apply the committed preferences to the running system.
+    pub fn apply_on_commit(&mut self) {
+        self.apply = true;
+    }
+
+    /// Commits the changes, applies them if requested with
+    /// [`apply_on_commit`](Self::apply_on_commit), and releases the lock.
+    ///
+    /// If committing fails the changes are discarded. If applying fails the changes stay committed.
+    pub fn commit(mut self) -> Result<(), SCError> {
+        self.preferences.commit_changes()?;
+        self.finished = true;
+        let applied = if self.apply {
+            self.preferences.apply_changes()
+        } else {
+            Ok(())
+        };
+        let unlocked = self.preferences.unlock();
+        applied.and(unlocked)
+    }
+
+    /// Discards the uncommitted changes and releases the lock. The same happens when the guard is
+    /// dropped without being committed.
+    pub fn rollback(self) {}
+}
+
+impl<P: PreferencesBackend + ?Sized> Deref for PreferencesLock<'_, P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        self.preferences
+    }
+}
+
+impl<P: PreferencesBackend + ?Sized> DerefMut for PreferencesLock<'_, P> {
+    fn deref_mut(&mut self) -> &mut P {
+        self.preferences
+    }
+}
+
+impl<P: PreferencesBackend + ?Sized> Drop for PreferencesLock<'_, P> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.preferences.synchronize();
+            // Errors can't be reported from here, and unlocking a held lock does not fail.
+            let _ = self.preferences.unlock();
+        }
+    }
+}
+
+impl SCPreferences {
+    /// Locks the preferences and returns a guard releasing the lock when dropped. See
+    /// [`PreferencesLock`] for details.
+    pub fn lock_guard(&mut self, wait: bool) -> Result<PreferencesLock<'_, Self>, SCError> {
+        PreferencesLock::acquire(self, wait)
+    }
+
+    /// Runs `f` with the preferences locked and commits its changes if it succeeds. See
+    /// [`PreferencesLock::transaction`] for details.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        E: From<SCError>,
+        F: FnOnce(&mut PreferencesLock<'_, Self>) -> Result<T, E>,
+    {
+        PreferencesLock::transaction(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::MemoryPreferences, *};
+    use crate::plist::PlistValue;
+
+    const KEY: &str = "Key";
+
+    fn value(preferences: &MemoryPreferences) -> Option<PlistValue> {
+        preferences.stored_values().get(KEY).cloned()
+    }
+
+    #[test]
+    fn commit() {
+        let mut preferences = MemoryPreferences::new();
+        let mut lock = PreferencesLock::acquire(&mut preferences, false).unwrap();
+        lock.set(KEY, &1.into()).unwrap();
+        lock.commit().unwrap();
+        assert!(!preferences.is_locked());
+        assert_eq!(value(&preferences), Some(1.into()));
+        assert_eq!(preferences.applied_values(), None);
+    }
+
+    #[test]
+    fn drop_discards_changes() {
+        let mut preferences = MemoryPreferences::new();
+        {
+            let mut lock = PreferencesLock::acquire(&mut preferences, false).unwrap();
+            lock.set(KEY, &1.into()).unwrap();
+            assert!(lock.is_locked());
+        }
+        assert!(!preferences.is_locked());
+        assert_eq!(preferences.get(KEY), None);
+        preferences.commit_changes().unwrap();
+        assert_eq!(value(&preferences), None);
+    }
+
+    #[test]
+    fn lock_held_elsewhere() {
+        let mut preferences = MemoryPreferences::new();
+        let mut other = preferences.session();
+        let lock = PreferencesLock::acquire(&mut other, false).unwrap();
+        assert_eq!(
+            PreferencesLock::acquire(&mut preferences, false).err(),
+            Some(SCError::PrefsBusy)
+        );
+        drop(lock);
+        assert!(PreferencesLock::acquire(&mut preferences, false).is_ok());
+    }
+
+    #[test]
+    fn transaction() {
+        let mut preferences = MemoryPreferences::new();
+        let result = PreferencesLock::transaction(&mut preferences, |lock| {
+            lock.set(KEY, &1.into())?;
+            lock.apply_on_commit();
+            Ok::<_, SCError>(5)
+        });
+        assert_eq!(result, Ok(5));
+        assert_eq!(value(&preferences), Some(1.into()));
+        assert_eq!(
+            preferences
+                .applied_values()
+                .and_then(|values| values.get(KEY).cloned()),
+            Some(1.into())
+        );
+
+        let result = PreferencesLock::transaction(&mut preferences, |lock| {
+            lock.set(KEY, &2.into())?;
+            lock.remove("Missing")
+        });
+        assert_eq!(result, Err(SCError::NoKey));
+        assert!(!preferences.is_locked());
+        assert_eq!(preferences.get(KEY), Some(1.into()));
+        assert_eq!(value(&preferences), Some(1.into()));
+    }
+
+    #[test]
+    fn transaction_on_stale_session() {
+        let mut preferences = MemoryPreferences::new();
+        let mut other = preferences.session();
+        assert_eq!(preferences.get(KEY), None);
+        other.set(KEY, &1.into()).unwrap();
+        other.commit_changes().unwrap();
+
+        let result =
+            PreferencesLock::transaction(&mut preferences, |lock| lock.set(KEY, &2.into()));
+        assert_eq!(result, Err(SCError::Stale));
+        preferences.synchronize();
+        let result =
+            PreferencesLock::transaction(&mut preferences, |lock| lock.set(KEY, &2.into()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(value(&preferences), Some(2.into()));
+    }
+}
@@ -0,0 +1,469 @@
+use super::{
+    path::{
+        path_create_child, path_get_link, path_get_value, path_remove_value, path_set_link,
+        path_set_value,
+    },
+    PreferencesBackend, PreferencesSignature,
+};
+use crate::{
+    error::SCError,
+    plist::{Dictionary, PlistValue},
+};
+use std::{
+    cell::{Ref, RefCell},
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+/// Preferences kept in memory, for testing code written against [`PreferencesBackend`].
+///
+/// A `MemoryPreferences` is one session on a set of stored preferences. Further sessions on the
+/// same preferences are opened with [`session`], like creating several [`SCPreferences`] for the
+/// same preferences file. Every session behaves like an `SCPreferences` session:
+///
+/// - The stored preferences are read on the first access and changes stay local to the session
+///   until they are committed with [`commit_changes`](PreferencesBackend::commit_changes).
+/// - Only one session holds the lock at a time. [`lock`](PreferencesBackend::lock) fails with
+///   [`SCError::PrefsBusy`] or waits for it to be released, and fails with [`SCError::Stale`] if
+///   another session committed since this session read the preferences.
+/// - Paths follow links and [`path_create_unique_child`] names children after a counter, so
+///   tests are deterministic.
+///
+/// Dropping a session releases its lock.
+///
+/// [`session`]: #method.session
+/// [`SCPreferences`]: super::SCPreferences
+/// [`path_create_unique_child`]: PreferencesBackend::path_create_unique_child
+pub struct MemoryPreferences {
+    shared: Arc<Shared>,
+    /// The preferences as read by this session, with its uncommitted changes.
+    accessed: RefCell<Option<Accessed>>,
+    locked: bool,
+    changed: bool,
+}
+
+struct Shared {
+    store: Mutex<Store>,
+    unlocked: Condvar,
+}
+
+#[derive(Default)]
+struct Store {
+    values: Dictionary,
+    /// Incremented by every commit that changes the stored values.
+    generation: u64,
+    locked: bool,
+    applied: Option<Dictionary>,
+    next_child: u64,
+}
+
+struct Accessed {
+    values: Dictionary,
+    generation: u64,
+}
+
+impl MemoryPreferences {
+    /// Returns a session on new, empty preferences.
+    pub fn new() -> Self {
+        Self::with_values(Dictionary::new())
+    }
+
+    /// Returns a session on new preferences storing `values`.
+    pub fn with_values(values: Dictionary) -> Self {
+        let store = Store {
+            values,
+            ..Store::default()
+        };
+        MemoryPreferences::open(Arc::new(Shared {
+            store: Mutex::new(store),
+            unlocked: Condvar::new(),
+        }))
+    }
+
+    fn open(shared: Arc<Shared>) -> Self {
+        MemoryPreferences {
+            shared,
+            accessed: RefCell::new(None),
+            locked: false,
+            changed: false,
+        }
+    }
+
+    /// Opens another session on the same stored preferences.
+    pub fn session(&self) -> Self {
+        MemoryPreferences::open(self.shared.clone())
+    }
+
+    /// Returns the stored preferences, as committed by all sessions.
+    pub fn stored_values(&self) -> Dictionary {
+        self.store().values.clone()
+    }
+
+    /// Returns the preferences as of the last call to
+    /// [`apply_changes`](PreferencesBackend::apply_changes), or `None` if they were never applied.
+    pub fn applied_values(&self) -> Option<Dictionary> {
+        self.store().applied.clone()
+    }
+
+    /// Returns `true` if this session holds the lock.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        self.shared
+            .store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the preferences as seen by this session, reading them on the first access.
+    fn values(&self) -> Ref<'_, Dictionary> {
+        if self.accessed.borrow().is_none() {
+            let store = self.store();
+            *self.accessed.borrow_mut() = Some(Accessed {
+                values: store.values.clone(),
+                generation: store.generation,
+            });
+        }
+        Ref::map(self.accessed.borrow(), |accessed| {
+            &accessed.as_ref().expect("preferences were accessed").values
+        })
+    }
+
+    /// Makes a change to the preferences of this session.
+    fn change<T>(
+        &mut self,
+        change: impl FnOnce(&mut Dictionary) -> Result<T, SCError>,
+    ) -> Result<T, SCError> {
+        drop(self.values());
+        let accessed = self
+            .accessed
+            .get_mut()
+            .as_mut()
+            .expect("preferences were accessed");
+        let result = change(&mut accessed.values)?;
+        self.changed = true;
+        Ok(result)
+    }
+
+    /// Runs `operation` with the lock held, taking the lock for the duration of the call if this
+    /// session does not hold it already.
+    fn with_lock(
+        &mut self,
+        operation: impl FnOnce(&mut Self) -> Result<(), SCError>,
+    ) -> Result<(), SCError> {
+        if self.locked {
+            return operation(self);
+        }
+        self.lock(true)?;
+        let result = operation(self);
+        let unlocked = self.unlock();
+        result.and(unlocked)
+    }
+}
+
+impl Default for MemoryPreferences {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Dictionary> for MemoryPreferences {
+    fn from(values: Dictionary) -> Self {
+        Self::with_values(values)
+    }
+}
+
+impl fmt::Debug for MemoryPreferences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryPreferences")
+            .field(
+                "values",
+                &self
+                    .accessed
+                    .borrow()
+                    .as_ref()
+                    .map(|accessed| &accessed.values),
+            )
+            .field("locked", &self.locked)
+            .field("changed", &self.changed)
+            .finish()
+    }
+}
+
+impl Drop for MemoryPreferences {
+    fn drop(&mut self) {
+        if self.locked {
+            let _ = self.unlock();
+        }
+    }
+}
+
+impl PreferencesBackend for MemoryPreferences {
+    fn get_keys(&self) -> Vec<String> {
+        self.values().keys().cloned().collect()
+    }
+
+    fn get(&self, key: &str) -> Option<PlistValue> {
+        self.values().get(key).cloned()
+    }
+
+    fn add(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        self.change(|values| {
+            if values.contains_key(key) {
+                return Err(SCError::KeyExists);
+            }
+            values.insert(key.to_owned(), value.clone());
+            Ok(())
+        })
+    }
+
+    fn set(&mut self, key: &str, value: &PlistValue) -> Result<(), SCError> {
+        self.change(|values| {
+            values.insert(key.to_owned(), value.clone());
+            Ok(())
+        })
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), SCError> {
+        self.change(|values| values.remove(key).map(|_| ()).ok_or(SCError::NoKey))
+    }
+
+    fn path_get_value(&self, path: &str) -> Option<Dictionary> {
+        path_get_value(&self.values(), path).cloned()
+    }
+
+    fn path_get_link(&self, path: &str) -> Option<String> {
+        path_get_link(&self.values(), path).map(str::to_owned)
+    }
+
+    fn path_create_unique_child(&mut self, prefix: &str) -> Result<String, SCError> {
+        let name = {
+            let values = self.values();
+            let mut store = self.store();
+            loop {
+                store.next_child += 1;
+                let name = format!("{:08X}-0000-0000-0000-000000000000", store.next_child);
+                let path = format!("{}/{}", prefix.trim_end_matches('/'), name);
+                if path_get_value(&values, &path).is_none() {
+                    break name;
+                }
+            }
+        };
+        self.change(|values| path_create_child(values, prefix, &name))
+    }
+
+    fn path_set_value(&mut self, path: &str, value: &Dictionary) -> Result<(), SCError> {
+        self.change(|values| path_set_value(values, path, value.clone()))
+    }
+
+    fn path_set_link(&mut self, path: &str, link: &str) -> Result<(), SCError> {
+        self.change(|values| path_set_link(values, path, link))
+    }
+
+    fn path_remove_value(&mut self, path: &str) -> Result<(), SCError> {
+        self.change(|values| path_remove_value(values, path))
+    }
+
+    fn lock(&mut self, wait: bool) -> Result<(), SCError> {
+        if self.locked {
+            return Err(SCError::Locked);
+        }
+        let shared = self.shared.clone();
+        let mut store = shared
+            .store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while store.locked {
+            if !wait {
+                return Err(SCError::PrefsBusy);
+            }
+            store = shared
+                .unlocked
+                .wait(store)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        if let Some(accessed) = self.accessed.borrow().as_ref() {
+            if accessed.generation != store.generation {
+                return Err(SCError::Stale);
+            }
+        }
+        store.locked = true;
+        drop(store);
+        self.locked = true;
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), SCError> {
+        if !self.locked {
+            return Err(SCError::NeedLock);
+        }
+        self.store().locked = false;
+        self.shared.unlocked.notify_all();
+        self.locked = false;
+        Ok(())
+    }
+
+    fn commit_changes(&mut self) -> Result<(), SCError> {
+        self.with_lock(|prefs| {
+            if !prefs.changed {
+                return Ok(());
+            }
+            let accessed = prefs
+                .accessed
+                .get_mut()
+                .as_mut()
+                .expect("changed preferences were accessed");
+            let mut store = prefs
+                .shared
+                .store
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            store.values = accessed.values.clone();
+            store.generation += 1;
+            accessed.generation = store.generation;
+            prefs.changed = false;
+            Ok(())
+        })
+    }
+
+    fn apply_changes(&mut self) -> Result<(), SCError> {
+        self.with_lock(|prefs| {
+            let mut store = prefs.store();
+            store.applied = Some(store.values.clone());
+            Ok(())
+        })
+    }
+
+    fn synchronize(&mut self) {
+        *self.accessed.get_mut() = None;
+        self.changed = false;
+    }
+
+    fn signature(&self) -> Option<PreferencesSignature> {
+        drop(self.values());
+        let accessed = self.accessed.borrow();
+        Some(signature_of(accessed.as_ref()?.generation))
+    }
+
+    fn has_changed_since(&mut self, signature: &PreferencesSignature) -> Result<bool, SCError> {
+        let store = self.store();
+        if store.locked && !self.locked {
+            return Err(SCError::PrefsBusy);
+        }
+        Ok(signature_of(store.generation) != *signature)
+    }
+}
+
+fn signature_of(generation: u64) -> PreferencesSignature {
+    PreferencesSignature::new(generation.to_be_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn top_level_values() {
+        let mut prefs = MemoryPreferences::new();
+        prefs.add("Key", &"a".into()).unwrap();
+        assert_eq!(prefs.add("Key", &"b".into()), Err(SCError::KeyExists));
+        prefs.set("Key", &"b".into()).unwrap();
+        assert_eq!(prefs.get("Key"), Some("b".into()));
+        assert_eq!(prefs.get_keys(), ["Key"]);
+        prefs.remove("Key").unwrap();
+        assert_eq!(prefs.remove("Key"), Err(SCError::NoKey));
+    }
+
+    #[test]
+    fn paths() {
+        let mut prefs = MemoryPreferences::new();
+        let service = prefs.path_create_unique_child("/NetworkServices").unwrap();
+        let other = prefs.path_create_unique_child("/NetworkServices").unwrap();
+        assert_ne!(service, other);
+        assert_eq!(prefs.path_get_value(&service), Some(Dictionary::new()));
+
+        let link = "/Sets/Set/Network/Service/Service";
+        prefs.path_set_link(link, &service).unwrap();
+        assert_eq!(prefs.path_get_link(link).as_deref(), Some(&*service));
+        let dns = [("Domain".to_owned(), "example.com".into())]
+            .into_iter()
+            .collect::<Dictionary>();
+        prefs
+            .path_set_value(&format!("{}/DNS", link), &dns)
+            .unwrap();
+        assert_eq!(prefs.path_get_value(&format!("{}/DNS", service)), Some(dns));
+        assert_eq!(prefs.path_create_unique_child(link), Err(SCError::Failed));
+
+        prefs.path_remove_value(link).unwrap();
+        assert_eq!(prefs.get("Sets"), None);
+        assert_eq!(prefs.path_remove_value(link), Err(SCError::NoKey));
+    }
+
+    #[test]
+    fn commit_and_synchronize() {
+        let mut prefs = MemoryPreferences::new();
+        let mut other = prefs.session();
+        prefs.set("Key", &1.into()).unwrap();
+        assert_eq!(other.get("Key"), None);
+        assert!(prefs.stored_values().is_empty());
+
+        prefs.commit_changes().unwrap();
+        assert!(!prefs.is_locked());
+        assert_eq!(prefs.stored_values().get("Key"), Some(&1.into()));
+        // The other session keeps the preferences it read until it synchronizes.
+        assert_eq!(other.get("Key"), None);
+        other.synchronize();
+        assert_eq!(other.get("Key"), Some(1.into()));
+
+        prefs.set("Key", &2.into()).unwrap();
+        prefs.synchronize();
+        assert_eq!(prefs.get("Key"), Some(1.into()));
+
+        assert_eq!(prefs.applied_values(), None);
+        prefs.apply_changes().unwrap();
+        assert_eq!(prefs.applied_values(), Some(prefs.stored_values()));
+    }
+
+    #[test]
+    fn lock_semantics() {
+        let mut prefs = MemoryPreferences::new();
+        let mut other = prefs.session();
+        assert_eq!(prefs.unlock(), Err(SCError::NeedLock));
+        prefs.lock(false).unwrap();
+        assert_eq!(prefs.lock(false), Err(SCError::Locked));
+        assert_eq!(other.lock(false), Err(SCError::PrefsBusy));
+        prefs.unlock().unwrap();
+
+        // A session that read the preferences before another session committed is stale.
+        let signature = other.signature();
+        prefs.set("Key", &1.into()).unwrap();
+        prefs.commit_changes().unwrap();
+        assert_ne!(prefs.signature(), signature);
+        assert_eq!(other.lock(false), Err(SCError::Stale));
+        other.synchronize();
+        other.lock(false).unwrap();
+        drop(other);
+        prefs.lock(false).unwrap();
+    }
+
+    #[test]
+    fn lock_waits_for_other_session() {
+        let mut prefs = MemoryPreferences::new();
+        let mut other = prefs.session();
+        prefs.lock(false).unwrap();
+        let waiter = thread::spawn(move || {
+            other.lock(true).unwrap();
+            other.set("Key", &1.into()).unwrap();
+            other.commit_changes().unwrap();
+            other.unlock().unwrap();
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(prefs.stored_values().is_empty());
+        prefs.unlock().unwrap();
+        waiter.join().unwrap();
+        assert_eq!(prefs.stored_values().get("Key"), Some(&1.into()));
+    }
+}
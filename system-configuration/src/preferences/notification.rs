@@ -0,0 +1,224 @@
+use super::SCPreferences;
+use crate::{
+    error::{to_result, SCError},
+    sys::{
+        dispatch_queue_t,
+        preferences::{
+            kSCPreferencesNotificationApply, kSCPreferencesNotificationCommit,
+            SCPreferencesContext, SCPreferencesNotification, SCPreferencesRef,
+            SCPreferencesScheduleWithRunLoop, SCPreferencesSetCallback,
+            SCPreferencesSetDispatchQueue, SCPreferencesUnscheduleFromRunLoop,
+        },
+    },
+};
+use core_foundation::{
+    base::{TCFType, ToVoid},
+    runloop::CFRunLoop,
+    string::{CFString, CFStringRef},
+};
+use std::{ffi::c_void, ptr, sync::Arc};
+
+bitflags::bitflags! {
+    /// Rustier interface for [`SCPreferencesNotification`].
+    ///
+    /// [`SCPreferencesNotification`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesnotification
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PreferencesNotification: u32 {
+        /// The preferences were committed to persistent storage.
+        const COMMIT = kSCPreferencesNotificationCommit;
+        /// The preferences were applied to the running system.
+        const APPLY = kSCPreferencesNotificationApply;
+    }
+}
+
+impl SCPreferences {
+    /// Sets a callback that is run whenever the preferences are committed or applied, by this or
+    /// any other process. For the callback to be invoked, the session has to be scheduled on a run
+    /// loop with [`schedule_with_runloop`](Self::schedule_with_runloop) or on a dispatch queue
+    /// with [`set_dispatch_queue`](Self::set_dispatch_queue). Calling this function again replaces
+    /// the callback.
+    ///
+    /// The callback receives the session it was set on. Its cached values are those read before
+    /// the change, call [`synchronize`](Self::synchronize) to see the new preferences.
+    ///
+    /// See [`SCPreferencesSetCallback`] for details.
+    ///
+    /// [`SCPreferencesSetCallback`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencessetcallback(_:_:_:)?language=objc
+    pub fn set_callback<F: Fn(SCPreferences, PreferencesNotification) + Sync + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) -> Result<(), SCError> {
+        let callback = Arc::new(PreferencesCallbackContext { callback });
+
+        let mut callback_context = SCPreferencesContext {
+            version: 0,
+            info: Arc::into_raw(callback) as *mut _,
+            retain: Some(PreferencesCallbackContext::<F>::retain_context),
+            release: Some(PreferencesCallbackContext::<F>::release_context),
+            copyDescription: Some(PreferencesCallbackContext::<F>::copy_ctx_description),
+        };
+
+        let result = unsafe {
+            SCPreferencesSetCallback(
+                self.0,
+                Some(PreferencesCallbackContext::<F>::callback),
+                &mut callback_context,
+            )
+        };
+
+        // SCPreferencesSetCallback retains the context if it succeeds, and the session releases it
+        // when the callback is replaced or the session is deallocated. The reference created above
+        // is not needed beyond this call either way.
+        unsafe {
+            Arc::decrement_strong_count(
+                callback_context.info as *const PreferencesCallbackContext<F>,
+            )
+        };
+
+        to_result(result)
+    }
+
+    /// Removes the callback set with [`set_callback`](Self::set_callback).
+    pub fn clear_callback(&mut self) -> Result<(), SCError> {
+        to_result(unsafe { SCPreferencesSetCallback(self.0, None, ptr::null_mut()) })
+    }
+
+    /// Schedules the callback with a run loop.
+    ///
+    /// See [`SCPreferencesScheduleWithRunLoop`] for details.
+    ///
+    /// [`SCPreferencesScheduleWithRunLoop`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesschedulewithrunloop(_:_:_:)?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `run_loop_mode` must not be NULL and must be a pointer to a valid run loop mode.
+    /// Use `core_foundation::runloop::kCFRunLoopCommonModes` if you are unsure.
+    pub unsafe fn schedule_with_runloop(
+        &self,
+        run_loop: &CFRunLoop,
+        run_loop_mode: CFStringRef,
+    ) -> Result<(), SCError> {
+        to_result(SCPreferencesScheduleWithRunLoop(
+            self.0,
+            run_loop.to_void() as *mut _,
+            run_loop_mode,
+        ))
+    }
+
+    /// Unschedules the callback from a run loop.
+    ///
+    /// See [`SCPreferencesUnscheduleFromRunLoop`] for details.
+    ///
+    /// [`SCPreferencesUnscheduleFromRunLoop`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesunschedulefromrunloop(_:_:_:)?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `run_loop_mode` must not be NULL and must be a pointer to a valid run loop mode.
+    /// Use `core_foundation::runloop::kCFRunLoopCommonModes` if you are unsure.
+    pub unsafe fn unschedule_from_runloop(
+        &self,
+        run_loop: &CFRunLoop,
+        run_loop_mode: CFStringRef,
+    ) -> Result<(), SCError> {
+        to_result(SCPreferencesUnscheduleFromRunLoop(
+            self.0,
+            run_loop.to_void() as *mut _,
+            run_loop_mode,
+        ))
+    }
+
+    /// Schedules the callback on a dispatch queue, or unschedules it if `queue` is NULL.
+    ///
+    /// See [`SCPreferencesSetDispatchQueue`] for details.
+    ///
+    /// [`SCPreferencesSetDispatchQueue`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencessetdispatchqueue(_:_:)?language=objc
+    ///
+    /// # Safety
+    ///
+    /// The `queue` must be NULL or a valid dispatch queue.
+    pub unsafe fn set_dispatch_queue(&self, queue: dispatch_queue_t) -> Result<(), SCError> {
+        to_result(SCPreferencesSetDispatchQueue(self.0, queue))
+    }
+}
+
+struct PreferencesCallbackContext<T: Fn(SCPreferences, PreferencesNotification) + Sync + Send> {
+    callback: T,
+}
+
+impl<T: Fn(SCPreferences, PreferencesNotification) + Sync + Send> PreferencesCallbackContext<T> {
+    extern "C" fn callback(
+        prefs: SCPreferencesRef,
+        notification_type: SCPreferencesNotification,
+        context: *mut c_void,
+    ) {
+        let context: &Self = unsafe { &*(context as *const Self) };
+        let prefs = unsafe { SCPreferences::wrap_under_get_rule(prefs) };
+        (context.callback)(
+            prefs,
+            PreferencesNotification::from_bits_retain(notification_type),
+        );
+    }
+
+    extern "C" fn copy_ctx_description(_ctx: *const c_void) -> CFStringRef {
+        let description = CFString::from_static_string("SCPreferences's callback context");
+        let description_ref = description.as_concrete_TypeRef();
+        std::mem::forget(description);
+        description_ref
+    }
+
+    extern "C" fn release_context(ctx: *const c_void) {
+        unsafe {
+            Arc::decrement_strong_count(ctx as *mut Self);
+        }
+    }
+
+    extern "C" fn retain_context(ctx_ptr: *const c_void) -> *const c_void {
+        unsafe {
+            Arc::increment_strong_count(ctx_ptr as *mut Self);
+        }
+        ctx_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_foundation::runloop::kCFRunLoopCommonModes;
+
+    #[test]
+    fn callback_is_released_with_session() {
+        let captured = Arc::new(());
+        let mut preferences = SCPreferences::default(&CFString::new("test"));
+        let callback_captured = captured.clone();
+        preferences
+            .set_callback(move |_, _| {
+                let _ = &callback_captured;
+            })
+            .unwrap();
+        assert_eq!(Arc::strong_count(&captured), 2);
+        unsafe {
+            preferences
+                .schedule_with_runloop(&CFRunLoop::get_current(), kCFRunLoopCommonModes)
+                .unwrap();
+            preferences
+                .unschedule_from_runloop(&CFRunLoop::get_current(), kCFRunLoopCommonModes)
+                .unwrap();
+        }
+        drop(preferences);
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+
+    #[test]
+    fn clear_callback() {
+        let captured = Arc::new(());
+        let mut preferences = SCPreferences::default(&CFString::new("test"));
+        let callback_captured = captured.clone();
+        preferences
+            .set_callback(move |_, _| {
+                let _ = &callback_captured;
+            })
+            .unwrap();
+        preferences.clear_callback().unwrap();
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+}
@@ -0,0 +1,723 @@
+//! Preference paths over pure Rust dictionaries, following the rules of `SCPreferencesPath`.
+//!
+//! A path such as `/Sets/<id>/Network` names a dictionary by the keys leading to it from the top
+//! level of the preferences. A dictionary holding a `kSCResvLink` string is a link: lookups of a
+//! path running through it continue at the path the link names.
+//!
+//! [`PrefsPath`] builds and parses such paths out of the `kSCPref*` and `kSCComp*` schema
+//! definitions, whose strings are mirrored here.
+
+use super::PreferencesBackend;
+use crate::{
+    dynamic_store::key::Entity,
+    error::SCError,
+    plist::{Dictionary, PlistValue},
+};
+use core_foundation::string::CFString;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// `kSCResvLink`.
+pub(crate) const LINK: &str = "__LINK__";
+/// `kSCPrefNetworkServices`.
+pub(crate) const NETWORK_SERVICES: &str = "NetworkServices";
+/// `kSCPrefSets`.
+pub(crate) const SETS: &str = "Sets";
+/// `kSCPrefSystem`.
+pub(crate) const SYSTEM: &str = "System";
+/// `kSCCompNetwork`.
+pub(crate) const NETWORK: &str = "Network";
+/// `kSCCompService`.
+pub(crate) const SERVICE: &str = "Service";
+/// `kSCCompGlobal`.
+pub(crate) const GLOBAL: &str = "Global";
+/// `kSCCompInterface`.
+const INTERFACE: &str = "Interface";
+
+/// How many links are followed when looking up a path, as in `SCPreferencesPathGetValue`.
+const MAX_LINKS: usize = 8;
+
+/// Splits a path into its components. Fails with [`SCError::NoKey`] if it is not absolute.
+fn components(path: &str) -> Result<Vec<&str>, SCError> {
+    Ok(path
+        .strip_prefix('/')
+        .ok_or(SCError::NoKey)?
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect())
+}
+
+/// Returns the link held by a dictionary, if it is a link.
+fn link(dictionary: &Dictionary) -> Option<&str> {
+    dictionary.get(LINK).and_then(PlistValue::as_str)
+}
+
+fn dictionary_mut(value: &mut PlistValue) -> Option<&mut Dictionary> {
+    match value {
+        PlistValue::Dictionary(dictionary) => Some(dictionary),
+        _ => None,
+    }
+}
+
+/// Returns the path continuing with the components `rest` at `link`.
+fn redirect(link: &str, rest: &[&str]) -> String {
+    let mut target = link.to_owned();
+    for component in rest {
+        target.push('/');
+        target.push_str(component);
+    }
+    target
+}
+
+/// Returns the dictionary at `path`, following links in the components leading up to it but not
+/// a link stored at the path itself.
+pub(crate) fn get_path<'a>(root: &'a Dictionary, path: &str) -> Result<&'a Dictionary, SCError> {
+    let mut path = path.to_owned();
+    'links: for _ in 0..=MAX_LINKS {
+        let components = components(&path)?;
+        let mut dictionary = root;
+        for (index, component) in components.iter().enumerate() {
+            dictionary = child(dictionary, component)?;
+            if index + 1 < components.len() {
+                if let Some(link) = link(dictionary) {
+                    path = redirect(link, &components[index + 1..]);
+                    continue 'links;
+                }
+            }
+        }
+        return Ok(dictionary);
+    }
+    Err(SCError::MaxLink)
+}
+
+fn child<'a>(dictionary: &'a Dictionary, component: &str) -> Result<&'a Dictionary, SCError> {
+    dictionary
+        .get(component)
+        .and_then(PlistValue::as_dictionary)
+        .ok_or(SCError::NoKey)
+}
+
+/// Resolves the links in the existing components leading up to the last component of `path`,
+/// returning the components of the path the value is stored at.
+fn resolve_for_update(root: &Dictionary, path: &str) -> Result<Vec<String>, SCError> {
+    let mut path = path.to_owned();
+    'links: for _ in 0..=MAX_LINKS {
+        let components = components(&path)?;
+        let mut dictionary = Some(root);
+        for (index, component) in components.iter().enumerate() {
+            if index + 1 == components.len() {
+                break;
+            }
+            dictionary = dictionary
+                .and_then(|dictionary| dictionary.get(*component))
+                .and_then(PlistValue::as_dictionary);
+            if let Some(link) = dictionary.and_then(link) {
+                path = redirect(link, &components[index + 1..]);
+                continue 'links;
+            }
+        }
+        return Ok(components.into_iter().map(str::to_owned).collect());
+    }
+    Err(SCError::MaxLink)
+}
+
+/// Looks up `path` in `root` like `SCPreferencesPathGetValue`.
+pub(crate) fn path_get_value<'a>(root: &'a Dictionary, path: &str) -> Option<&'a Dictionary> {
+    let dictionary = get_path(root, path).ok()?;
+    match link(dictionary) {
+        Some(link) => get_path(root, link).ok(),
+        None => Some(dictionary),
+    }
+}
+
+/// Looks up the link at `path` in `root` like `SCPreferencesPathGetLink`.
+pub(crate) fn path_get_link<'a>(root: &'a Dictionary, path: &str) -> Option<&'a str> {
+    link(get_path(root, path).ok()?)
+}
+
+/// Stores `value` at `path` like `SCPreferencesPathSetValue`, creating the dictionaries leading up
+/// to it.
+pub(crate) fn path_set_value(
+    root: &mut Dictionary,
+    path: &str,
+    value: Dictionary,
+) -> Result<(), SCError> {
+    let components = resolve_for_update(root, path)?;
+    let (last, parents) = components.split_last().ok_or(SCError::InvalidArgument)?;
+    let mut dictionary = root;
+    for component in parents {
+        let entry = dictionary
+            .entry(component.clone())
+            .or_insert_with(|| PlistValue::Dictionary(Dictionary::new()));
+        if entry.as_dictionary().is_none() {
+            *entry = PlistValue::Dictionary(Dictionary::new());
+        }
+        dictionary = dictionary_mut(entry).expect("entry was made a dictionary");
+    }
+    dictionary.insert(last.clone(), PlistValue::Dictionary(value));
+    Ok(())
+}
+
+/// Stores a link to `link` at `path` like `SCPreferencesPathSetLink`. The link must name an
+/// existing dictionary.
+pub(crate) fn path_set_link(root: &mut Dictionary, path: &str, link: &str) -> Result<(), SCError> {
+    get_path(root, link)?;
+    let mut value = Dictionary::new();
+    value.insert(LINK.to_owned(), link.into());
+    path_set_value(root, path, value)
+}
+
+/// Removes the value at `path` like `SCPreferencesPathRemoveValue`. Dictionaries left empty by the
+/// removal are removed as well.
+pub(crate) fn path_remove_value(root: &mut Dictionary, path: &str) -> Result<(), SCError> {
+    get_path(root, path)?;
+    let components = resolve_for_update(root, path)?;
+    if components.is_empty() {
+        return Err(SCError::InvalidArgument);
+    }
+    remove(root, &components)
+}
+
+fn remove(dictionary: &mut Dictionary, components: &[String]) -> Result<(), SCError> {
+    let (first, rest) = components.split_first().ok_or(SCError::NoKey)?;
+    if rest.is_empty() {
+        return dictionary.remove(first).map(|_| ()).ok_or(SCError::NoKey);
+    }
+    let child = dictionary
+        .get_mut(first)
+        .and_then(dictionary_mut)
+        .ok_or(SCError::NoKey)?;
+    remove(child, rest)?;
+    if child.is_empty() {
+        dictionary.remove(first);
+    }
+    Ok(())
+}
+
+/// Creates an empty dictionary named `name` below `prefix` like
+/// `SCPreferencesPathCreateUniqueChild`, returning its path. The prefix is created if it does not
+/// exist, but must not be a link.
+pub(crate) fn path_create_child(
+    root: &mut Dictionary,
+    prefix: &str,
+    name: &str,
+) -> Result<String, SCError> {
+    match get_path(root, prefix) {
+        Ok(dictionary) if link(dictionary).is_some() => return Err(SCError::Failed),
+        Ok(_) | Err(SCError::NoKey) => (),
+        Err(error) => return Err(error),
+    }
+    let path = format!("{}/{}", prefix.trim_end_matches('/'), name);
+    path_set_value(root, &path, Dictionary::new())?;
+    Ok(path)
+}
+
+/// A validated preferences path, such as `/NetworkServices/<service id>/DNS`.
+///
+/// Use the constructors to build the paths the framework stores network configuration at, and
+/// [`str::parse`] to validate an existing path. A path is absolute and its segments are neither
+/// empty nor contain `/`. The string form of the path is available through [`as_str`] and the
+/// [`Display`] implementation, and it converts into a [`CFString`], so it can be passed directly
+/// to methods such as [`SCPreferences::path_get_value`].
+///
+/// [`as_str`]: #method.as_str
+/// [`SCPreferences::path_get_value`]: super::SCPreferences::path_get_value
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PrefsPath(String);
+
+impl PrefsPath {
+    /// The root path, `/`.
+    pub fn root() -> Self {
+        PrefsPath("/".to_owned())
+    }
+
+    /// Path of the dictionary holding all network services, `/NetworkServices`.
+    pub fn network_services() -> Self {
+        Self::root().join(NETWORK_SERVICES)
+    }
+
+    /// Path of a network service, `/NetworkServices/<service id>`.
+    pub fn network_service(service_id: &str) -> Result<Self, ParsePathError> {
+        Self::network_services().child(service_id)
+    }
+
+    /// Path of an entity of a network service, such as `/NetworkServices/<service id>/DNS`.
+    pub fn network_service_entity(
+        service_id: &str,
+        entity: &Entity,
+    ) -> Result<Self, ParsePathError> {
+        Self::network_service(service_id)?.child(entity.as_str())
+    }
+
+    /// Path of the dictionary holding all network sets, `/Sets`.
+    pub fn sets() -> Self {
+        Self::root().join(SETS)
+    }
+
+    /// Path of a network set, `/Sets/<set id>`.
+    pub fn set(set_id: &str) -> Result<Self, ParsePathError> {
+        Self::sets().child(set_id)
+    }
+
+    /// Path of a service in a network set, `/Sets/<set id>/Network/Service/<service id>`. This is
+    /// usually a link to the service below [`network_services`](Self::network_services).
+    pub fn set_service(set_id: &str, service_id: &str) -> Result<Self, ParsePathError> {
+        Self::set(set_id)?
+            .join(NETWORK)
+            .join(SERVICE)
+            .child(service_id)
+    }
+
+    /// Path of a global entity of a network set, such as `/Sets/<set id>/Network/Global/IPv4`.
+    pub fn set_global_entity(set_id: &str, entity: &Entity) -> Result<Self, ParsePathError> {
+        Self::set(set_id)?
+            .join(NETWORK)
+            .join(GLOBAL)
+            .child(entity.as_str())
+    }
+
+    /// Path of an interface in a network set, `/Sets/<set id>/Network/Interface/<name>`.
+    pub fn set_interface(set_id: &str, interface_name: &str) -> Result<Self, ParsePathError> {
+        Self::set(set_id)?
+            .join(NETWORK)
+            .join(INTERFACE)
+            .child(interface_name)
+    }
+
+    /// Path of the system settings, `/System`.
+    pub fn system() -> Self {
+        Self::root().join(SYSTEM)
+    }
+
+    /// Returns the path of the child `segment` of this path. Fails if the segment is empty or
+    /// contains `/`.
+    pub fn child(&self, segment: &str) -> Result<Self, ParsePathError> {
+        if !is_valid_segment(segment) {
+            return Err(ParsePathError::InvalidSegment(segment.to_owned()));
+        }
+        Ok(self.join(segment))
+    }
+
+    /// Appends a segment that is known to be valid.
+    fn join(&self, segment: &str) -> Self {
+        debug_assert!(is_valid_segment(segment));
+        let mut path = self.0.clone();
+        if !self.is_root() {
+            path.push('/');
+        }
+        path.push_str(segment);
+        PrefsPath(path)
+    }
+
+    /// Returns the path this path is a child of, or `None` for the root path.
+    pub fn parent(&self) -> Option<Self> {
+        if self.is_root() {
+            return None;
+        }
+        let (parent, _) = self.0.rsplit_once('/')?;
+        Some(if parent.is_empty() {
+            Self::root()
+        } else {
+            PrefsPath(parent.to_owned())
+        })
+    }
+
+    /// Returns `true` if this is the root path.
+    pub fn is_root(&self) -> bool {
+        self.0 == "/"
+    }
+
+    /// Returns the segments of the path. The root path has no segments.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0[1..].split('/').filter(|segment| !segment.is_empty())
+    }
+
+    /// Returns the string form of the path.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the set identifier of a path below [`set`](Self::set).
+    pub fn set_id(&self) -> Option<&str> {
+        match self.segments().collect::<Vec<_>>().as_slice() {
+            [SETS, set_id, ..] => Some(set_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the service identifier of a path below [`network_service`](Self::network_service)
+    /// or [`set_service`](Self::set_service).
+    pub fn service_id(&self) -> Option<&str> {
+        match self.segments().collect::<Vec<_>>().as_slice() {
+            [NETWORK_SERVICES, service_id, ..] => Some(service_id),
+            [SETS, _, NETWORK, SERVICE, service_id, ..] => Some(service_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the path the dictionary at this path is stored at, following the links in the
+    /// path and a link stored at the path itself.
+    ///
+    /// Fails with [`SCError::MaxLink`] if the links form a cycle or more links than the
+    /// framework follows are needed, and with [`SCError::Failed`] if a link is not a valid path.
+    /// The path does not need to exist.
+    pub fn resolve<B: PreferencesBackend + ?Sized>(
+        &self,
+        preferences: &B,
+    ) -> Result<Self, SCError> {
+        self.resolve_links(|path| preferences.path_get_link(path.as_str()))
+    }
+
+    /// Resolves the links of the path, `link_at` returning the link stored at a path if any.
+    fn resolve_links(
+        &self,
+        mut link_at: impl FnMut(&PrefsPath) -> Option<String>,
+    ) -> Result<Self, SCError> {
+        let mut path = self.clone();
+        let mut visited = vec![path.clone()];
+        'links: loop {
+            let segments = path.segments().map(str::to_owned).collect::<Vec<_>>();
+            let mut prefix = Self::root();
+            for (index, segment) in segments.iter().enumerate() {
+                prefix = prefix.join(segment);
+                if let Some(link) = link_at(&prefix) {
+                    let mut target = link.parse::<PrefsPath>().map_err(|_| SCError::Failed)?;
+                    for segment in &segments[index + 1..] {
+                        target = target.join(segment);
+                    }
+                    if visited.contains(&target) || visited.len() > MAX_LINKS {
+                        return Err(SCError::MaxLink);
+                    }
+                    visited.push(target.clone());
+                    path = target;
+                    continue 'links;
+                }
+            }
+            return Ok(path);
+        }
+    }
+}
+
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && !segment.contains('/')
+}
+
+impl Display for PrefsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for PrefsPath {
+    type Err = ParsePathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let rest = path
+            .strip_prefix('/')
+            .ok_or_else(|| ParsePathError::NotAbsolute(path.to_owned()))?;
+        if rest.is_empty() {
+            return Ok(Self::root());
+        }
+        if let Some(segment) = rest.split('/').find(|segment| !is_valid_segment(segment)) {
+            return Err(ParsePathError::InvalidSegment(segment.to_owned()));
+        }
+        Ok(PrefsPath(path.to_owned()))
+    }
+}
+
+impl AsRef<str> for PrefsPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&PrefsPath> for CFString {
+    fn from(path: &PrefsPath) -> Self {
+        CFString::new(&path.0)
+    }
+}
+
+impl From<PrefsPath> for CFString {
+    fn from(path: PrefsPath) -> Self {
+        CFString::from(&path)
+    }
+}
+
+/// Failure to build or parse a preferences path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePathError {
+    /// The path does not start with `/`.
+    NotAbsolute(String),
+    /// A segment of the path is empty or contains `/`.
+    InvalidSegment(String),
+}
+
+impl Display for ParsePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAbsolute(path) => write!(f, "Preferences path is not absolute: {}", path),
+            Self::InvalidSegment(segment) => {
+                write!(f, "Invalid preferences path segment: {:?}", segment)
+            }
+        }
+    }
+}
+
+impl Error for ParsePathError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(entries: &[(&str, PlistValue)]) -> Dictionary {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn link_to(target: &str) -> PlistValue {
+        PlistValue::Dictionary(dictionary(&[(LINK, target.into())]))
+    }
+
+    #[test]
+    fn set_through_links() {
+        let mut root = Dictionary::new();
+        path_set_value(
+            &mut root,
+            "/Services/A",
+            dictionary(&[("Name", "a".into())]),
+        )
+        .unwrap();
+        path_set_link(&mut root, "/Sets/1/Service/A", "/Services/A").unwrap();
+        assert_eq!(
+            path_get_link(&root, "/Sets/1/Service/A"),
+            Some("/Services/A")
+        );
+
+        path_set_value(
+            &mut root,
+            "/Sets/1/Service/A/DNS",
+            dictionary(&[("Domain", "example.com".into())]),
+        )
+        .unwrap();
+        assert!(path_get_value(&root, "/Services/A/DNS").is_some());
+        assert_eq!(
+            path_get_value(&root, "/Sets/1/Service/A"),
+            path_get_value(&root, "/Services/A")
+        );
+
+        assert_eq!(
+            path_set_link(&mut root, "/Sets/1/Service/B", "/Services/B"),
+            Err(SCError::NoKey)
+        );
+        assert_eq!(
+            path_set_value(&mut root, "/", Dictionary::new()),
+            Err(SCError::InvalidArgument)
+        );
+        assert_eq!(
+            path_set_value(&mut root, "Services", Dictionary::new()),
+            Err(SCError::NoKey)
+        );
+    }
+
+    #[test]
+    fn remove_prunes_empty_parents() {
+        let mut root = Dictionary::new();
+        path_set_value(&mut root, "/A/B/C", Dictionary::new()).unwrap();
+        path_set_value(&mut root, "/A/D", Dictionary::new()).unwrap();
+        path_remove_value(&mut root, "/A/B/C").unwrap();
+        assert!(path_get_value(&root, "/A/B").is_none());
+        assert!(path_get_value(&root, "/A/D").is_some());
+        assert_eq!(path_remove_value(&mut root, "/A/B"), Err(SCError::NoKey));
+        path_remove_value(&mut root, "/A/D").unwrap();
+        assert!(root.is_empty());
+    }
+
+    #[test]
+    fn link_limit() {
+        let mut root = dictionary(&[("A", link_to("/B")), ("B", link_to("/A"))]);
+        assert_eq!(get_path(&root, "/A/C").err(), Some(SCError::MaxLink));
+        assert_eq!(
+            path_set_value(&mut root, "/A/C", Dictionary::new()),
+            Err(SCError::MaxLink)
+        );
+        assert_eq!(path_get_value(&root, "/A/C"), None);
+    }
+
+    #[test]
+    fn create_child() {
+        let mut root = dictionary(&[("L", link_to("/T")), ("T", link_to("/L"))]);
+        assert_eq!(
+            path_create_child(&mut root, "/Sets", "X"),
+            Ok("/Sets/X".to_owned())
+        );
+        assert_eq!(path_get_value(&root, "/Sets/X"), Some(&Dictionary::new()));
+        assert_eq!(
+            path_create_child(&mut root, "/L", "X"),
+            Err(SCError::Failed)
+        );
+    }
+
+    #[test]
+    fn build_paths() {
+        assert_eq!(PrefsPath::root().as_str(), "/");
+        assert_eq!(
+            PrefsPath::network_service_entity("A", &Entity::DNS)
+                .unwrap()
+                .as_str(),
+            "/NetworkServices/A/DNS"
+        );
+        assert_eq!(
+            PrefsPath::set_service("S", "A").unwrap().to_string(),
+            "/Sets/S/Network/Service/A"
+        );
+        assert_eq!(
+            PrefsPath::set_global_entity("S", &Entity::IPv4)
+                .unwrap()
+                .as_str(),
+            "/Sets/S/Network/Global/IPv4"
+        );
+        assert_eq!(
+            PrefsPath::set_interface("S", "en0").unwrap().as_str(),
+            "/Sets/S/Network/Interface/en0"
+        );
+        assert_eq!(PrefsPath::system().as_str(), "/System");
+        assert_eq!(
+            PrefsPath::set("a/b"),
+            Err(ParsePathError::InvalidSegment("a/b".to_owned()))
+        );
+        assert_eq!(
+            PrefsPath::network_service(""),
+            Err(ParsePathError::InvalidSegment(String::new()))
+        );
+    }
+
+    #[test]
+    fn parse_paths() {
+        let path = "/Sets/S/Network/Service/A/DNS"
+            .parse::<PrefsPath>()
+            .unwrap();
+        assert_eq!(path.set_id(), Some("S"));
+        assert_eq!(path.service_id(), Some("A"));
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            ["Sets", "S", "Network", "Service", "A", "DNS"]
+        );
+        assert_eq!(
+            path.parent(),
+            Some(PrefsPath::set_service("S", "A").unwrap())
+        );
+        assert_eq!(PrefsPath::sets().parent(), Some(PrefsPath::root()));
+        assert_eq!(PrefsPath::root().parent(), None);
+        assert_eq!(PrefsPath::root().segments().count(), 0);
+        assert_eq!("/".parse(), Ok(PrefsPath::root()));
+        assert_eq!(
+            PrefsPath::network_service("A").unwrap().service_id(),
+            Some("A")
+        );
+        assert_eq!(PrefsPath::network_service("A").unwrap().set_id(), None);
+
+        assert_eq!(
+            "Sets".parse::<PrefsPath>(),
+            Err(ParsePathError::NotAbsolute("Sets".to_owned()))
+        );
+        for invalid in ["//", "/Sets//S", "/Sets/"] {
+            assert_eq!(
+                invalid.parse::<PrefsPath>(),
+                Err(ParsePathError::InvalidSegment(String::new())),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_links() {
+        use super::super::MemoryPreferences;
+
+        let mut prefs = MemoryPreferences::new();
+        let service = PrefsPath::network_service("A").unwrap();
+        prefs
+            .path_set_value(service.as_str(), &Dictionary::new())
+            .unwrap();
+        let set_service = PrefsPath::set_service("S", "A").unwrap();
+        prefs
+            .path_set_link(set_service.as_str(), service.as_str())
+            .unwrap();
+        prefs.path_set_link("/Current", "/Sets/S").unwrap();
+
+        assert_eq!(set_service.resolve(&prefs), Ok(service.clone()));
+        let dns = "/Current/Network/Service/A/DNS"
+            .parse::<PrefsPath>()
+            .unwrap();
+        assert_eq!(
+            dns.resolve(&prefs),
+            PrefsPath::network_service_entity("A", &Entity::DNS).map_err(|_| SCError::Failed)
+        );
+        assert_eq!(service.resolve(&prefs), Ok(service.clone()));
+        let missing = "/Missing/A".parse::<PrefsPath>().unwrap();
+        assert_eq!(missing.resolve(&prefs), Ok(missing));
+    }
+
+    #[test]
+    fn resolve_link_cycles() {
+        let links = |path: &PrefsPath| match path.as_str() {
+            "/A" => Some("/B".to_owned()),
+            "/B" => Some("/A".to_owned()),
+            "/C" => Some("/C/D".to_owned()),
+            "/E" => Some("E".to_owned()),
+            _ => None,
+        };
+        for (path, error) in [
+            ("/A/X", SCError::MaxLink),
+            ("/C", SCError::MaxLink),
+            ("/E", SCError::Failed),
+        ] {
+            assert_eq!(
+                path.parse::<PrefsPath>().unwrap().resolve_links(links),
+                Err(error),
+                "{}",
+                path
+            );
+        }
+
+        // A chain of distinct links ends at the link limit.
+        let chain = |path: &PrefsPath| {
+            let n = path.as_str()[2..].parse::<usize>().ok()?;
+            Some(format!("/L{}", n + 1))
+        };
+        assert_eq!(
+            "/L0".parse::<PrefsPath>().unwrap().resolve_links(chain),
+            Err(SCError::MaxLink)
+        );
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::base::TCFType;
+
+        let keys = unsafe {
+            [
+                (LINK, kSCResvLink),
+                (NETWORK_SERVICES, kSCPrefNetworkServices),
+                (SETS, kSCPrefSets),
+                (SYSTEM, kSCPrefSystem),
+                (NETWORK, kSCCompNetwork),
+                (SERVICE, kSCCompService),
+                (GLOBAL, kSCCompGlobal),
+                (INTERFACE, kSCCompInterface),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use super::{PreferencesBackend, SCPreferences};
+use crate::{
+    error::{to_result, SCError},
+    sys::preferences::{SCPreferencesGetSignature, SCPreferencesLock, SCPreferencesUnlock},
+};
+use core_foundation::{
+    base::{Boolean, TCFType},
+    data::CFData,
+};
+use std::fmt;
+
+/// Identifies a version of the stored preferences.
+///
+/// The content is opaque, signatures are only meaningful when compared with each other. A session
+/// keeps the signature of the version it read until it commits or is synchronized, see
+/// [`SCPreferences::signature`].
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PreferencesSignature(Vec<u8>);
+
+impl PreferencesSignature {
+    pub(crate) fn new(bytes: Vec<u8>) -> Self {
+        PreferencesSignature(bytes)
+    }
+
+    /// Returns the bytes of the signature.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for PreferencesSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PreferencesSignature(")?;
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl SCPreferences {
+    /// Returns the signature of the stored preferences as read by this session. Or `None` if the
+    /// preferences cannot be accessed.
+    ///
+    /// The signature changes when the session commits, or when it is synchronized after another
+    /// process committed. Use [`has_changed_since`](Self::has_changed_since) to find out whether
+    /// another process committed in the meantime.
+    ///
+    /// See [`SCPreferencesGetSignature`] for details.
+    ///
+    /// [`SCPreferencesGetSignature`]: https://developer.apple.com/documentation/systemconfiguration/scpreferencesgetsignature(_:)?language=objc
+    pub fn signature(&self) -> Option<PreferencesSignature> {
+        unsafe {
+            let data_ref = SCPreferencesGetSignature(self.0);
+            if !data_ref.is_null() {
+                Some(PreferencesSignature::new(
+                    CFData::wrap_under_get_rule(data_ref).bytes().to_vec(),
+                ))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns `true` if the stored preferences are no longer the version identified by
+    /// `signature`, because this or any other process committed since the signature was taken.
+    ///
+    /// The stored preferences are checked by briefly taking the lock, as [`SCPreferencesLock`]
+    /// fails with [`SCError::Stale`] once another process committed. A session in that state
+    /// reports a change, as reading the stored signature would discard its uncommitted changes.
+    /// The lock is not waited for, so this fails with [`SCError::PrefsBusy`] while another
+    /// process holds it, and like [`lock`](Self::lock) otherwise.
+    ///
+    /// [`SCPreferencesLock`]: https://developer.apple.com/documentation/systemconfiguration/scpreferenceslock(_:_:)?language=objc
+    pub fn has_changed_since(&mut self, signature: &PreferencesSignature) -> Result<bool, SCError> {
+        match to_result(unsafe { SCPreferencesLock(self.0, false as Boolean) }) {
+            Ok(()) => {
+                let changed = self.signature().as_ref() != Some(signature);
+                to_result(unsafe { SCPreferencesUnlock(self.0) })?;
+                Ok(changed)
+            }
+            // This session holds the lock, so no other process committed since it read the
+            // preferences.
+            Err(SCError::Locked) => Ok(self.signature().as_ref() != Some(signature)),
+            Err(SCError::Stale) => Ok(true),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Commits the changes made in this session, provided the session read the version of the
+    /// preferences identified by `signature` and no other process committed since.
+    ///
+    /// Fails with [`SCError::Stale`] if the preferences changed, in which case nothing is written.
+    /// The check is made with the lock held, see
+    /// [`PreferencesBackend::commit_changes_if_unchanged`].
+    pub fn commit_changes_if_unchanged(
+        &mut self,
+        signature: &PreferencesSignature,
+    ) -> Result<(), SCError> {
+        PreferencesBackend::commit_changes_if_unchanged(self, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::MemoryPreferences, *};
+
+    #[test]
+    fn detects_changes_by_other_sessions() {
+        let mut prefs = MemoryPreferences::new();
+        let signature = prefs.signature().unwrap();
+        prefs.set("Key", &1.into()).unwrap();
+        assert_eq!(prefs.has_changed_since(&signature), Ok(false));
+
+        let mut other = prefs.session();
+        other.set("Key", &2.into()).unwrap();
+        other.commit_changes().unwrap();
+        // The other commit is seen without synchronizing, and committing this session would
+        // clobber it.
+        assert_eq!(prefs.has_changed_since(&signature), Ok(true));
+        assert_eq!(prefs.signature().as_ref(), Some(&signature));
+        assert_eq!(
+            prefs.commit_changes_if_unchanged(&signature),
+            Err(SCError::Stale)
+        );
+        assert!(!prefs.is_locked());
+        assert_eq!(prefs.stored_values().get("Key"), Some(&2.into()));
+
+        prefs.synchronize();
+        let signature = prefs.signature().unwrap();
+        assert_eq!(prefs.has_changed_since(&signature), Ok(false));
+        prefs.set("Key", &3.into()).unwrap();
+        prefs.commit_changes_if_unchanged(&signature).unwrap();
+        assert!(!prefs.is_locked());
+        assert_eq!(prefs.stored_values().get("Key"), Some(&3.into()));
+        assert_eq!(prefs.has_changed_since(&signature), Ok(true));
+    }
+
+    #[test]
+    fn signature_of_other_session() {
+        let mut prefs = MemoryPreferences::new();
+        prefs.set("Key", &1.into()).unwrap();
+        let signature = prefs.signature().unwrap();
+        prefs.commit_changes().unwrap();
+        let mut other = prefs.session();
+        assert_eq!(other.has_changed_since(&signature), Ok(true));
+        other.set("Key", &2.into()).unwrap();
+        assert_eq!(
+            other.commit_changes_if_unchanged(&signature),
+            Err(SCError::Stale)
+        );
+        assert_eq!(prefs.stored_values().get("Key"), Some(&1.into()));
+    }
+
+    #[test]
+    fn commit_if_unchanged_with_lock_held() {
+        let mut prefs = MemoryPreferences::new();
+        let signature = prefs.signature().unwrap();
+        prefs.lock(true).unwrap();
+        prefs.set("Key", &1.into()).unwrap();
+        assert_eq!(prefs.has_changed_since(&signature), Ok(false));
+        prefs.commit_changes_if_unchanged(&signature).unwrap();
+        assert!(prefs.is_locked());
+        assert_eq!(
+            prefs.commit_changes_if_unchanged(&signature),
+            Err(SCError::Stale)
+        );
+        assert!(prefs.is_locked());
+        prefs.unlock().unwrap();
+    }
+
+    #[test]
+    fn busy_while_other_session_holds_lock() {
+        let mut prefs = MemoryPreferences::new();
+        let signature = prefs.signature().unwrap();
+        let mut other = prefs.session();
+        other.lock(false).unwrap();
+        assert_eq!(prefs.has_changed_since(&signature), Err(SCError::PrefsBusy));
+        other.unlock().unwrap();
+        assert_eq!(prefs.has_changed_since(&signature), Ok(false));
+    }
+}
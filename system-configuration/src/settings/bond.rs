@@ -0,0 +1,97 @@
+use super::{ConfigError, DictionaryReader, DictionaryWriter, SettingsModel};
+use crate::plist::Dictionary;
+
+/// How the members of an Ethernet bond are aggregated, the `kSCBondMode*` values.
+///
+/// The mode is not one of the [`BondOptions`], the framework stores it beside them. Read and
+/// change it with `SCBondInterface::mode` and `SCBondInterface::set_mode`, which need the
+/// `private` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BondMode {
+    /// `kSCBondModeLACP`, the link aggregation is negotiated with the peer using LACP.
+    Lacp,
+    /// `kSCBondModeStatic`, all members are always aggregated.
+    Static,
+    /// A mode not known to this crate.
+    Other(i64),
+}
+
+impl From<i64> for BondMode {
+    fn from(mode: i64) -> Self {
+        match mode {
+            0 => BondMode::Lacp,
+            1 => BondMode::Static,
+            other => BondMode::Other(other),
+        }
+    }
+}
+
+impl From<BondMode> for i64 {
+    fn from(mode: BondMode) -> Self {
+        match mode {
+            BondMode::Lacp => 0,
+            BondMode::Static => 1,
+            BondMode::Other(other) => other,
+        }
+    }
+}
+
+/// Options of an Ethernet bond, the dictionary set with [`SCBondInterface::set_bond_options`].
+///
+/// The framework defines no option keys of its own, so every key is kept in [`extra`]. The
+/// [`BondMode`] is not an option.
+///
+/// [`SCBondInterface::set_bond_options`]: crate::network_configuration::SCBondInterface::set_bond_options
+/// [`extra`]: #structfield.extra
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BondOptions {
+    /// The keys of the options, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl SettingsModel for BondOptions {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let reader = DictionaryReader::new(dictionary);
+        Ok(BondOptions {
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        DictionaryWriter::new(&self.extra).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plist::PlistValue;
+
+    #[test]
+    fn round_trip() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert("Custom".to_owned(), "value".into());
+        dictionary.insert("Mode".to_owned(), PlistValue::Integer(1));
+
+        let options = BondOptions::from_plist(&dictionary).unwrap();
+        assert_eq!(options.extra, dictionary);
+        assert_eq!(options.validate(), Ok(()));
+        assert_eq!(options.to_plist(), dictionary);
+        assert!(BondOptions::default().to_plist().is_empty());
+    }
+
+    #[test]
+    fn modes() {
+        for (mode, value) in [
+            (BondMode::Lacp, 0),
+            (BondMode::Static, 1),
+            (BondMode::Other(7), 7),
+        ] {
+            assert_eq!(BondMode::from(value), mode);
+            assert_eq!(i64::from(mode), value);
+        }
+    }
+}
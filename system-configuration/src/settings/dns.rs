@@ -0,0 +1,486 @@
+use super::{
+    is_contiguous_mask, validate_domain_name, ConfigError, DictionaryReader, DictionaryWriter,
+    SettingsModel,
+};
+use crate::plist::Dictionary;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// `kSCPropNetDNSDomainName`.
+const DOMAIN_NAME: &str = "DomainName";
+/// `kSCPropNetDNSOptions`.
+const OPTIONS: &str = "Options";
+/// `kSCPropNetDNSSearchDomains`.
+const SEARCH_DOMAINS: &str = "SearchDomains";
+/// `kSCPropNetDNSSearchOrder`.
+const SEARCH_ORDER: &str = "SearchOrder";
+/// `kSCPropNetDNSServerAddresses`.
+const SERVER_ADDRESSES: &str = "ServerAddresses";
+/// `kSCPropNetDNSServerPort`.
+const SERVER_PORT: &str = "ServerPort";
+/// `kSCPropNetDNSServerTimeout`.
+const SERVER_TIMEOUT: &str = "ServerTimeout";
+/// `kSCPropNetDNSSortList`.
+const SORT_LIST: &str = "SortList";
+/// `kSCPropNetDNSSupplementalMatchDomains`.
+const SUPPLEMENTAL_MATCH_DOMAINS: &str = "SupplementalMatchDomains";
+/// `kSCPropNetDNSSupplementalMatchOrders`.
+const SUPPLEMENTAL_MATCH_ORDERS: &str = "SupplementalMatchOrders";
+
+/// DNS configuration, the `kSCEntNetDNS` entity.
+///
+/// Every field mirrors one of the `kSCPropNetDNS*` keys and is `None` when the key is absent.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DnsConfig {
+    /// `kSCPropNetDNSDomainName`, the default domain name.
+    pub domain_name: Option<String>,
+    /// `kSCPropNetDNSSearchDomains`, the domains appended to unqualified names.
+    pub search_domains: Option<Vec<String>>,
+    /// `kSCPropNetDNSSearchOrder`, the order of this resolver relative to other resolvers.
+    pub search_order: Option<i64>,
+    /// `kSCPropNetDNSServerAddresses`, the name servers to query.
+    pub server_addresses: Option<Vec<ServerAddress>>,
+    /// `kSCPropNetDNSServerPort`, the port the name servers listen on.
+    pub server_port: Option<u16>,
+    /// `kSCPropNetDNSServerTimeout`, the query timeout in seconds.
+    pub server_timeout: Option<u32>,
+    /// `kSCPropNetDNSSortList`, the preferred ordering of returned addresses.
+    pub sort_list: Option<Vec<SortListEntry>>,
+    /// `kSCPropNetDNSSupplementalMatchDomains`, the domains this resolver is used for in addition
+    /// to the default resolver. An empty string matches all domains.
+    pub supplemental_match_domains: Option<Vec<String>>,
+    /// `kSCPropNetDNSSupplementalMatchOrders`, the order of each supplemental match domain.
+    pub supplemental_match_orders: Option<Vec<i64>>,
+    /// `kSCPropNetDNSOptions`, resolver options in `resolv.conf` syntax.
+    pub options: Option<String>,
+    /// Keys not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl SettingsModel for DnsConfig {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(DnsConfig {
+            domain_name: reader.string(DOMAIN_NAME)?,
+            search_domains: reader.string_array(SEARCH_DOMAINS)?,
+            search_order: reader.integer(SEARCH_ORDER)?,
+            server_addresses: reader.parsed_array(SERVER_ADDRESSES)?,
+            server_port: reader.bounded_integer(SERVER_PORT)?,
+            server_timeout: reader.bounded_integer(SERVER_TIMEOUT)?,
+            sort_list: reader.parsed_array(SORT_LIST)?,
+            supplemental_match_domains: reader.string_array(SUPPLEMENTAL_MATCH_DOMAINS)?,
+            supplemental_match_orders: reader.integer_array(SUPPLEMENTAL_MATCH_ORDERS)?,
+            options: reader.string(OPTIONS)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        writer.string(DOMAIN_NAME, &self.domain_name);
+        writer.string_array(SEARCH_DOMAINS, &self.search_domains);
+        writer.integer(SEARCH_ORDER, self.search_order);
+        writer.string_array(SERVER_ADDRESSES, &self.server_addresses);
+        writer.integer(SERVER_PORT, self.server_port);
+        writer.integer(SERVER_TIMEOUT, self.server_timeout);
+        writer.string_array(SORT_LIST, &self.sort_list);
+        writer.string_array(SUPPLEMENTAL_MATCH_DOMAINS, &self.supplemental_match_domains);
+        writer.integer_array(SUPPLEMENTAL_MATCH_ORDERS, &self.supplemental_match_orders);
+        writer.string(OPTIONS, &self.options);
+        writer.finish()
+    }
+
+    /// Checks that all domain names are valid, that the port and timeout are non-zero, that sort
+    /// list masks are valid subnet masks and that every supplemental match domain has an order.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(domain_name) = &self.domain_name {
+            validate_domain_name(DOMAIN_NAME, domain_name)?;
+        }
+        for domain in self.search_domains.iter().flatten() {
+            validate_domain_name(SEARCH_DOMAINS, domain)?;
+        }
+        for domain in self.supplemental_match_domains.iter().flatten() {
+            if !domain.is_empty() {
+                validate_domain_name(SUPPLEMENTAL_MATCH_DOMAINS, domain)?;
+            }
+        }
+        if self.server_port == Some(0) {
+            return Err(ConfigError::invalid_value(
+                SERVER_PORT,
+                "port must not be 0",
+            ));
+        }
+        if self.server_timeout == Some(0) {
+            return Err(ConfigError::invalid_value(
+                SERVER_TIMEOUT,
+                "timeout must not be 0",
+            ));
+        }
+        for entry in self.sort_list.iter().flatten() {
+            if !entry.mask.map_or(true, is_contiguous_mask) {
+                return Err(ConfigError::invalid_value(
+                    SORT_LIST,
+                    format!("{} does not have a valid subnet mask", entry),
+                ));
+            }
+        }
+        if let Some(orders) = &self.supplemental_match_orders {
+            let domains = self.supplemental_match_domains.as_ref().map_or(0, Vec::len);
+            if orders.len() != domains {
+                return Err(ConfigError::invalid_value(
+                    SUPPLEMENTAL_MATCH_ORDERS,
+                    format!(
+                        "has {} orders for {} supplemental match domains",
+                        orders.len(),
+                        domains
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The address of a name server, written as `address[%scope]`. IPv6 link-local servers carry
+/// the interface they are reached through as their scope, as in `fe80::1%en0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerAddress {
+    /// The address of the name server.
+    pub address: IpAddr,
+    /// The name of the interface the name server is reached through. Only IPv6 addresses can
+    /// have a scope.
+    pub scope: Option<String>,
+}
+
+impl From<IpAddr> for ServerAddress {
+    fn from(address: IpAddr) -> Self {
+        ServerAddress {
+            address,
+            scope: None,
+        }
+    }
+}
+
+impl FromStr for ServerAddress {
+    type Err = ParseServerAddressError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        match address.split_once('%') {
+            Some((address, scope)) => {
+                if scope.is_empty() {
+                    return Err(ParseServerAddressError::EmptyScope);
+                }
+                let address = address.parse::<Ipv6Addr>()?;
+                Ok(ServerAddress {
+                    address: IpAddr::V6(address),
+                    scope: Some(scope.to_owned()),
+                })
+            }
+            None => Ok(ServerAddress::from(address.parse::<IpAddr>()?)),
+        }
+    }
+}
+
+impl Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.scope {
+            Some(scope) => write!(f, "{}%{}", self.address, scope),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+/// Failure to parse a [`ServerAddress`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseServerAddressError {
+    /// The address is not a valid IP address, or not an IPv6 address while it has a scope.
+    InvalidAddress(AddrParseError),
+    /// The scope after the `%` is empty.
+    EmptyScope,
+}
+
+impl From<AddrParseError> for ParseServerAddressError {
+    fn from(error: AddrParseError) -> Self {
+        Self::InvalidAddress(error)
+    }
+}
+
+impl Display for ParseServerAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAddress(error) => write!(f, "Invalid server address: {}", error),
+            Self::EmptyScope => write!(f, "Empty server address scope"),
+        }
+    }
+}
+
+impl Error for ParseServerAddressError {}
+
+/// An entry of the DNS sort list, an address with an optional subnet mask, written as
+/// `address[/mask]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SortListEntry {
+    /// The network address.
+    pub address: Ipv4Addr,
+    /// The subnet mask. When absent the resolver uses the natural mask of the address class.
+    pub mask: Option<Ipv4Addr>,
+}
+
+impl FromStr for SortListEntry {
+    type Err = AddrParseError;
+
+    fn from_str(entry: &str) -> Result<Self, Self::Err> {
+        match entry.split_once('/') {
+            Some((address, mask)) => Ok(SortListEntry {
+                address: address.parse()?,
+                mask: Some(mask.parse()?),
+            }),
+            None => Ok(SortListEntry {
+                address: entry.parse()?,
+                mask: None,
+            }),
+        }
+    }
+}
+
+impl Display for SortListEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mask {
+            Some(mask) => write!(f, "{}/{}", self.address, mask),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plist::PlistValue;
+
+    fn strings(strings: &[&str]) -> PlistValue {
+        PlistValue::Array(strings.iter().map(|string| (*string).into()).collect())
+    }
+
+    fn full_dictionary() -> Dictionary {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(DOMAIN_NAME.to_owned(), "example.com".into());
+        dictionary.insert(
+            SEARCH_DOMAINS.to_owned(),
+            strings(&["a.example.com", "example.org"]),
+        );
+        dictionary.insert(SEARCH_ORDER.to_owned(), PlistValue::Integer(200_000));
+        dictionary.insert(
+            SERVER_ADDRESSES.to_owned(),
+            strings(&["8.8.8.8", "2001:4860:4860::8888", "fe80::1%en0"]),
+        );
+        dictionary.insert(SERVER_PORT.to_owned(), PlistValue::Integer(5353));
+        dictionary.insert(SERVER_TIMEOUT.to_owned(), PlistValue::Integer(5));
+        dictionary.insert(
+            SORT_LIST.to_owned(),
+            strings(&["10.0.0.0/255.0.0.0", "192.168.1.1"]),
+        );
+        dictionary.insert(
+            SUPPLEMENTAL_MATCH_DOMAINS.to_owned(),
+            strings(&["", "corp"]),
+        );
+        dictionary.insert(
+            SUPPLEMENTAL_MATCH_ORDERS.to_owned(),
+            PlistValue::Array(vec![1.into(), 2.into()]),
+        );
+        dictionary.insert(OPTIONS.to_owned(), "ndots:2".into());
+        dictionary.insert("ConfirmedServiceID".to_owned(), "ABC".into());
+        dictionary.insert("__MAGIC__".to_owned(), PlistValue::Array(vec![true.into()]));
+        dictionary
+    }
+
+    #[test]
+    fn round_trip() {
+        let dictionary = full_dictionary();
+        let config = DnsConfig::from_plist(&dictionary).unwrap();
+        assert_eq!(config.domain_name.as_deref(), Some("example.com"));
+        assert_eq!(
+            config.server_addresses,
+            Some(vec![
+                "8.8.8.8".parse().unwrap(),
+                "2001:4860:4860::8888".parse().unwrap(),
+                ServerAddress {
+                    address: "fe80::1".parse().unwrap(),
+                    scope: Some("en0".to_owned()),
+                },
+            ])
+        );
+        assert_eq!(config.server_port, Some(5353));
+        assert_eq!(
+            config.sort_list.as_ref().unwrap()[0],
+            SortListEntry {
+                address: Ipv4Addr::new(10, 0, 0, 0),
+                mask: Some(Ipv4Addr::new(255, 0, 0, 0)),
+            }
+        );
+        assert_eq!(config.extra.len(), 2);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.to_plist(), dictionary);
+
+        assert_eq!(
+            DnsConfig::from_plist(&Dictionary::new()),
+            Ok(DnsConfig::default())
+        );
+        assert!(DnsConfig::default().to_plist().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let config = DnsConfig::from_plist(&full_dictionary()).unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<DnsConfig>(&json).unwrap(), config);
+        assert_eq!(
+            serde_json::from_str::<DnsConfig>(r#"{"domain_name": "example.com"}"#).unwrap(),
+            DnsConfig {
+                domain_name: Some("example.com".to_owned()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            (SERVER_ADDRESSES, strings(&["8.8.8"])),
+            (SERVER_ADDRESSES, "8.8.8.8".into()),
+            (SERVER_PORT, PlistValue::Integer(65536)),
+            (SERVER_PORT, "53".into()),
+            (SERVER_TIMEOUT, PlistValue::Integer(-1)),
+            (SORT_LIST, strings(&["10.0.0.0/8"])),
+            (SEARCH_ORDER, PlistValue::Real(1.5)),
+            (DOMAIN_NAME, PlistValue::Boolean(true)),
+            (SUPPLEMENTAL_MATCH_ORDERS, strings(&["1"])),
+        ];
+        for (key, value) in cases {
+            let mut dictionary = Dictionary::new();
+            dictionary.insert(key.to_owned(), value.clone());
+            let error = DnsConfig::from_plist(&dictionary).unwrap_err();
+            match error {
+                ConfigError::InvalidType { key: error_key, .. }
+                | ConfigError::InvalidValue { key: error_key, .. } => {
+                    assert_eq!(error_key, key, "{:?}", value)
+                }
+                ConfigError::NotAPropertyList => panic!("unexpected error"),
+            }
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let valid = DnsConfig::from_plist(&full_dictionary()).unwrap();
+        let invalid = [
+            DnsConfig {
+                domain_name: Some("bad domain".to_owned()),
+                ..valid.clone()
+            },
+            DnsConfig {
+                search_domains: Some(vec!["".to_owned()]),
+                ..valid.clone()
+            },
+            DnsConfig {
+                server_port: Some(0),
+                ..valid.clone()
+            },
+            DnsConfig {
+                server_timeout: Some(0),
+                ..valid.clone()
+            },
+            DnsConfig {
+                sort_list: Some(vec!["10.0.0.0/255.0.255.0".parse().unwrap()]),
+                ..valid.clone()
+            },
+            DnsConfig {
+                supplemental_match_orders: Some(vec![1]),
+                ..valid.clone()
+            },
+            DnsConfig {
+                supplemental_match_domains: None,
+                ..valid.clone()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn server_addresses() {
+        for address in [
+            "8.8.8.8",
+            "2001:4860:4860::8888",
+            "fe80::1%en0",
+            "fe80::1%utun3",
+        ] {
+            assert_eq!(
+                address.parse::<ServerAddress>().unwrap().to_string(),
+                address
+            );
+        }
+        for address in ["", "8.8.8", "fe80::1%", "8.8.8.8%en0", "%en0", "fe80::1/64"] {
+            assert!(address.parse::<ServerAddress>().is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn sort_list_entries() {
+        for entry in ["10.0.0.0/255.0.0.0", "192.168.1.1", "0.0.0.0/0.0.0.0"] {
+            assert_eq!(entry.parse::<SortListEntry>().unwrap().to_string(), entry);
+        }
+        for entry in [
+            "",
+            "/",
+            "10.0.0.0/",
+            "10.0.0.0/8",
+            "::1",
+            "10.0.0.0/255.0.0.0/1",
+        ] {
+            assert!(entry.parse::<SortListEntry>().is_err(), "{}", entry);
+        }
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::{base::TCFType, string::CFString};
+
+        let keys = unsafe {
+            [
+                (DOMAIN_NAME, kSCPropNetDNSDomainName),
+                (OPTIONS, kSCPropNetDNSOptions),
+                (SEARCH_DOMAINS, kSCPropNetDNSSearchDomains),
+                (SEARCH_ORDER, kSCPropNetDNSSearchOrder),
+                (SERVER_ADDRESSES, kSCPropNetDNSServerAddresses),
+                (SERVER_PORT, kSCPropNetDNSServerPort),
+                (SERVER_TIMEOUT, kSCPropNetDNSServerTimeout),
+                (SORT_LIST, kSCPropNetDNSSortList),
+                (
+                    SUPPLEMENTAL_MATCH_DOMAINS,
+                    kSCPropNetDNSSupplementalMatchDomains,
+                ),
+                (
+                    SUPPLEMENTAL_MATCH_ORDERS,
+                    kSCPropNetDNSSupplementalMatchOrders,
+                ),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
+    }
+}
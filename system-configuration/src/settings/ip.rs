@@ -0,0 +1,722 @@
+use super::{is_contiguous_mask, ConfigError, DictionaryReader, DictionaryWriter, SettingsModel};
+use crate::plist::Dictionary;
+use std::{
+    fmt::{self, Display},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+/// `kSCPropNetIPv4Addresses` and `kSCPropNetIPv6Addresses`.
+const ADDRESSES: &str = "Addresses";
+/// `kSCPropNetIPv4ConfigMethod` and `kSCPropNetIPv6ConfigMethod`.
+const CONFIG_METHOD: &str = "ConfigMethod";
+/// `kSCPropNetIPv4DestAddresses` and `kSCPropNetIPv6DestAddresses`.
+const DEST_ADDRESSES: &str = "DestAddresses";
+/// `kSCPropNetIPv4Router` and `kSCPropNetIPv6Router`.
+const ROUTER: &str = "Router";
+/// `kSCPropNetIPv4BroadcastAddresses`.
+const BROADCAST_ADDRESSES: &str = "BroadcastAddresses";
+/// `kSCPropNetIPv4DHCPClientID`.
+const DHCP_CLIENT_ID: &str = "DHCPClientID";
+/// `kSCPropNetIPv4SubnetMasks`.
+const SUBNET_MASKS: &str = "SubnetMasks";
+/// `kSCPropNetIPv6Flags`.
+const FLAGS: &str = "Flags";
+/// `kSCPropNetIPv6PrefixLength`.
+const PREFIX_LENGTH: &str = "PrefixLength";
+
+/// How IPv4 is configured on a service, the `kSCValNetIPv4ConfigMethod*` values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ipv4ConfigMethod {
+    /// `kSCValNetIPv4ConfigMethodAutomatic`.
+    Automatic,
+    /// `kSCValNetIPv4ConfigMethodBOOTP`.
+    Bootp,
+    /// `kSCValNetIPv4ConfigMethodDHCP`.
+    Dhcp,
+    /// `kSCValNetIPv4ConfigMethodINFORM`, a manual address with the rest of the configuration
+    /// from DHCP.
+    Inform,
+    /// `kSCValNetIPv4ConfigMethodLinkLocal`.
+    LinkLocal,
+    /// `kSCValNetIPv4ConfigMethodManual`.
+    Manual,
+    /// `kSCValNetIPv4ConfigMethodPPP`.
+    Ppp,
+    /// A method not known to this crate.
+    Other(String),
+}
+
+impl Ipv4ConfigMethod {
+    /// Returns the string this method is represented by in the configuration.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Ipv4ConfigMethod::Automatic => "Automatic",
+            Ipv4ConfigMethod::Bootp => "BOOTP",
+            Ipv4ConfigMethod::Dhcp => "DHCP",
+            Ipv4ConfigMethod::Inform => "INFORM",
+            Ipv4ConfigMethod::LinkLocal => "LinkLocal",
+            Ipv4ConfigMethod::Manual => "Manual",
+            Ipv4ConfigMethod::Ppp => "PPP",
+            Ipv4ConfigMethod::Other(method) => method,
+        }
+    }
+}
+
+impl From<&str> for Ipv4ConfigMethod {
+    fn from(method: &str) -> Self {
+        match method {
+            "Automatic" => Ipv4ConfigMethod::Automatic,
+            "BOOTP" => Ipv4ConfigMethod::Bootp,
+            "DHCP" => Ipv4ConfigMethod::Dhcp,
+            "INFORM" => Ipv4ConfigMethod::Inform,
+            "LinkLocal" => Ipv4ConfigMethod::LinkLocal,
+            "Manual" => Ipv4ConfigMethod::Manual,
+            "PPP" => Ipv4ConfigMethod::Ppp,
+            other => Ipv4ConfigMethod::Other(other.to_owned()),
+        }
+    }
+}
+
+impl Display for Ipv4ConfigMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serde_as_str!(Ipv4ConfigMethod);
+
+/// How IPv6 is configured on a service, the `kSCValNetIPv6ConfigMethod*` values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ipv6ConfigMethod {
+    /// `kSCValNetIPv6ConfigMethodAutomatic`.
+    Automatic,
+    /// `kSCValNetIPv6ConfigMethodLinkLocal`.
+    LinkLocal,
+    /// `kSCValNetIPv6ConfigMethodManual`.
+    Manual,
+    /// `kSCValNetIPv6ConfigMethodRouterAdvertisement`.
+    RouterAdvertisement,
+    /// `kSCValNetIPv6ConfigMethod6to4`.
+    SixToFour,
+    /// A method not known to this crate.
+    Other(String),
+}
+
+impl Ipv6ConfigMethod {
+    /// Returns the string this method is represented by in the configuration.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Ipv6ConfigMethod::Automatic => "Automatic",
+            Ipv6ConfigMethod::LinkLocal => "LinkLocal",
+            Ipv6ConfigMethod::Manual => "Manual",
+            Ipv6ConfigMethod::RouterAdvertisement => "RouterAdvertisement",
+            Ipv6ConfigMethod::SixToFour => "6to4",
+            Ipv6ConfigMethod::Other(method) => method,
+        }
+    }
+}
+
+impl From<&str> for Ipv6ConfigMethod {
+    fn from(method: &str) -> Self {
+        match method {
+            "Automatic" => Ipv6ConfigMethod::Automatic,
+            "LinkLocal" => Ipv6ConfigMethod::LinkLocal,
+            "Manual" => Ipv6ConfigMethod::Manual,
+            "RouterAdvertisement" => Ipv6ConfigMethod::RouterAdvertisement,
+            "6to4" => Ipv6ConfigMethod::SixToFour,
+            other => Ipv6ConfigMethod::Other(other.to_owned()),
+        }
+    }
+}
+
+impl Display for Ipv6ConfigMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serde_as_str!(Ipv6ConfigMethod);
+
+/// IPv4 configuration, the `kSCEntNetIPv4` entity.
+///
+/// Every field mirrors one of the `kSCPropNetIPv4*` keys and is `None` when the key is absent.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Ipv4Config {
+    /// `kSCPropNetIPv4ConfigMethod`.
+    pub config_method: Option<Ipv4ConfigMethod>,
+    /// `kSCPropNetIPv4Addresses`.
+    pub addresses: Option<Vec<Ipv4Addr>>,
+    /// `kSCPropNetIPv4SubnetMasks`, one for each address.
+    pub subnet_masks: Option<Vec<Ipv4Addr>>,
+    /// `kSCPropNetIPv4Router`.
+    pub router: Option<Ipv4Addr>,
+    /// `kSCPropNetIPv4DestAddresses`, the remote addresses of point to point links.
+    pub dest_addresses: Option<Vec<Ipv4Addr>>,
+    /// `kSCPropNetIPv4BroadcastAddresses`.
+    pub broadcast_addresses: Option<Vec<Ipv4Addr>>,
+    /// `kSCPropNetIPv4DHCPClientID`.
+    pub dhcp_client_id: Option<String>,
+    /// Keys not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl Ipv4Config {
+    /// Configuration obtaining everything from DHCP.
+    pub fn dhcp() -> Self {
+        Ipv4Config {
+            config_method: Some(Ipv4ConfigMethod::Dhcp),
+            ..Default::default()
+        }
+    }
+
+    /// Static configuration with a single address.
+    pub fn manual(address: Ipv4Addr, subnet_mask: Ipv4Addr, router: Option<Ipv4Addr>) -> Self {
+        Ipv4Config {
+            config_method: Some(Ipv4ConfigMethod::Manual),
+            addresses: Some(vec![address]),
+            subnet_masks: Some(vec![subnet_mask]),
+            router,
+            ..Default::default()
+        }
+    }
+}
+
+impl SettingsModel for Ipv4Config {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(Ipv4Config {
+            config_method: reader
+                .string(CONFIG_METHOD)?
+                .map(|method| Ipv4ConfigMethod::from(method.as_str())),
+            addresses: reader.parsed_array(ADDRESSES)?,
+            subnet_masks: reader.parsed_array(SUBNET_MASKS)?,
+            router: reader.parsed(ROUTER)?,
+            dest_addresses: reader.parsed_array(DEST_ADDRESSES)?,
+            broadcast_addresses: reader.parsed_array(BROADCAST_ADDRESSES)?,
+            dhcp_client_id: reader.string(DHCP_CLIENT_ID)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        writer.formatted(CONFIG_METHOD, &self.config_method);
+        writer.string_array(ADDRESSES, &self.addresses);
+        writer.string_array(SUBNET_MASKS, &self.subnet_masks);
+        writer.formatted(ROUTER, &self.router);
+        writer.string_array(DEST_ADDRESSES, &self.dest_addresses);
+        writer.string_array(BROADCAST_ADDRESSES, &self.broadcast_addresses);
+        writer.string(DHCP_CLIENT_ID, &self.dhcp_client_id);
+        writer.finish()
+    }
+
+    /// Checks that manual methods have addresses, that there is one valid subnet mask per address,
+    /// which the `Manual` method requires, and that the router is a unicast address other than
+    /// the configured ones, in the subnet of one of the addresses when there are subnet masks.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let addresses = self.addresses.as_deref().unwrap_or_default();
+        if matches!(
+            self.config_method,
+            Some(Ipv4ConfigMethod::Manual | Ipv4ConfigMethod::Inform)
+        ) && addresses.is_empty()
+        {
+            return Err(ConfigError::invalid_value(
+                ADDRESSES,
+                "manual configuration requires an address",
+            ));
+        }
+        if self.config_method == Some(Ipv4ConfigMethod::Manual) && self.subnet_masks.is_none() {
+            return Err(ConfigError::invalid_value(
+                SUBNET_MASKS,
+                "manual configuration requires a subnet mask",
+            ));
+        }
+        if let Some(masks) = &self.subnet_masks {
+            if masks.len() != addresses.len() {
+                return Err(ConfigError::invalid_value(
+                    SUBNET_MASKS,
+                    format!(
+                        "has {} masks for {} addresses",
+                        masks.len(),
+                        addresses.len()
+                    ),
+                ));
+            }
+            if let Some(mask) = masks.iter().find(|mask| !is_contiguous_mask(**mask)) {
+                return Err(ConfigError::invalid_value(
+                    SUBNET_MASKS,
+                    format!("{} is not a valid subnet mask", mask),
+                ));
+            }
+        }
+        if let Some(router) = self.router {
+            if router.is_unspecified()
+                || router.is_broadcast()
+                || router.is_multicast()
+                || addresses.contains(&router)
+            {
+                return Err(ConfigError::invalid_value(
+                    ROUTER,
+                    format!("{} cannot be a router", router),
+                ));
+            }
+            if let Some(masks) = &self.subnet_masks {
+                let in_subnet = addresses.iter().zip(masks).any(|(address, mask)| {
+                    let mask = u32::from(*mask);
+                    u32::from(*address) & mask == u32::from(router) & mask
+                });
+                if !in_subnet {
+                    return Err(ConfigError::invalid_value(
+                        ROUTER,
+                        format!("{} is not in the subnet of any address", router),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// IPv6 configuration, the `kSCEntNetIPv6` entity.
+///
+/// Every field mirrors one of the `kSCPropNetIPv6*` keys and is `None` when the key is absent.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Ipv6Config {
+    /// `kSCPropNetIPv6ConfigMethod`.
+    pub config_method: Option<Ipv6ConfigMethod>,
+    /// `kSCPropNetIPv6Addresses`.
+    pub addresses: Option<Vec<Ipv6Addr>>,
+    /// `kSCPropNetIPv6PrefixLength`, one for each address.
+    pub prefix_lengths: Option<Vec<u8>>,
+    /// `kSCPropNetIPv6Router`.
+    pub router: Option<Ipv6Addr>,
+    /// `kSCPropNetIPv6DestAddresses`, the remote addresses of point to point links.
+    pub dest_addresses: Option<Vec<Ipv6Addr>>,
+    /// `kSCPropNetIPv6Flags`.
+    pub flags: Option<i64>,
+    /// Keys not covered by the fields above, kept as they are.
+    pub extra: Dictionary,
+}
+
+impl Ipv6Config {
+    /// Configuration obtaining everything automatically.
+    pub fn automatic() -> Self {
+        Ipv6Config {
+            config_method: Some(Ipv6ConfigMethod::Automatic),
+            ..Default::default()
+        }
+    }
+
+    /// Static configuration with a single address.
+    pub fn manual(address: Ipv6Addr, prefix_length: u8, router: Option<Ipv6Addr>) -> Self {
+        Ipv6Config {
+            config_method: Some(Ipv6ConfigMethod::Manual),
+            addresses: Some(vec![address]),
+            prefix_lengths: Some(vec![prefix_length]),
+            router,
+            ..Default::default()
+        }
+    }
+}
+
+impl SettingsModel for Ipv6Config {
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(Ipv6Config {
+            config_method: reader
+                .string(CONFIG_METHOD)?
+                .map(|method| Ipv6ConfigMethod::from(method.as_str())),
+            addresses: reader.parsed_array(ADDRESSES)?,
+            prefix_lengths: reader.bounded_integer_array(PREFIX_LENGTH)?,
+            router: reader.parsed(ROUTER)?,
+            dest_addresses: reader.parsed_array(DEST_ADDRESSES)?,
+            flags: reader.integer(FLAGS)?,
+            extra: reader.finish(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&self.extra);
+        writer.formatted(CONFIG_METHOD, &self.config_method);
+        writer.string_array(ADDRESSES, &self.addresses);
+        writer.integer_array(PREFIX_LENGTH, &self.prefix_lengths);
+        writer.formatted(ROUTER, &self.router);
+        writer.string_array(DEST_ADDRESSES, &self.dest_addresses);
+        writer.integer(FLAGS, self.flags);
+        writer.finish()
+    }
+
+    /// Checks that manual configuration has addresses, that there is one prefix length of at most
+    /// 128 per address, which manual configuration requires, and that the router is a unicast
+    /// address other than the configured ones, either link-local or in the prefix of one of the
+    /// addresses when there are prefix lengths.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let addresses = self.addresses.as_deref().unwrap_or_default();
+        let manual = self.config_method == Some(Ipv6ConfigMethod::Manual);
+        if manual && addresses.is_empty() {
+            return Err(ConfigError::invalid_value(
+                ADDRESSES,
+                "manual configuration requires an address",
+            ));
+        }
+        if manual && self.prefix_lengths.is_none() {
+            return Err(ConfigError::invalid_value(
+                PREFIX_LENGTH,
+                "manual configuration requires a prefix length",
+            ));
+        }
+        if let Some(prefix_lengths) = &self.prefix_lengths {
+            if prefix_lengths.len() != addresses.len() {
+                return Err(ConfigError::invalid_value(
+                    PREFIX_LENGTH,
+                    format!(
+                        "has {} prefix lengths for {} addresses",
+                        prefix_lengths.len(),
+                        addresses.len()
+                    ),
+                ));
+            }
+            if let Some(length) = prefix_lengths.iter().find(|length| **length > 128) {
+                return Err(ConfigError::invalid_value(
+                    PREFIX_LENGTH,
+                    format!("{} is longer than 128", length),
+                ));
+            }
+        }
+        if let Some(router) = self.router {
+            if router.is_unspecified() || router.is_multicast() || addresses.contains(&router) {
+                return Err(ConfigError::invalid_value(
+                    ROUTER,
+                    format!("{} cannot be a router", router),
+                ));
+            }
+            if let Some(prefix_lengths) = &self.prefix_lengths {
+                let link_local = u128::from(router) & ipv6_mask(10) == u128::from(0xfe80u16) << 112;
+                let in_prefix = addresses
+                    .iter()
+                    .zip(prefix_lengths)
+                    .any(|(address, length)| {
+                        let mask = ipv6_mask(*length);
+                        u128::from(*address) & mask == u128::from(router) & mask
+                    });
+                if !link_local && !in_prefix {
+                    return Err(ConfigError::invalid_value(
+                        ROUTER,
+                        format!(
+                            "{} is not link-local or in the prefix of any address",
+                            router
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the IPv6 netmask with `length` leading ones. `length` must be at most 128.
+fn ipv6_mask(length: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(length)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plist::PlistValue;
+
+    fn strings(strings: &[&str]) -> PlistValue {
+        PlistValue::Array(strings.iter().map(|string| (*string).into()).collect())
+    }
+
+    #[test]
+    fn ipv4_round_trip() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(CONFIG_METHOD.to_owned(), "Manual".into());
+        dictionary.insert(ADDRESSES.to_owned(), strings(&["192.168.1.10", "10.0.0.2"]));
+        dictionary.insert(
+            SUBNET_MASKS.to_owned(),
+            strings(&["255.255.255.0", "255.0.0.0"]),
+        );
+        dictionary.insert(ROUTER.to_owned(), "192.168.1.1".into());
+        dictionary.insert(
+            "ARPResolvedHardwareAddress".to_owned(),
+            "00:11:22:33:44:55".into(),
+        );
+
+        let config = Ipv4Config::from_plist(&dictionary).unwrap();
+        assert_eq!(config.config_method, Some(Ipv4ConfigMethod::Manual));
+        assert_eq!(config.router, Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(config.extra.len(), 1);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.to_plist(), dictionary);
+
+        let manual = Ipv4Config::manual(
+            Ipv4Addr::new(192, 168, 1, 10),
+            Ipv4Addr::new(255, 255, 255, 0),
+            Some(Ipv4Addr::new(192, 168, 1, 1)),
+        );
+        assert_eq!(Ipv4Config::from_plist(&manual.to_plist()), Ok(manual));
+    }
+
+    #[test]
+    fn ipv6_round_trip() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(CONFIG_METHOD.to_owned(), "Manual".into());
+        dictionary.insert(ADDRESSES.to_owned(), strings(&["2001:db8::10"]));
+        dictionary.insert(PREFIX_LENGTH.to_owned(), PlistValue::Array(vec![64.into()]));
+        dictionary.insert(ROUTER.to_owned(), "2001:db8::1".into());
+        dictionary.insert(FLAGS.to_owned(), PlistValue::Integer(0));
+        dictionary.insert("EnableCGA".to_owned(), PlistValue::Integer(1));
+
+        let config = Ipv6Config::from_plist(&dictionary).unwrap();
+        assert_eq!(config.prefix_lengths, Some(vec![64]));
+        assert_eq!(config.extra.len(), 1);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.to_plist(), dictionary);
+
+        assert_eq!(
+            Ipv6Config::from_plist(&Ipv6Config::automatic().to_plist()),
+            Ok(Ipv6Config::automatic())
+        );
+    }
+
+    #[test]
+    fn config_methods() {
+        for method in [
+            "Automatic",
+            "BOOTP",
+            "DHCP",
+            "INFORM",
+            "LinkLocal",
+            "Manual",
+            "PPP",
+        ] {
+            let parsed = Ipv4ConfigMethod::from(method);
+            assert!(!matches!(parsed, Ipv4ConfigMethod::Other(_)), "{}", method);
+            assert_eq!(parsed.as_str(), method);
+        }
+        for method in [
+            "Automatic",
+            "LinkLocal",
+            "Manual",
+            "RouterAdvertisement",
+            "6to4",
+        ] {
+            let parsed = Ipv6ConfigMethod::from(method);
+            assert!(!matches!(parsed, Ipv6ConfigMethod::Other(_)), "{}", method);
+            assert_eq!(parsed.as_str(), method);
+        }
+        assert_eq!(
+            Ipv4ConfigMethod::from("Future"),
+            Ipv4ConfigMethod::Other("Future".to_owned())
+        );
+        assert_eq!(Ipv6ConfigMethod::from("Future").to_string(), "Future");
+    }
+
+    #[test]
+    fn ipv4_validation() {
+        let address = Ipv4Addr::new(192, 168, 1, 10);
+        let mask = Ipv4Addr::new(255, 255, 255, 0);
+        let valid = Ipv4Config::manual(address, mask, Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(valid.validate(), Ok(()));
+        assert_eq!(Ipv4Config::dhcp().validate(), Ok(()));
+        let inform = Ipv4Config {
+            config_method: Some(Ipv4ConfigMethod::Inform),
+            subnet_masks: None,
+            ..valid.clone()
+        };
+        assert_eq!(inform.validate(), Ok(()));
+
+        let invalid = [
+            Ipv4Config {
+                addresses: None,
+                subnet_masks: None,
+                ..valid.clone()
+            },
+            Ipv4Config {
+                subnet_masks: Some(vec![mask, mask]),
+                ..valid.clone()
+            },
+            Ipv4Config {
+                subnet_masks: Some(vec![Ipv4Addr::new(255, 0, 255, 0)]),
+                ..valid.clone()
+            },
+            Ipv4Config {
+                router: Some(Ipv4Addr::new(192, 168, 2, 1)),
+                ..valid.clone()
+            },
+            Ipv4Config {
+                subnet_masks: None,
+                ..valid.clone()
+            },
+            Ipv4Config {
+                router: Some(address),
+                ..valid.clone()
+            },
+            Ipv4Config {
+                router: Some(Ipv4Addr::BROADCAST),
+                ..Ipv4Config::dhcp()
+            },
+            Ipv4Config {
+                config_method: Some(Ipv4ConfigMethod::Inform),
+                subnet_masks: None,
+                router: Some(address),
+                ..valid.clone()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn ipv6_validation() {
+        let address = "2001:db8::10".parse().unwrap();
+        let valid = Ipv6Config::manual(address, 64, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(valid.validate(), Ok(()));
+        let link_local_router = Ipv6Config {
+            router: Some("fe80::1".parse().unwrap()),
+            ..valid.clone()
+        };
+        assert_eq!(link_local_router.validate(), Ok(()));
+        for length in [0, 128] {
+            let config = Ipv6Config {
+                prefix_lengths: Some(vec![length]),
+                router: None,
+                ..valid.clone()
+            };
+            assert_eq!(config.validate(), Ok(()));
+        }
+
+        let invalid = [
+            Ipv6Config {
+                addresses: None,
+                prefix_lengths: None,
+                ..valid.clone()
+            },
+            Ipv6Config {
+                prefix_lengths: Some(vec![64, 64]),
+                ..valid.clone()
+            },
+            Ipv6Config {
+                prefix_lengths: Some(vec![129]),
+                ..valid.clone()
+            },
+            Ipv6Config {
+                router: Some("2001:db9::1".parse().unwrap()),
+                ..valid.clone()
+            },
+            Ipv6Config {
+                prefix_lengths: None,
+                ..valid.clone()
+            },
+            Ipv6Config {
+                router: Some(address),
+                ..valid.clone()
+            },
+            Ipv6Config {
+                router: Some("ff02::2".parse().unwrap()),
+                ..Ipv6Config::automatic()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(
+            PREFIX_LENGTH.to_owned(),
+            PlistValue::Array(vec![256.into()]),
+        );
+        assert!(Ipv6Config::from_plist(&dictionary).is_err());
+
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(ROUTER.to_owned(), "2001:db8::1".into());
+        assert!(Ipv4Config::from_plist(&dictionary).is_err());
+
+        let mut dictionary = Dictionary::new();
+        dictionary.insert(SUBNET_MASKS.to_owned(), "255.255.255.0".into());
+        assert!(Ipv4Config::from_plist(&dictionary).is_err());
+    }
+
+    #[test]
+    fn ipv6_masks() {
+        assert_eq!(ipv6_mask(0), 0);
+        assert_eq!(ipv6_mask(128), u128::MAX);
+        assert_eq!(ipv6_mask(64), u128::from(u64::MAX) << 64);
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::{base::TCFType, string::CFString};
+
+        let wrap = |const_str| unsafe { CFString::wrap_under_get_rule(const_str) }.to_string();
+        unsafe {
+            let keys = [
+                (ADDRESSES, kSCPropNetIPv4Addresses),
+                (ADDRESSES, kSCPropNetIPv6Addresses),
+                (CONFIG_METHOD, kSCPropNetIPv4ConfigMethod),
+                (CONFIG_METHOD, kSCPropNetIPv6ConfigMethod),
+                (DEST_ADDRESSES, kSCPropNetIPv4DestAddresses),
+                (DEST_ADDRESSES, kSCPropNetIPv6DestAddresses),
+                (ROUTER, kSCPropNetIPv4Router),
+                (ROUTER, kSCPropNetIPv6Router),
+                (BROADCAST_ADDRESSES, kSCPropNetIPv4BroadcastAddresses),
+                (DHCP_CLIENT_ID, kSCPropNetIPv4DHCPClientID),
+                (SUBNET_MASKS, kSCPropNetIPv4SubnetMasks),
+                (FLAGS, kSCPropNetIPv6Flags),
+                (PREFIX_LENGTH, kSCPropNetIPv6PrefixLength),
+            ];
+            for (key, const_str) in keys {
+                assert_eq!(key, wrap(const_str));
+            }
+            let ipv4_methods = [
+                (
+                    Ipv4ConfigMethod::Automatic,
+                    kSCValNetIPv4ConfigMethodAutomatic,
+                ),
+                (Ipv4ConfigMethod::Bootp, kSCValNetIPv4ConfigMethodBOOTP),
+                (Ipv4ConfigMethod::Dhcp, kSCValNetIPv4ConfigMethodDHCP),
+                (Ipv4ConfigMethod::Inform, kSCValNetIPv4ConfigMethodINFORM),
+                (
+                    Ipv4ConfigMethod::LinkLocal,
+                    kSCValNetIPv4ConfigMethodLinkLocal,
+                ),
+                (Ipv4ConfigMethod::Manual, kSCValNetIPv4ConfigMethodManual),
+                (Ipv4ConfigMethod::Ppp, kSCValNetIPv4ConfigMethodPPP),
+            ];
+            for (method, const_str) in ipv4_methods {
+                assert_eq!(method.as_str(), wrap(const_str));
+            }
+            let ipv6_methods = [
+                (
+                    Ipv6ConfigMethod::Automatic,
+                    kSCValNetIPv6ConfigMethodAutomatic,
+                ),
+                (
+                    Ipv6ConfigMethod::LinkLocal,
+                    kSCValNetIPv6ConfigMethodLinkLocal,
+                ),
+                (Ipv6ConfigMethod::Manual, kSCValNetIPv6ConfigMethodManual),
+                (
+                    Ipv6ConfigMethod::RouterAdvertisement,
+                    kSCValNetIPv6ConfigMethodRouterAdvertisement,
+                ),
+                (Ipv6ConfigMethod::SixToFour, kSCValNetIPv6ConfigMethod6to4),
+            ];
+            for (method, const_str) in ipv6_methods {
+                assert_eq!(method.as_str(), wrap(const_str));
+            }
+        }
+    }
+}
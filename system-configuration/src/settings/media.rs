@@ -0,0 +1,228 @@
+use super::{ConfigError, DictionaryReader, DictionaryWriter, SettingsModel};
+use crate::plist::Dictionary;
+
+/// `kSCPropNetEthernetMediaSubType`.
+const MEDIA_SUBTYPE: &str = "MediaSubType";
+/// `kSCPropNetEthernetMediaOptions`.
+const MEDIA_OPTIONS: &str = "MediaOptions";
+
+/// A media subtype, such as `autoselect` or `1000baseT`, together with its options, such as
+/// `full-duplex`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MediaSelection {
+    /// `kSCPropNetEthernetMediaSubType`.
+    pub subtype: String,
+    /// `kSCPropNetEthernetMediaOptions`. The order of the options is not significant.
+    pub options: Vec<String>,
+}
+
+impl MediaSelection {
+    /// Returns a selection of the given subtype and options.
+    pub fn new<S: Into<String>>(subtype: S, options: impl IntoIterator<Item = S>) -> Self {
+        MediaSelection {
+            subtype: subtype.into(),
+            options: options.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns `true` if both selections have the same subtype and the same set of options.
+    pub fn matches(&self, other: &MediaSelection) -> bool {
+        let sorted = |options: &[String]| {
+            let mut options = options.to_vec();
+            options.sort();
+            options
+        };
+        self.subtype == other.subtype && sorted(&self.options) == sorted(&other.options)
+    }
+}
+
+impl SettingsModel for MediaSelection {
+    /// Parses a media dictionary. Keys other than the subtype and options are ignored.
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError> {
+        let mut reader = DictionaryReader::new(dictionary);
+        Ok(MediaSelection {
+            subtype: reader
+                .string(MEDIA_SUBTYPE)?
+                .ok_or_else(|| ConfigError::invalid_value(MEDIA_SUBTYPE, "is missing"))?,
+            options: reader.string_array(MEDIA_OPTIONS)?.unwrap_or_default(),
+        })
+    }
+
+    fn to_plist(&self) -> Dictionary {
+        let mut writer = DictionaryWriter::new(&Dictionary::new());
+        writer.string(MEDIA_SUBTYPE, &Some(self.subtype.clone()));
+        writer.string_array(MEDIA_OPTIONS, &Some(self.options.clone()));
+        writer.finish()
+    }
+}
+
+/// The media settings of an interface, as returned by
+/// [`SCNetworkInterface::media_options`].
+///
+/// [`SCNetworkInterface::media_options`]: crate::network_configuration::SCNetworkInterface::media_options
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MediaOptions {
+    /// The media the interface is configured to use.
+    pub current: Option<MediaSelection>,
+    /// The media the interface is actually using.
+    pub active: Option<MediaSelection>,
+    /// Every combination of subtype and options the interface supports.
+    pub available: Vec<MediaSelection>,
+}
+
+impl MediaOptions {
+    /// Returns the available subtypes, in the order the interface reports them.
+    ///
+    /// This mirrors [`SCNetworkInterface::media_subtypes`] for options captured earlier, such as
+    /// when validating a selection without querying the interface again.
+    ///
+    /// [`SCNetworkInterface::media_subtypes`]: crate::network_configuration::SCNetworkInterface::media_subtypes
+    pub fn subtypes(&self) -> Vec<&str> {
+        let mut subtypes = Vec::new();
+        for selection in &self.available {
+            if !subtypes.contains(&selection.subtype.as_str()) {
+                subtypes.push(selection.subtype.as_str());
+            }
+        }
+        subtypes
+    }
+
+    /// Returns the option combinations available for the given subtype.
+    ///
+    /// This mirrors [`SCNetworkInterface::media_subtype_options`] for options captured earlier.
+    ///
+    /// [`SCNetworkInterface::media_subtype_options`]: crate::network_configuration::SCNetworkInterface::media_subtype_options
+    pub fn subtype_options(&self, subtype: &str) -> Vec<&[String]> {
+        self.available
+            .iter()
+            .filter(|selection| selection.subtype == subtype)
+            .map(|selection| selection.options.as_slice())
+            .collect()
+    }
+
+    /// Checks that the subtype of `selection` is available and supports exactly the chosen
+    /// options.
+    pub fn validate(&self, selection: &MediaSelection) -> Result<(), ConfigError> {
+        if !self
+            .available
+            .iter()
+            .any(|available| available.subtype == selection.subtype)
+        {
+            return Err(ConfigError::invalid_value(
+                MEDIA_SUBTYPE,
+                format!("{:?} is not an available subtype", selection.subtype),
+            ));
+        }
+        if !self
+            .available
+            .iter()
+            .any(|available| available.matches(selection))
+        {
+            return Err(ConfigError::invalid_value(
+                MEDIA_OPTIONS,
+                format!(
+                    "{:?} are not available for subtype {:?}",
+                    selection.options, selection.subtype
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> MediaOptions {
+        MediaOptions {
+            current: Some(MediaSelection::new("autoselect", [])),
+            active: Some(MediaSelection::new("1000baseT", ["full-duplex"])),
+            available: vec![
+                MediaSelection::new("autoselect", []),
+                MediaSelection::new("100baseTX", ["half-duplex"]),
+                MediaSelection::new("100baseTX", ["full-duplex"]),
+                MediaSelection::new("100baseTX", ["full-duplex", "flow-control"]),
+                MediaSelection::new("1000baseT", ["full-duplex"]),
+            ],
+        }
+    }
+
+    #[test]
+    fn subtypes_and_options() {
+        let options = options();
+        assert_eq!(
+            options.subtypes(),
+            vec!["autoselect", "100baseTX", "1000baseT"]
+        );
+        assert_eq!(options.subtype_options("100baseTX").len(), 3);
+        assert_eq!(
+            options.subtype_options("autoselect"),
+            vec![&[] as &[String]]
+        );
+        assert!(options.subtype_options("10baseT").is_empty());
+    }
+
+    #[test]
+    fn validate_selection() {
+        let options = options();
+        assert_eq!(
+            options.validate(&MediaSelection::new("autoselect", [])),
+            Ok(())
+        );
+        assert_eq!(
+            options.validate(&MediaSelection::new(
+                "100baseTX",
+                ["flow-control", "full-duplex"]
+            )),
+            Ok(())
+        );
+        assert!(matches!(
+            options.validate(&MediaSelection::new("10baseT", [])),
+            Err(ConfigError::InvalidValue { key, .. }) if key == MEDIA_SUBTYPE
+        ));
+        assert!(matches!(
+            options.validate(&MediaSelection::new("1000baseT", ["half-duplex"])),
+            Err(ConfigError::InvalidValue { key, .. }) if key == MEDIA_OPTIONS
+        ));
+        assert!(options
+            .validate(&MediaSelection::new(
+                "100baseTX",
+                ["full-duplex", "full-duplex"]
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let selection = MediaSelection::new("100baseTX", ["full-duplex"]);
+        assert_eq!(
+            MediaSelection::from_plist(&selection.to_plist()),
+            Ok(selection)
+        );
+        assert!(MediaSelection::from_plist(&Dictionary::new()).is_err());
+    }
+
+    #[test]
+    fn matches_framework_constants() {
+        use crate::sys::schema_definitions::*;
+        use core_foundation::{base::TCFType, string::CFString};
+
+        let keys = unsafe {
+            [
+                (MEDIA_SUBTYPE, kSCPropNetEthernetMediaSubType),
+                (MEDIA_OPTIONS, kSCPropNetEthernetMediaOptions),
+            ]
+        };
+        for (key, const_str) in keys {
+            assert_eq!(
+                key,
+                unsafe { CFString::wrap_under_get_rule(const_str) }.to_string()
+            );
+        }
+    }
+}
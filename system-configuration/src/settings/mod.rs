@@ -0,0 +1,442 @@
+//! Typed models of the configuration dictionaries used by SystemConfiguration.
+//!
+//! The framework hands out configuration, for example from [`SCNetworkProtocol::configuration`]
+//! or [`SCDynamicStore::get`], as untyped dictionaries keyed by the `kSCProp*` schema definitions.
+//! The models in this module parse those dictionaries into structs, validate them and turn them
+//! back into dictionaries. Parsing and validation work on [`Dictionary`] and are pure Rust.
+//! Keys a model does not know about are preserved, so a dictionary survives a round trip through a
+//! model unchanged.
+//!
+//! [`SCNetworkProtocol::configuration`]: crate::network_configuration::SCNetworkProtocol::configuration
+//! [`SCDynamicStore::get`]: crate::dynamic_store::SCDynamicStore::get
+
+mod bond;
+mod dns;
+mod ip;
+mod media;
+mod proxy;
+
+pub use bond::*;
+pub use dns::*;
+pub use ip::*;
+pub use media::*;
+pub use proxy::*;
+
+use crate::plist::{dictionary_from_cf, dictionary_to_cf, Dictionary, PlistValue};
+use core_foundation::{base::CFType, dictionary::CFDictionary, string::CFString};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    net::Ipv4Addr,
+    str::FromStr,
+};
+
+/// A configuration model that can be converted to and from a configuration dictionary.
+pub trait SettingsModel: Sized {
+    /// Parses the model from a configuration dictionary. Keys the model does not know about must
+    /// be preserved, so that [`SettingsModel::to_plist`] gives back the same dictionary.
+    fn from_plist(dictionary: &Dictionary) -> Result<Self, ConfigError>;
+
+    /// Converts the model into a configuration dictionary.
+    fn to_plist(&self) -> Dictionary;
+
+    /// Checks that the configuration is consistent and can be applied.
+    fn validate(&self) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    /// Parses the model from a CoreFoundation configuration dictionary.
+    fn from_dictionary(dictionary: &CFDictionary<CFString, CFType>) -> Result<Self, ConfigError> {
+        let dictionary = dictionary_from_cf(dictionary).ok_or(ConfigError::NotAPropertyList)?;
+        Self::from_plist(&dictionary)
+    }
+
+    /// Converts the model into a CoreFoundation configuration dictionary.
+    fn to_dictionary(&self) -> CFDictionary<CFString, CFType> {
+        dictionary_to_cf(&self.to_plist())
+    }
+}
+
+/// Failure to parse or validate a configuration model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The dictionary contains values that are not property list types.
+    NotAPropertyList,
+    /// The value of a key has the wrong type.
+    InvalidType {
+        /// The key holding the value.
+        key: String,
+        /// The type the key should have.
+        expected: &'static str,
+        /// The type the value actually has.
+        found: &'static str,
+    },
+    /// The value of a key is of the right type, but is not valid.
+    InvalidValue {
+        /// The key holding the value.
+        key: String,
+        /// What is wrong with the value.
+        reason: String,
+    },
+}
+
+impl ConfigError {
+    pub(crate) fn invalid_value(key: &str, reason: impl Into<String>) -> Self {
+        ConfigError::InvalidValue {
+            key: key.to_owned(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotAPropertyList => {
+                write!(
+                    f,
+                    "Configuration contains values that are not property list types"
+                )
+            }
+            ConfigError::InvalidType {
+                key,
+                expected,
+                found,
+            } => write!(f, "Expected {} for {}, found {}", expected, key, found),
+            ConfigError::InvalidValue { key, reason } => {
+                write!(f, "Invalid value for {}: {}", key, reason)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Takes the known keys out of a configuration dictionary, leaving the unknown ones behind.
+pub(crate) struct DictionaryReader {
+    remaining: Dictionary,
+}
+
+impl DictionaryReader {
+    pub fn new(dictionary: &Dictionary) -> Self {
+        DictionaryReader {
+            remaining: dictionary.clone(),
+        }
+    }
+
+    fn take<T>(
+        &mut self,
+        key: &str,
+        expected: &'static str,
+        convert: impl FnOnce(&PlistValue) -> Option<T>,
+    ) -> Result<Option<T>, ConfigError> {
+        match self.remaining.remove(key) {
+            None => Ok(None),
+            Some(value) => match convert(&value) {
+                Some(converted) => Ok(Some(converted)),
+                None => Err(ConfigError::InvalidType {
+                    key: key.to_owned(),
+                    expected,
+                    found: value.type_name(),
+                }),
+            },
+        }
+    }
+
+    pub fn string(&mut self, key: &str) -> Result<Option<String>, ConfigError> {
+        self.take(key, "string", |value| value.as_str().map(str::to_owned))
+    }
+
+    pub fn integer(&mut self, key: &str) -> Result<Option<i64>, ConfigError> {
+        self.take(key, "integer", PlistValue::as_i64)
+    }
+
+    /// Reads an integer that has to fit in `T`.
+    pub fn bounded_integer<T: TryFrom<i64>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, ConfigError> {
+        match self.integer(key)? {
+            None => Ok(None),
+            Some(integer) => T::try_from(integer).map(Some).map_err(|_| {
+                ConfigError::invalid_value(key, format!("{} is out of range", integer))
+            }),
+        }
+    }
+
+    /// Reads a flag. The framework stores flags as the integer `0` or `1`, but any nonzero
+    /// integer and booleans are read as well, like the framework does.
+    pub fn flag(&mut self, key: &str) -> Result<Option<bool>, ConfigError> {
+        self.take(key, "flag", |value| match value {
+            PlistValue::Boolean(flag) => Some(*flag),
+            PlistValue::Integer(integer) => Some(*integer != 0),
+            _ => None,
+        })
+    }
+
+    pub fn string_array(&mut self, key: &str) -> Result<Option<Vec<String>>, ConfigError> {
+        self.take(key, "array of strings", |value| {
+            value
+                .as_array()?
+                .iter()
+                .map(|element| element.as_str().map(str::to_owned))
+                .collect()
+        })
+    }
+
+    pub fn integer_array(&mut self, key: &str) -> Result<Option<Vec<i64>>, ConfigError> {
+        self.take(key, "array of integers", |value| {
+            value.as_array()?.iter().map(PlistValue::as_i64).collect()
+        })
+    }
+
+    /// Reads an array of integers that all have to fit in `T`.
+    pub fn bounded_integer_array<T: TryFrom<i64>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<Vec<T>>, ConfigError> {
+        match self.integer_array(key)? {
+            None => Ok(None),
+            Some(integers) => integers
+                .into_iter()
+                .map(|integer| {
+                    T::try_from(integer).map_err(|_| {
+                        ConfigError::invalid_value(key, format!("{} is out of range", integer))
+                    })
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+        }
+    }
+
+    /// Reads a string and parses it with [`FromStr`].
+    pub fn parsed<T: FromStr>(&mut self, key: &str) -> Result<Option<T>, ConfigError> {
+        match self.string(key)? {
+            None => Ok(None),
+            Some(string) => parse(key, &string).map(Some),
+        }
+    }
+
+    /// Reads an array of strings and parses every element with [`FromStr`].
+    pub fn parsed_array<T: FromStr>(&mut self, key: &str) -> Result<Option<Vec<T>>, ConfigError> {
+        match self.string_array(key)? {
+            None => Ok(None),
+            Some(strings) => strings
+                .iter()
+                .map(|string| parse(key, string))
+                .collect::<Result<_, _>>()
+                .map(Some),
+        }
+    }
+
+    pub fn dictionary(&mut self, key: &str) -> Result<Option<Dictionary>, ConfigError> {
+        self.take(key, "dictionary", |value| value.as_dictionary().cloned())
+    }
+
+    /// Reads a nested dictionary and parses it as the model `T`.
+    pub fn model<T: SettingsModel>(&mut self, key: &str) -> Result<Option<T>, ConfigError> {
+        match self.dictionary(key)? {
+            None => Ok(None),
+            Some(dictionary) => T::from_plist(&dictionary).map(Some),
+        }
+    }
+
+    /// Returns the keys that were not read.
+    pub fn finish(self) -> Dictionary {
+        self.remaining
+    }
+}
+
+fn parse<T: FromStr>(key: &str, string: &str) -> Result<T, ConfigError> {
+    string
+        .parse()
+        .map_err(|_| ConfigError::invalid_value(key, format!("cannot parse {:?}", string)))
+}
+
+/// Builds a configuration dictionary, skipping the values that are not set.
+pub(crate) struct DictionaryWriter {
+    dictionary: Dictionary,
+}
+
+impl DictionaryWriter {
+    /// Starts from the unknown keys preserved by a model.
+    pub fn new(extra: &Dictionary) -> Self {
+        DictionaryWriter {
+            dictionary: extra.clone(),
+        }
+    }
+
+    pub fn value(&mut self, key: &str, value: Option<PlistValue>) {
+        if let Some(value) = value {
+            self.dictionary.insert(key.to_owned(), value);
+        }
+    }
+
+    pub fn string(&mut self, key: &str, value: &Option<String>) {
+        self.value(key, value.clone().map(PlistValue::String));
+    }
+
+    pub fn integer(&mut self, key: &str, value: Option<impl Into<i64>>) {
+        self.value(
+            key,
+            value.map(|integer| PlistValue::Integer(integer.into())),
+        );
+    }
+
+    pub fn flag(&mut self, key: &str, value: Option<bool>) {
+        self.integer(key, value.map(i64::from));
+    }
+
+    /// Writes a string, formatting the value with [`ToString`].
+    pub fn formatted<T: ToString>(&mut self, key: &str, value: &Option<T>) {
+        self.value(
+            key,
+            value
+                .as_ref()
+                .map(|value| PlistValue::String(value.to_string())),
+        );
+    }
+
+    /// Writes an array of strings, formatting every element with [`ToString`].
+    pub fn string_array<T: ToString>(&mut self, key: &str, value: &Option<Vec<T>>) {
+        self.value(
+            key,
+            value.as_ref().map(|elements| {
+                PlistValue::Array(
+                    elements
+                        .iter()
+                        .map(|element| PlistValue::String(element.to_string()))
+                        .collect(),
+                )
+            }),
+        );
+    }
+
+    pub fn integer_array<T: Copy + Into<i64>>(&mut self, key: &str, value: &Option<Vec<T>>) {
+        self.value(
+            key,
+            value.as_ref().map(|elements| {
+                PlistValue::Array(
+                    elements
+                        .iter()
+                        .map(|element| PlistValue::Integer((*element).into()))
+                        .collect(),
+                )
+            }),
+        );
+    }
+
+    /// Writes a nested dictionary holding the model.
+    pub fn model<T: SettingsModel>(&mut self, key: &str, value: &Option<T>) {
+        self.value(
+            key,
+            value
+                .as_ref()
+                .map(|model| PlistValue::Dictionary(model.to_plist())),
+        );
+    }
+
+    pub fn finish(self) -> Dictionary {
+        self.dictionary
+    }
+}
+
+/// Checks that `name` is a syntactically valid DNS domain name. A single trailing dot is allowed.
+pub(crate) fn validate_domain_name(key: &str, name: &str) -> Result<(), ConfigError> {
+    let invalid = |reason: &str| ConfigError::invalid_value(key, format!("{:?} {}", name, reason));
+    let trimmed = name.strip_suffix('.').unwrap_or(name);
+    if trimmed.is_empty() {
+        return Err(invalid("is empty"));
+    }
+    if trimmed.len() > 253 {
+        return Err(invalid("is longer than 253 characters"));
+    }
+    for label in trimmed.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid(
+                "has a label that is empty or longer than 63 characters",
+            ));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(invalid("has a label starting or ending with a hyphen"));
+        }
+        if !label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid("contains characters not allowed in domain names"));
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if `mask` is a valid subnet mask, with all its set bits leading.
+pub(crate) fn is_contiguous_mask(mask: Ipv4Addr) -> bool {
+    let bits = u32::from(mask);
+    bits.leading_ones() + bits.trailing_zeros() == 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_names() {
+        for name in [
+            "example.com",
+            "example.com.",
+            "a",
+            "_tcp.local",
+            "xn--bcher-kva.ch",
+        ] {
+            assert_eq!(validate_domain_name("Key", name), Ok(()), "{}", name);
+        }
+        let long_label = "a".repeat(64);
+        let long_name = ["a"; 128].join(".");
+        for name in [
+            "",
+            ".",
+            "a..b",
+            "-a.com",
+            "a-.com",
+            "a b",
+            "ä.com",
+            &long_label,
+            &long_name,
+        ] {
+            assert!(validate_domain_name("Key", name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn subnet_masks() {
+        for mask in ["255.255.255.0", "0.0.0.0", "255.255.255.255", "255.128.0.0"] {
+            assert!(is_contiguous_mask(mask.parse().unwrap()), "{}", mask);
+        }
+        for mask in ["255.0.255.0", "0.0.0.255", "255.255.255.253"] {
+            assert!(!is_contiguous_mask(mask.parse().unwrap()), "{}", mask);
+        }
+    }
+
+    #[test]
+    fn reader_reports_types() {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert("Port".to_owned(), PlistValue::Integer(70000));
+        dictionary.insert("Names".to_owned(), PlistValue::Array(vec![1.into()]));
+        let mut reader = DictionaryReader::new(&dictionary);
+        assert_eq!(
+            reader.bounded_integer::<u16>("Port"),
+            Err(ConfigError::invalid_value("Port", "70000 is out of range"))
+        );
+        assert_eq!(
+            reader.string_array("Names"),
+            Err(ConfigError::InvalidType {
+                key: "Names".to_owned(),
+                expected: "array of strings",
+                found: "array",
+            })
+        );
+        assert_eq!(reader.string("Missing"), Ok(None));
+        assert!(reader.finish().is_empty());
+    }
+}